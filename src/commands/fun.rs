@@ -2,7 +2,6 @@ pub(crate) use crate::types::{Context, Data, Error};
 use poise::serenity_prelude as serenity;
 use serenity::all::Mentionable;
 use crate::commands::general::{choose, remind, say};
-use crate::helpers::role_colours::{is_feature_enabled, set_feature_enabled};
 
 use crate::commands::moderation::{mod_check};
 
@@ -12,26 +11,32 @@ async fn sbstatus(
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap().get();
 
-    let config = ctx.data().starboard.get_starboard_config(guild_id).await?;
+    let boards = ctx.data().starboard.get_starboard_configs(guild_id).await?;
 
-    let embed = serenity::CreateEmbed::default()
+    let mut embed = serenity::CreateEmbed::default()
         .title("Starboard config:")
         .color(0x5865F2);
 
-    let embed = match config {
-        Some(config) => embed
-            .field("Enabled", if config.enabled {"✅"} else { "❌"}, true)
-            .field("Channel",
-                   config.starboard_channel_id
-                       .and_then(|id| id.parse::<u64>().ok())
-                       .map(|id| format!("<#{}>", id))
-                   .unwrap_or_else(|| "None".to_string()),
-                   true)
-            .field("Threshold", config.threshold.to_string(), true)
-            .field("Self Star", if config.self_star_allowed { "Allowed"} else {"Not Allowed"},
-            true),
-        None => embed.description("No starboard config provided"),
-    };
+    if boards.is_empty() {
+        embed = embed.description("No starboard config provided");
+    }
+
+    for config in boards {
+        let channel = config.starboard_channel_id
+            .and_then(|id| id.parse::<u64>().ok())
+            .map(|id| format!("<#{}>", id))
+            .unwrap_or_else(|| "None".to_string());
+
+        embed = embed.field(
+            format!("{} {}", config.star_emoji, config.name),
+            format!("Enabled: {}\nChannel: {}\nThreshold: {}\nSelf Star: {}",
+                    if config.enabled { "✅" } else { "❌" },
+                    channel,
+                    config.threshold,
+                    if config.self_star_allowed { "Allowed" } else { "Not Allowed" }),
+            true,
+        );
+    }
 
     let reply = poise::CreateReply::default()
         .embed(embed);
@@ -53,7 +58,7 @@ pub async fn rolecolours(
 
     let guild_id_u64 = guild_id.get();
 
-    set_feature_enabled(&ctx.data().db, guild_id_u64, enabled).await?;
+    ctx.data().role_colours.set_feature_enabled(guild_id_u64, enabled).await?;
     Ok(())
 }
 
@@ -62,7 +67,7 @@ pub async fn rcstatus(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
-    let is_enabled = is_feature_enabled(&ctx.data().db, guild_id.get()).await?;
+    let is_enabled = ctx.data().role_colours.is_feature_enabled(guild_id.get()).await?;
     let embed = serenity::CreateEmbed::default()
         .title("Role Colour Configuration")
         .color(0x800080)
@@ -84,7 +89,7 @@ pub async fn roleset(
 
     let guild_id = ctx.guild_id().ok_or(Error::from("guild not found"))?;
 
-    if !is_feature_enabled(&ctx.data().db, guild_id.get()).await? {
+    if !ctx.data().role_colours.is_feature_enabled(guild_id.get()).await? {
         return Err(format!("Feature not enabled").into());
     }
 
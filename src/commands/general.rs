@@ -3,10 +3,14 @@ use chrono::{DateTime, Utc};
 use poise::CreateReply;
 pub(crate) use crate::types::{Context, Data, Error};
 
-use rand::seq::{IndexedRandom, SliceRandom};
+use rand::seq::IndexedRandom;
 use rand::rng;
-use serenity::{};
+use regex::Regex;
 use crate::commands::moderation::mod_check;
+use crate::helpers::reminder::{MAX_ACTIVE_REMINDERS, MAX_REMINDER_HORIZON_DAYS};
+use crate::helpers::reminder_components::subscribe_button;
+use crate::structs::recurrence::Recurrence;
+use crate::structs::reminders::ReminderId;
 use crate::structs::time_parse::ParsedDuration;
 
 pub fn all_commands() -> Vec<poise::Command<Data, Error>> {
@@ -14,6 +18,8 @@ pub fn all_commands() -> Vec<poise::Command<Data, Error>> {
         say(),
         choose(),
         remind(),
+        remind_about(),
+        reminders(),
         avatar(),
         banner(),
     ]
@@ -40,10 +46,10 @@ pub async fn choose(
 
     if all_options.len() < 2 {
             ctx.say("Minimum of 2 options required").await?;
-            return Err(format!("Less than required arguments were given for command").into());
+            return Err("Less than required arguments were given for command".into());
     }
     let choice = all_options.choose(&mut rng()).unwrap();
-    ctx.say(format!("{}", choice)).await?;
+    ctx.say(choice.to_string()).await?;
     Ok(())
 }
 
@@ -70,57 +76,331 @@ pub async fn banner(
     Ok(())
 }
 
-#[poise::command(slash_command, prefix_command, dm_only = false)]
+/// Sets a DM reminder, as a slash command use `/remind set <when> <message>`
+#[poise::command(
+    slash_command,
+    prefix_command,
+    dm_only = false,
+    subcommands("remind_set", "remind_me", "remind_here", "remind_ping")
+)]
 pub async fn remind(
     ctx: Context<'_>,
+    #[description = "Delay (10m, 2h) or schedule (\"every day at 9am until 2026-12-31\", \"weekdays at 17:00 x10\")"]
     when: String,
     #[rest]
     message: String,
 ) -> Result<(), Error> {
-    let parsed = ParsedDuration::new(&when)
-        .map_err(|e| format!("Could not parse when: {:?}", e))?;
+    create_reminder(ctx, &when, &message, ReminderTarget::Dm, None).await
+}
 
-    let remind_at = parsed.until_datetime();
+#[derive(Debug, poise::Modal)]
+#[name = "Remind me about this"]
+struct RemindAboutModal {
+    #[name = "When"]
+    #[placeholder = "10m, 2h, every day at 9am"]
+    #[max_length = 100]
+    when: String,
+    #[name = "Note"]
+    #[placeholder = "Optional, what to remind you of"]
+    #[max_length = 1000]
+    note: Option<String>,
+}
 
-    let context  = match ctx {
-        poise::Context::Prefix(ctx) => {
-            ctx.msg.link()
-        }
-        _ => {""}.parse()?
+/// Message context menu, asks when in a modal and quotes the message when it fires
+#[poise::command(context_menu_command = "Remind me about this")]
+pub async fn remind_about(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "Message to be reminded about"]
+    message: serenity::Message,
+) -> Result<(), Error> {
+    let modal = match poise::execute_modal::<_, _, RemindAboutModal>(
+        ctx,
+        None,
+        Some(std::time::Duration::from_secs(300)),
+    ).await? {
+        Some(modal) => modal,
+        None => return Ok(()),
     };
 
-    let remind = crate::structs::reminders::Reminder::new(
-        ctx.author().id.to_string(),
+    let note = modal.note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty())
+        .unwrap_or_else(|| format!("Message from {}", message.author.name));
+
+    create_reminder(ctx.into(), modal.when.trim(), &note, ReminderTarget::Dm, Some(&message)).await
+}
+
+/// Reminds you in DMs, the same as the old `/remind <when> <message>`
+// slash commands with subcommands can't be run on their own, this keeps the bare form around
+#[poise::command(slash_command, prefix_command, rename = "set")]
+pub async fn remind_set(
+    ctx: Context<'_>,
+    #[description = "Delay (10m, 2h) or schedule (\"every day at 9am until 2026-12-31\", \"weekdays at 17:00 x10\")"]
+    when: String,
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    create_reminder(ctx, &when, &message, ReminderTarget::Dm, None).await
+}
+
+/// Reminds you in DMs
+#[poise::command(slash_command, prefix_command, rename = "me")]
+pub async fn remind_me(
+    ctx: Context<'_>,
+    #[description = "Delay (10m, 2h) or schedule (\"every day at 9am until 2026-12-31\", \"weekdays at 17:00 x10\")"]
+    when: String,
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    create_reminder(ctx, &when, &message, ReminderTarget::Dm, None).await
+}
+
+/// Posts the reminder in this channel instead of DMs
+#[poise::command(slash_command, prefix_command, guild_only, rename = "here")]
+pub async fn remind_here(
+    ctx: Context<'_>,
+    #[description = "Delay (10m, 2h) or schedule (\"every day at 9am until 2026-12-31\", \"weekdays at 17:00 x10\")"]
+    when: String,
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    create_reminder(ctx, &when, &message, ReminderTarget::Here, None).await
+}
+
+/// Posts the reminder in this channel and pings a role and/or users
+#[poise::command(slash_command, prefix_command, guild_only, rename = "ping", check = "mod_check")]
+pub async fn remind_ping(
+    ctx: Context<'_>,
+    #[description = "Role and user mentions to ping, quoted for prefix use"]
+    mentions: String,
+    #[description = "Delay (10m, 2h) or schedule (\"every day at 9am until 2026-12-31\", \"weekdays at 17:00 x10\")"]
+    when: String,
+    #[rest]
+    message: String,
+) -> Result<(), Error> {
+    let role_re = Regex::new(r"<@&(\d+)>")?;
+    let user_re = Regex::new(r"<@!?(\d+)>")?;
+
+    let roles: Vec<serenity::RoleId> = role_re.captures_iter(&mentions)
+        .filter_map(|c| c[1].parse().ok())
+        .collect();
+    let users: Vec<serenity::UserId> = user_re.captures_iter(&mentions)
+        .filter_map(|c| c[1].parse().ok())
+        .collect();
+
+    if roles.len() > 1 {
+        ctx.say("Only one role can be pinged per reminder").await?;
+        return Err("Multiple roles given to remind ping".into());
+    }
+    if roles.is_empty() && users.is_empty() {
+        ctx.say("Mention a role or at least one user to ping").await?;
+        return Err("No mentions given to remind ping".into());
+    }
+
+    create_reminder(ctx, &when, &message, ReminderTarget::Ping {
+        role_id: roles.first().copied(),
+        users,
+    }, None).await
+}
+
+enum ReminderTarget {
+    Dm,
+    Here,
+    Ping { role_id: Option<serenity::RoleId>, users: Vec<serenity::UserId> },
+}
+
+/// `about` is the message to quote on delivery, prefix commands fall back to the message they
+/// reply to. Without one the reminder still links back to where it was set.
+async fn create_reminder(
+    ctx: Context<'_>,
+    when: &str,
+    message: &str,
+    target: ReminderTarget,
+    about: Option<&serenity::Message>,
+) -> Result<(), Error> {
+    let recurrence = if Recurrence::is_recurring_spec(when) {
+        Some(Recurrence::parse(when).map_err(|e| format!("Could not parse schedule: {}", e))?)
+    } else {
+        None
+    };
+
+    let remind_at = match &recurrence {
+        Some(recurrence) => recurrence.first_occurrence(Utc::now())
+            .map_err(|e| format!("Could not parse schedule: {}", e))?,
+        None => ParsedDuration::new(when)
+            .map_err(|e| format!("Could not parse when: {:?}", e))?
+            .until_datetime(),
+    };
+
+    if remind_at > Utc::now() + chrono::Duration::days(MAX_REMINDER_HORIZON_DAYS) {
+        ctx.say(format!("Reminders can be set at most {} years ahead", MAX_REMINDER_HORIZON_DAYS / 365)).await?;
+        return Err("Reminder past the maximum horizon".into());
+    }
+
+    let active = ctx.data().reminders.count_active_reminders(ctx.author().id).await?;
+    if active >= MAX_ACTIVE_REMINDERS {
+        ctx.say(format!("You already have {} pending reminders, cancel some before adding more", active)).await?;
+        return Err("Reminder quota reached".into());
+    }
+
+    let (about, context) = match ctx {
+        poise::Context::Prefix(prefix) => (
+            about.or(prefix.msg.referenced_message.as_deref()),
+            Some(prefix.msg.id.link(prefix.msg.channel_id, ctx.guild_id())),
+        ),
+        // slash invocations have no message of their own, the confirmation gets linked below
+        poise::Context::Application(_) => (about, None),
+    };
+
+    let mut remind = crate::structs::reminders::Reminder::new(
+        ctx.author().id,
         remind_at,
         message.to_string(),
-        Option::from(context),
+        context,
+        Some(ctx.channel_id()),
     );
 
+    if let Some(about) = about {
+        remind = remind.about(about, ctx.guild_id());
+    }
+
+    if let Some(recurrence) = &recurrence {
+        remind = remind.recurring(recurrence);
+    }
+
+    let mut subscribers = Vec::new();
+    match target {
+        ReminderTarget::Dm => {}
+        ReminderTarget::Here => {
+            remind = remind.in_channel(ctx.channel_id());
+        }
+        ReminderTarget::Ping { role_id, users } => {
+            remind = remind.in_channel(ctx.channel_id());
+            if let Some(role_id) = role_id {
+                remind = remind.pinging_role(role_id);
+            }
+            subscribers = users;
+        }
+    }
+
     let reminder_id = ctx.data().reminders.add_reminder(&remind).await?;
+    for user_id in subscribers {
+        ctx.data().reminders.add_subscriber(reminder_id, user_id).await?;
+    }
+    ctx.data().reminder_scheduler.schedule(reminder_id, remind_at);
 
-    let until_datetime = || -> chrono::DateTime<Utc> {
-        Utc::now() + parsed.duration
+    let content = match &recurrence {
+        Some(recurrence) => format!("Reminder ID #{} repeats {}, first at {}",
+                                    reminder_id, recurrence, to_discord_timestamp(remind_at)),
+        None => format!("Reminder ID #{} set for {}", reminder_id, to_discord_timestamp(remind_at)),
     };
 
-    fn to_discord_timestamp(dt: DateTime<Utc>) -> String {
-        format!("<t:{}:F>", dt.timestamp())
+    let reply = ctx.send(CreateReply::default()
+        .content(content)
+        .components(vec![subscribe_button(reminder_id)])
+        .reply(true)
+    ).await?;
+
+    if remind.context_message_url.is_none() {
+        let confirmation = reply.message().await?;
+        let url = confirmation.id.link(confirmation.channel_id, ctx.guild_id());
+        ctx.data().reminders.set_context_url(reminder_id, &url).await?;
     }
 
-    ctx.send(CreateReply::default()
-        .content(format!("Reminder ID #{} set for {}", reminder_id, to_discord_timestamp(until_datetime()))
-        ).reply(true)
-    ).await?;
+    Ok(())
+}
 
+fn to_discord_timestamp(dt: DateTime<Utc>) -> String {
+    format!("<t:{}:F>", dt.timestamp())
+}
+
+#[poise::command(slash_command, prefix_command, subcommands("reminders_list", "reminders_cancel"), subcommand_required)]
+pub async fn reminders(_ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
-/*
-do later i cba
-#[poise::command(slash_command, prefix_command)]
-pub async fn reminders(
+
+#[poise::command(slash_command, prefix_command, rename = "list")]
+pub async fn reminders_list(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
-    let user_id = ctx.author().id.to_string();
-    let reminders = ctx.data().reminders.
+    let user_id = ctx.author().id;
+    let reminders = ctx.data().reminders.get_user_reminders(user_id).await?;
+
+    let embed = serenity::CreateEmbed::default()
+        .title("Your reminders")
+        .color(0x800080);
+
+    if reminders.is_empty() {
+        ctx.send(CreateReply::default()
+            .embed(embed.description("You have no pending reminders"))
+            .ephemeral(true)
+        ).await?;
+        return Ok(());
+    }
+
+    let lines = reminders
+        .iter()
+        .take(20)
+        .map(|reminder| {
+            let mut line = format!("**#{}** <t:{}:R> - {}",
+                                   reminder.id, reminder.fire_at().timestamp(), reminder.reminder_message);
+            if reminder.user_id != user_id {
+                line.push_str(&format!(" (from <@{}>)", reminder.user_id));
+            }
+            if let Some(channel_id) = &reminder.target_channel_id {
+                line.push_str(&format!(" in <#{}>", channel_id));
+            }
+            if reminder.failed {
+                line.push_str(&format!("\n-# ❌ delivery failed: {}", reminder.failure_reason.as_deref().unwrap_or("unknown error")));
+            } else if reminder.retry_at.is_some() && reminder.attempts == 0 {
+                line.push_str("\n-# ⏰ snoozed");
+            } else if let Some(retry_at) = reminder.retry_at {
+                line.push_str(&format!("\n-# ⚠️ retrying <t:{}:R> after {} failed attempts: {}",
+                                       retry_at.timestamp(), reminder.attempts,
+                                       reminder.failure_reason.as_deref().unwrap_or("unknown error")));
+            } else if let Some(reason) = &reminder.failure_reason {
+                line.push_str(&format!("\n-# ⚠️ last delivery failed: {}", reason));
+            }
+            if let Some(recurrence) = &reminder.recurrence {
+                line.push_str(&format!("\n-# repeats {}", recurrence));
+                if let Some(max) = reminder.max_occurrences {
+                    line.push_str(&format!(", {} of {} sent", reminder.occurrences, max));
+                }
+                if let Some(until) = reminder.recur_until {
+                    line.push_str(&format!(", until <t:{}:d>", until.timestamp()));
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = embed
+        .description(lines)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "Total: {} | Showing first {}",
+            reminders.len(),
+            reminders.len().min(20)
+        )));
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true)).await?;
+    Ok(())
 }
 
-*/
\ No newline at end of file
+#[poise::command(slash_command, prefix_command, rename = "cancel")]
+pub async fn reminders_cancel(
+    ctx: Context<'_>,
+    #[description = "Reminder ID from the reminders list"]
+    id: i64,
+) -> Result<(), Error> {
+    if !ctx.data().reminders.cancel_reminder(ReminderId(id), ctx.author().id).await? {
+        ctx.say(format!("No pending reminder #{} found", id)).await?;
+        return Err(format!("Reminder {} not found for cancel", id).into());
+    }
+
+    ctx.send(CreateReply::default()
+        .content(format!("Reminder #{} cancelled", id))
+        .reply(true)
+    ).await?;
+    Ok(())
+}
@@ -5,6 +5,7 @@ use poise::serenity_prelude as serenity;
 use sqlx::{Column, Row};
 
 use super::moderation::BOT_OWNER_ID;
+use crate::structs::time_parse::ParsedDuration;
 
 pub(crate) use crate::types::{Context, Data, Error};
 
@@ -13,6 +14,7 @@ pub fn all_commands() -> Vec<poise::Command<Data, Error>> {
     vec![
         exec(),
         authsetup(),
+        authkey(),
         authlist(),
         authstatus(),
         authdisable(),
@@ -153,7 +155,7 @@ async fn handle_update(ctx: &Context<'_>, query: &str) -> Result<u64, sqlx::Erro
 #[poise::command(prefix_command, slash_command, guild_only)]
 async fn authenticate(
     ctx: Context<'_>,
-    key_id: String,
+    key: String,
 ) -> Result<(), Error> {
     let guild_id = match ctx.guild_id() {
         Some(id) => id,
@@ -175,21 +177,46 @@ async fn authenticate(
         return Ok(());
     }
 
-    let config = match ctx.data().auth.get_config_by_key_id(&key_id).await? {
-        Some(config) if config.guild_id == guild_id_i64 => config,
+    let config = match ctx.data().auth.get_auth_config(guild_id_i64).await? {
+        Some(config) if config.enabled => config,
         Some(_) => {
             let embed = serenity::CreateEmbed::default()
-                .title("Invalid Key")
-                .description("This authentication key doesn't belong to this server")
+                .title("Authentication Disabled")
+                .description("Authentication is currently disabled in this server.")
                 .color(0xFF0000);
 
             ctx.send(poise::CreateReply::default().embed(embed)).await?;
             return Ok(());
         }
         None => {
+            let embed = serenity::CreateEmbed::default()
+                .title("Not Configured")
+                .description("This server doesn't have authentication configured.")
+                .color(0xFF0000);
+
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+            return Ok(());
+        }
+    };
+
+    // keys only ever match in the guild they were made for
+    let now = chrono::Utc::now();
+    let auth_key = ctx.data().auth.find_auth_key(guild_id_i64, &hash_key(&key)).await?;
+    let status = auth_key.as_ref().map(|auth_key| auth_key.status(now));
+
+    let problem = match status {
+        None | Some(KeyStatus::Revoked) => Some("The provided authentication key is invalid"),
+        Some(KeyStatus::Expired) => Some("This authentication key has expired"),
+        Some(KeyStatus::UsedUp) => Some("This authentication key has no uses left"),
+        Some(KeyStatus::Usable) => None,
+    };
+
+    let key_id = match (&auth_key, problem) {
+        (Some(auth_key), None) => auth_key.id,
+        _ => {
             let embed = serenity::CreateEmbed::default()
                 .title("Invalid Key")
-                .description("The provided authentication key is invalid")
+                .description(problem.unwrap_or("The provided authentication key is invalid"))
                 .color(0xFF0000);
 
             ctx.send(poise::CreateReply::default().embed(embed)).await?;
@@ -197,37 +224,43 @@ async fn authenticate(
         }
     };
 
-    if !config.enabled {
+    // nothing is spent on a member we can't look up
+    let mut member = match guild_id.member(ctx.http(), author_id).await {
+        Ok(member) => member,
+        Err(e) => {
+            let embed = serenity::CreateEmbed::default()
+                .title("Failed to Add Role")
+                .description(format!("Could not look you up in this server: {}", e))
+                .color(0xFF0000);
+
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+            return Ok(());
+        }
+    };
+
+    // the use is taken before the role is given so the last one can't be shared
+    if !ctx.data().auth.redeem_auth_key(key_id, now).await? {
         let embed = serenity::CreateEmbed::default()
-            .title("Authentication Disabled")
-            .description("Authentication is currently disabled in this server.")
+            .title("Invalid Key")
+            .description("This authentication key has no uses left")
             .color(0xFF0000);
 
         ctx.send(poise::CreateReply::default().embed(embed)).await?;
         return Ok(());
     }
 
-
     let role_id = serenity::RoleId::new(config.role_id as u64);
 
+    if let Err(e) = member.add_role(ctx.http(), &role_id).await {
+        ctx.data().auth.release_auth_key(key_id).await?;
 
-    match guild_id.member(ctx.http(), author_id).await {
-        Ok(mut member) => {
-            if let Err(e) = member.add_role(ctx.http(), &role_id).await {
-                    let embed = serenity::CreateEmbed::default()
-                    .title("Failed to Add Role")
-                    .description(format!("Could not add role: {}",
-                    e))
-                    .color(0xFF0000);
+        let embed = serenity::CreateEmbed::default()
+            .title("Failed to Add Role")
+            .description(format!("Could not add role: {}", e))
+            .color(0xFF0000);
 
-                    ctx.send(poise::CreateReply::default().embed(embed)).await?;
-                    return Ok(());
-            }
-        }
-        Err(e) => {
-            // format error here later im so tired
-            return Ok(())
-            }
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        return Ok(());
     }
 
 
@@ -247,7 +280,6 @@ async fn authenticate(
 #[poise::command(prefix_command, slash_command, guild_only)]
 async fn authsetup(
     ctx: Context<'_>,
-    key_id: String,
     role: serenity::RoleId,
     enabled: Option<bool>,
 ) -> Result<(), Error> {
@@ -259,7 +291,6 @@ async fn authsetup(
 
     ctx.data().auth.set_auth_config(
         guild_id,
-        key_id.clone(),
         i64::from(role),
         enabled,
     ).await?;
@@ -267,15 +298,152 @@ async fn authsetup(
     let embed = serenity::CreateEmbed::default()
         .title("Authentication Setup Complete")
         .color(0x00FF00)
-        .field("Key ID", format!("`{}`", key_id), true)
         .field("Role", role.mention().to_string(), true)
         .field("Status", if enabled { "✅ Enabled" } else { "❌ Disabled" }, true)
-        .field("Usage", format!("`{}authenticate [key-id]`", ctx.prefix()), false);
+        .field("Keys", format!("`{}authkey create` makes a key for members to use", ctx.prefix()), false)
+        .field("Usage", format!("`{}authenticate [key]`", ctx.prefix()), false);
 
     ctx.send(poise::CreateReply::default().embed(embed)).await?;
     Ok(())
 }
 
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    subcommands("authkey_create", "authkey_revoke", "authkey_list"),
+    subcommand_required
+)]
+async fn authkey(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Makes a new key, the plaintext is only ever shown here
+#[poise::command(prefix_command, slash_command, guild_only, rename = "create")]
+async fn authkey_create(
+    ctx: Context<'_>,
+    #[description = "How long the key works for (30m, 7d)"] expires: Option<String>,
+    #[description = "How many times the key can be used"] max_uses: Option<i64>,
+    #[description = "What the key is for"]
+    #[rest]
+    label: Option<String>,
+) -> Result<(), Error> {
+    if ctx.author().id != BOT_OWNER_ID {
+        return Err(format!("Bot management command ran by unprivileged user {}", ctx.author().name).into());
+    }
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+
+    let expires_at = match expires.as_deref().map(ParsedDuration::new) {
+        Some(Ok(parsed)) => Some(chrono::Utc::now() + parsed.as_chrono_duration()),
+        Some(Err(e)) => {
+            ctx.say(format!("Invalid expiry: {}", e)).await?;
+            return Ok(());
+        }
+        None => None,
+    };
+
+    if max_uses.is_some_and(|max_uses| max_uses < 1) {
+        ctx.say("Max uses has to be at least 1").await?;
+        return Ok(());
+    }
+
+    let plaintext = generate_key();
+    let mut key = AuthKey::new(guild_id, &plaintext, ctx.author().id.get() as i64);
+    key.label = label;
+    key.expires_at = expires_at;
+    key.max_uses = max_uses;
+    let key = ctx.data().auth.add_auth_key(&key).await?;
+
+    let embed = serenity::CreateEmbed::default()
+        .title("Authentication Key Created")
+        .description(format!("`{}`\nThis is the only time the key is shown, it can't be recovered later.", plaintext))
+        .color(0x00FF00)
+        .field("ID", key.id.to_string(), true)
+        .field("Expires", key.expires_at.map(|at| format!("<t:{}:R>", at.timestamp())).unwrap_or("Never".to_string()), true)
+        .field("Uses", key.max_uses.map(|max_uses| max_uses.to_string()).unwrap_or("Unlimited".to_string()), true);
+
+    // prefix replies are public so the key goes to dms instead
+    match ctx {
+        poise::Context::Application(_) => {
+            ctx.send(CreateReply::default().embed(embed).ephemeral(true)).await?;
+        }
+        poise::Context::Prefix(_) => {
+            ctx.author().direct_message(ctx.http(), serenity::CreateMessage::new().embed(embed)).await?;
+            ctx.say(format!("Key #{} created, check your DMs", key.id)).await?;
+        }
+    }
+    Ok(())
+}
+
+#[poise::command(prefix_command, slash_command, guild_only, rename = "revoke")]
+async fn authkey_revoke(
+    ctx: Context<'_>,
+    #[description = "ID from the key list"] id: i64,
+) -> Result<(), Error> {
+    if ctx.author().id != BOT_OWNER_ID {
+        return Err(format!("Bot management command ran by unprivileged user {}", ctx.author().name).into());
+    }
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+
+    if ctx.data().auth.revoke_auth_key(guild_id, id).await? {
+        ctx.say(format!("Key #{} revoked", id)).await?;
+    } else {
+        ctx.say(format!("No active key #{} in this server", id)).await?;
+    }
+    Ok(())
+}
+
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+async fn authkey_list(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    if ctx.author().id != BOT_OWNER_ID {
+        return Err(format!("Bot management command ran by unprivileged user {}", ctx.author().name).into());
+    }
+    let guild_id = ctx.guild_id().unwrap().get() as i64;
+
+    let keys = ctx.data().auth.get_auth_keys(guild_id).await?;
+    let embed = serenity::CreateEmbed::default()
+        .title("Authentication Keys")
+        .color(0x5865F2);
+
+    if keys.is_empty() {
+        ctx.send(CreateReply::default().embed(embed.description("This server has no keys."))).await?;
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    let key_list = keys
+        .iter()
+        .take(20)
+        .map(|key| {
+            let uses = match key.max_uses {
+                Some(max_uses) => format!("{}/{} uses", key.uses, max_uses),
+                None => format!("{} uses", key.uses),
+            };
+            let expiry = match key.expires_at {
+                Some(_) if key.status(now) == KeyStatus::Expired => "expired".to_string(),
+                Some(at) => format!("expires <t:{}:R>", at.timestamp()),
+                None => "no expiry".to_string(),
+            };
+            let label = key.label.as_deref().unwrap_or("no label");
+            format!("**#{}** {} - {}, {}, by <@{}>", key.id, label, uses, expiry, key.created_by)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = embed
+        .description(key_list)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "Total: {} keys | Showing first {}",
+            keys.len(),
+            keys.len().min(20)
+        )));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
 #[poise::command(prefix_command, slash_command, guild_only)]
 async fn authstatus(
     ctx: Context<'_>,
@@ -295,9 +463,15 @@ async fn authstatus(
 
             let user_count = user_count.len();
 
+            let now = chrono::Utc::now();
+            let usable_keys = ctx.data().auth.get_auth_keys(guild_id).await?
+                .iter()
+                .filter(|key| key.status(now) == KeyStatus::Usable)
+                .count();
+
             embed
                 .field("Enabled", if config.enabled { "✅" } else { "❌" }, true)
-                .field("Key ID", format!("`{}`", config.key_id), true)
+                .field("Usable Keys", usable_keys.to_string(), true)
                 .field("Role", format!("<@&{}>", config.role_id), true)
                 .field("Authenticated Users", user_count.to_string(), true)
         },
@@ -322,9 +496,8 @@ async fn authdisable(
     if let Some(config) = ctx.data().auth.delete_auth_config(guild_id).await? {
         let embed = serenity::CreateEmbed::default()
             .title("Authentication Disabled")
-            .description("Authentication has been disabled for this server.")
+            .description("Authentication has been disabled for this server and its keys removed.")
             .color(0xFFA500)
-            .field("Key ID", format!("`{}`", config.key_id), true)
             .field("Role", format!("<@&{}>", config.role_id), true);
 
         ctx.send(poise::CreateReply::default().embed(embed)).await?;
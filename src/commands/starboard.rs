@@ -0,0 +1,1254 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+pub(crate) use crate::types::{Context, Data, Error};
+
+use crate::commands::moderation::mod_check;
+use crate::helpers::starboard::{BlockMode, ChannelFilter, DeletePolicy, StarTier, StarboardConfig, StarTally, StatsScope, DEFAULT_BOARD};
+use crate::helpers::starboard_manager::{
+    accepts_source, refresh_starred_message, remove_starboard_entry, source_channel, update_starboard_message,
+};
+use crate::helpers::starboard_activity::MAX_SCALE;
+use crate::helpers::starboard_render::render_starboard_post;
+use crate::helpers::starboard_rescan::{load_boards, ChannelScan, RescanProgress};
+use crate::structs::time_parse::ParsedDuration;
+use crate::structs::starboard_message::StarEmoji;
+
+const MAX_THRESHOLD: i64 = 100;
+
+// stats commands fetch this many rows and page through them locally
+const STATS_LIMIT: i64 = 100;
+const PAGE_SIZE: usize = 10;
+
+const DEFAULT_RESCAN_WINDOW: &str = "7d";
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum TimeRange {
+    #[name = "day"]
+    Day,
+    #[name = "week"]
+    Week,
+    #[name = "month"]
+    Month,
+    #[name = "year"]
+    Year,
+    #[name = "all"]
+    All,
+}
+
+impl TimeRange {
+    fn days(self) -> Option<i64> {
+        match self {
+            TimeRange::Day => Some(1),
+            TimeRange::Week => Some(7),
+            TimeRange::Month => Some(30),
+            TimeRange::Year => Some(365),
+            TimeRange::All => None,
+        }
+    }
+}
+
+pub fn all_commands() -> Vec<poise::Command<Data, Error>> {
+    vec![
+        starboard(),
+    ]
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "starboard_setup",
+        "starboard_list",
+        "starboard_delete",
+        "starboard_enable",
+        "starboard_disable",
+        "starboard_settings",
+        "starboard_remove",
+        "starboard_restore",
+        "starboard_force",
+        "starboard_freeze",
+        "starboard_block",
+        "starboard_unblock",
+        "starboard_top",
+        "starboard_leaderboard",
+        "starboard_stats",
+        "starboard_random",
+        "starboard_rescan",
+    ),
+    subcommand_required
+)]
+pub async fn starboard(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+// discord caps a slash command at 25 subcommands, so board settings get a group of their own
+
+/// How a board picks and shows messages
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "config",
+    subcommands(
+        "starboard_channel",
+        "starboard_threshold",
+        "starboard_emoji",
+        "starboard_colour",
+        "starboard_selfstar",
+        "starboard_allow",
+        "starboard_deny",
+        "starboard_unlist",
+        "starboard_ignorebots",
+        "starboard_ondelete",
+        "starboard_maxage",
+        "starboard_activity",
+        "starboard_keepafter",
+        "starboard_tier",
+        "starboard_untier",
+        "starboard_fame",
+    ),
+    subcommand_required
+)]
+pub async fn starboard_settings(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+async fn autocomplete_board(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id.get(),
+        None => return Vec::new(),
+    };
+
+    ctx.data().starboard.get_starboard_configs(guild_id).await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|config| config.name)
+        .filter(|name| name.starts_with(partial))
+        .collect()
+}
+
+/// Creates a board, or reconfigures it if the name is taken, and enables it
+#[poise::command(slash_command, prefix_command, guild_only, rename = "setup", check = "mod_check")]
+pub async fn starboard_setup(
+    ctx: Context<'_>,
+    #[description = "Channel starred messages are posted in"]
+    channel: serenity::GuildChannel,
+    #[description = "Stars needed before a message is posted"]
+    threshold: Option<i64>,
+    #[description = "Emoji that counts as a star"]
+    emoji: Option<String>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let name = validate_board_name(ctx, board.as_deref().unwrap_or(DEFAULT_BOARD)).await?;
+
+    validate_channel(ctx, &channel).await?;
+
+    let mut config = ctx.data().starboard.get_starboard_config(guild_id, &name).await?
+        .unwrap_or_else(|| StarboardConfig::new(guild_id, &name));
+    config.starboard_channel_id = Some(channel.id.get().to_string());
+    config.enabled = true;
+
+    if let Some(threshold) = threshold {
+        validate_threshold(ctx, threshold).await?;
+        config.threshold = threshold;
+    }
+    if let Some(emoji) = emoji {
+        config.star_emoji = validate_emoji(ctx, &emoji).await?;
+    }
+
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("Board **{}** set up in <#{}>, {} {} needed",
+                    config.name, channel.id, config.threshold, config.star_emoji)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "list")]
+pub async fn starboard_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let boards = ctx.data().starboard.get_starboard_configs(guild_id).await?;
+
+    if boards.is_empty() {
+        ctx.say("No starboards set up, create one with `starboard setup`").await?;
+        return Ok(());
+    }
+
+    let mut lines = Vec::new();
+    for config in &boards {
+        lines.push(format!("{} **{}** → {} ({}+){}",
+                           config.star_emoji,
+                           config.name,
+                           config.starboard_channel_id.as_deref()
+                               .map(|id| format!("<#{}>", id))
+                               .unwrap_or_else(|| "no channel".to_string()),
+                           config.threshold,
+                           if config.enabled { "" } else { " *disabled*" }));
+
+        let filters = ctx.data().starboard.get_channel_filters(config.id).await?;
+        for allow in [true, false] {
+            let channels: Vec<String> = filters
+                .iter()
+                .filter(|filter| filter.allow == allow)
+                .map(|filter| format!("<#{}>", filter.channel_id))
+                .collect();
+            if !channels.is_empty() {
+                lines.push(format!("-# {}: {}", if allow { "allowed" } else { "denied" }, channels.join(" ")));
+            }
+        }
+        if config.ignore_bots {
+            lines.push("-# ignores bots".to_string());
+        }
+        if let Some(days) = config.max_age_days {
+            lines.push(format!("-# only messages from the last {} days", days));
+        }
+        if let Some(per_star) = config.activity_scale {
+            lines.push(format!("-# +1 star needed per {} messages an hour", per_star));
+        }
+        if let Some(days) = config.keep_after_days {
+            lines.push(format!("-# posts stay up after {} days", days));
+        }
+        let tiers = config.tiers();
+        if !tiers.is_empty() {
+            let tiers: Vec<String> = tiers.iter().map(|tier| format!("{} {}+", tier.emoji, tier.min_stars)).collect();
+            lines.push(format!("-# tiers: {}", tiers.join(" ")));
+        }
+        if let Some(fame_stars) = config.fame_stars {
+            lines.push(format!("-# {} at {} stars", fame_target(config), fame_stars));
+        }
+        if config.on_delete != DeletePolicy::Mark {
+            lines.push(format!("-# deleted messages: {}", poise::ChoiceParameter::name(&config.on_delete)));
+        }
+    }
+    let lines = lines.join("\n");
+
+    let embed = serenity::CreateEmbed::default()
+        .title("Starboards")
+        .color(0x5865F2)
+        .description(lines);
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Deletes a board and forgets what it tracked, posts already made are left alone
+#[poise::command(slash_command, prefix_command, guild_only, rename = "delete", check = "mod_check")]
+pub async fn starboard_delete(
+    ctx: Context<'_>,
+    #[description = "Board name"]
+    #[autocomplete = "autocomplete_board"]
+    board: String,
+) -> Result<(), Error> {
+    let config = existing_board(ctx, Some(board)).await?;
+    ctx.data().starboard.delete_starboard(config.id).await?;
+
+    ctx.say(format!("Board **{}** deleted", config.name)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "channel", check = "mod_check")]
+pub async fn starboard_channel(
+    ctx: Context<'_>,
+    #[description = "Channel starred messages are posted in"]
+    channel: serenity::GuildChannel,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let config = existing_board(ctx, board).await?;
+
+    validate_channel(ctx, &channel).await?;
+    ctx.data().starboard.set_starboard_channel(config.id, channel.id.get()).await?;
+
+    ctx.say(format!("**{}** channel set to <#{}>", config.name, channel.id)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "threshold", check = "mod_check")]
+pub async fn starboard_threshold(
+    ctx: Context<'_>,
+    #[description = "Stars needed before a message is posted"]
+    threshold: i64,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let config = existing_board(ctx, board).await?;
+
+    validate_threshold(ctx, threshold).await?;
+    ctx.data().starboard.set_starboard_threshold(config.id, threshold).await?;
+
+    ctx.say(format!("**{}** threshold set to {}", config.name, threshold)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "emoji", check = "mod_check")]
+pub async fn starboard_emoji(
+    ctx: Context<'_>,
+    #[description = "Emoji that counts as a star"]
+    emoji: String,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.star_emoji = validate_emoji(ctx, &emoji).await?;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("**{}** emoji set to {}", config.name, config.star_emoji)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "colour", aliases("color"), check = "mod_check")]
+pub async fn starboard_colour(
+    ctx: Context<'_>,
+    #[description = "Hex colour like #ffac33, or \"none\" for the default"]
+    colour: String,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.colour = validate_colour(ctx, &colour).await?;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(match config.colour {
+        Some(value) => format!("**{}** colour set to #{:06x}", config.name, value),
+        None => format!("**{}** colour reset", config.name),
+    }).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "selfstar", check = "mod_check")]
+pub async fn starboard_selfstar(
+    ctx: Context<'_>,
+    #[description = "Whether people can star their own messages"]
+    allowed: bool,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.self_star_allowed = allowed;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("Self stars are {} on **{}**", if allowed { "now allowed" } else { "no longer allowed" }, config.name)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "enable", check = "mod_check")]
+pub async fn starboard_enable(
+    ctx: Context<'_>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    set_enabled(ctx, board, true).await
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "disable", check = "mod_check")]
+pub async fn starboard_disable(
+    ctx: Context<'_>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    set_enabled(ctx, board, false).await
+}
+
+async fn set_enabled(ctx: Context<'_>, board: Option<String>, enabled: bool) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    if enabled && config.starboard_channel_id.is_none() {
+        ctx.say("Set a starboard channel first with `starboard config channel`").await?;
+        return Err("Starboard enabled without a channel".into());
+    }
+
+    config.enabled = enabled;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("**{}** {}", config.name, if enabled { "enabled" } else { "disabled" })).await?;
+    Ok(())
+}
+
+/// Only messages from allowed channels or categories reach the board once any are allowed
+#[poise::command(slash_command, prefix_command, guild_only, rename = "allow", check = "mod_check")]
+pub async fn starboard_allow(
+    ctx: Context<'_>,
+    #[description = "Channel or category"]
+    channel: serenity::GuildChannel,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    set_channel_filter(ctx, channel, board, true).await
+}
+
+/// Keeps messages from a channel or category off the board
+#[poise::command(slash_command, prefix_command, guild_only, rename = "deny", check = "mod_check")]
+pub async fn starboard_deny(
+    ctx: Context<'_>,
+    #[description = "Channel or category"]
+    channel: serenity::GuildChannel,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    set_channel_filter(ctx, channel, board, false).await
+}
+
+async fn set_channel_filter(
+    ctx: Context<'_>,
+    channel: serenity::GuildChannel,
+    board: Option<String>,
+    allow: bool,
+) -> Result<(), Error> {
+    let config = existing_board(ctx, board).await?;
+
+    if channel.guild_id != ctx.guild_id().unwrap() {
+        ctx.say("That channel isn't in this server").await?;
+        return Err(format!("Filter channel {} not in guild", channel.id).into());
+    }
+
+    ctx.data().starboard.set_channel_filter(config.id, channel.id.get(), allow).await?;
+
+    ctx.say(format!("<#{}> is now {} on **{}**", channel.id,
+                    if allow { "allowed" } else { "denied" }, config.name)).await?;
+    Ok(())
+}
+
+/// Takes a channel or category off the board's allow and deny lists
+#[poise::command(slash_command, prefix_command, guild_only, rename = "unlist", check = "mod_check")]
+pub async fn starboard_unlist(
+    ctx: Context<'_>,
+    #[description = "Channel or category"]
+    channel: serenity::GuildChannel,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let config = existing_board(ctx, board).await?;
+
+    if !ctx.data().starboard.remove_channel_filter(config.id, channel.id.get()).await? {
+        ctx.say(format!("<#{}> isn't listed on **{}**", channel.id, config.name)).await?;
+        return Err(format!("Channel {} not listed on board {}", channel.id, config.id).into());
+    }
+
+    ctx.say(format!("<#{}> removed from **{}**'s lists", channel.id, config.name)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "ignorebots", check = "mod_check")]
+pub async fn starboard_ignorebots(
+    ctx: Context<'_>,
+    #[description = "Whether messages from bots are kept off the board"]
+    ignore: bool,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.ignore_bots = ignore;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("Bot messages are {} on **{}**", if ignore { "now ignored" } else { "allowed again" }, config.name)).await?;
+    Ok(())
+}
+
+/// What happens to a post when its original message is deleted
+#[poise::command(slash_command, prefix_command, guild_only, rename = "ondelete", check = "mod_check")]
+pub async fn starboard_ondelete(
+    ctx: Context<'_>,
+    #[description = "Keep the post, mark it as deleted or remove it"]
+    policy: DeletePolicy,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.on_delete = policy;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    let outcome = match policy {
+        DeletePolicy::Keep => "are kept as they are",
+        DeletePolicy::Mark => "are marked as deleted",
+        DeletePolicy::Remove => "are removed",
+    };
+    ctx.say(format!("Posts on **{}** {} when the original message is deleted", config.name, outcome)).await?;
+    Ok(())
+}
+
+/// Stops old messages from being posted when someone stars them
+#[poise::command(slash_command, prefix_command, guild_only, rename = "maxage", check = "mod_check")]
+pub async fn starboard_maxage(
+    ctx: Context<'_>,
+    #[description = "Oldest message in days that can still be posted, leave out for no limit"]
+    days: Option<i64>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.max_age_days = validate_days(ctx, days).await?;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    match config.max_age_days {
+        Some(days) => ctx.say(format!("Only messages from the last {} days can go on **{}**", days, config.name)).await?,
+        None => ctx.say(format!("Messages of any age can go on **{}**", config.name)).await?,
+    };
+    Ok(())
+}
+
+/// Raises the threshold in busy channels
+#[poise::command(slash_command, prefix_command, guild_only, rename = "activity", check = "mod_check")]
+pub async fn starboard_activity(
+    ctx: Context<'_>,
+    #[description = "Messages an hour in a channel that add one star to the threshold, leave out to turn off"]
+    messages: Option<i64>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.activity_scale = messages.filter(|messages| *messages > 0);
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    match config.activity_scale {
+        Some(messages) => ctx.say(format!("**{}** needs an extra star for every {} messages sent in the last hour, up to {}",
+                                          config.name, messages, config.threshold * MAX_SCALE)).await?,
+        None => ctx.say(format!("**{}** always needs {} stars", config.name, config.threshold)).await?,
+    };
+    Ok(())
+}
+
+/// Keeps older posts up even if they lose stars
+#[poise::command(slash_command, prefix_command, guild_only, rename = "keepafter", check = "mod_check")]
+pub async fn starboard_keepafter(
+    ctx: Context<'_>,
+    #[description = "Days after which a post stays up whatever happens to its stars, leave out to turn off"]
+    days: Option<i64>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    config.keep_after_days = validate_days(ctx, days).await?;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    match config.keep_after_days {
+        Some(days) => ctx.say(format!("Posts on **{}** older than {} days are no longer taken down when unstarred", config.name, days)).await?,
+        None => ctx.say(format!("Posts on **{}** are taken down whenever they drop below the threshold", config.name)).await?,
+    };
+    Ok(())
+}
+
+/// Changes a post's footer emoji and colour once it reaches a number of stars
+#[poise::command(slash_command, prefix_command, guild_only, rename = "tier", check = "mod_check")]
+pub async fn starboard_tier(
+    ctx: Context<'_>,
+    #[description = "Stars a post needs to reach this tier"]
+    stars: i64,
+    #[description = "Emoji shown in the footer from this tier up"]
+    emoji: String,
+    #[description = "Hex colour like #ffac33, leave out to keep the board colour"]
+    colour: Option<String>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    validate_threshold(ctx, stars).await?;
+    let emoji = validate_emoji(ctx, &emoji).await?;
+    let colour = match colour {
+        Some(colour) => validate_colour(ctx, &colour).await?,
+        None => None,
+    };
+
+    config.set_tier(StarTier { min_stars: stars, emoji: emoji.clone(), colour });
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("Posts on **{}** with {}+ stars now show {}", config.name, stars, emoji)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "untier", check = "mod_check")]
+pub async fn starboard_untier(
+    ctx: Context<'_>,
+    #[description = "Star count the tier starts at"]
+    stars: i64,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    if !config.remove_tier(stars) {
+        ctx.say(format!("**{}** has no tier at {} stars", config.name, stars)).await?;
+        return Ok(());
+    }
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("Removed the {} star tier from **{}**", stars, config.name)).await?;
+    Ok(())
+}
+
+/// Pins posts or sends them to a hall of fame channel once they get enough stars
+#[poise::command(slash_command, prefix_command, guild_only, rename = "fame", check = "mod_check")]
+pub async fn starboard_fame(
+    ctx: Context<'_>,
+    #[description = "Stars a post needs, leave out to turn the hall of fame off"]
+    stars: Option<i64>,
+    #[description = "Channel posts are copied to"]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "Whether to pin the post in the starboard channel"]
+    pin: Option<bool>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mut config = existing_board(ctx, board).await?;
+
+    let Some(stars) = stars else {
+        config.fame_stars = None;
+        ctx.data().starboard.update_starboard_config(&config).await?;
+        ctx.say(format!("Hall of fame turned off for **{}**", config.name)).await?;
+        return Ok(());
+    };
+
+    validate_threshold(ctx, stars).await?;
+    if let Some(channel) = &channel {
+        validate_channel(ctx, channel).await?;
+    }
+
+    let pin = pin.unwrap_or(channel.is_none());
+    if channel.is_none() && !pin {
+        ctx.say("Give a channel, pin the posts, or both").await?;
+        return Err("Hall of fame without a channel or pins".into());
+    }
+
+    config.fame_stars = Some(stars);
+    config.fame_channel_id = channel.map(|channel| channel.id.get().to_string());
+    config.fame_pin = pin;
+    ctx.data().starboard.update_starboard_config(&config).await?;
+
+    ctx.say(format!("Posts on **{}** are {} once they reach {} stars", config.name, fame_target(&config), stars)).await?;
+    Ok(())
+}
+
+fn fame_target(config: &StarboardConfig) -> String {
+    match (&config.fame_channel_id, config.fame_pin) {
+        (Some(channel_id), true) => format!("pinned and sent to <#{}>", channel_id),
+        (Some(channel_id), false) => format!("sent to <#{}>", channel_id),
+        (None, _) => "pinned".to_string(),
+    }
+}
+
+/// Takes a message off the board and keeps it from being posted again
+#[poise::command(slash_command, prefix_command, guild_only, rename = "remove", check = "mod_check")]
+pub async fn starboard_remove(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the message or its starboard post"]
+    message: serenity::Message,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let (config, _, message_id) = moderation_target(ctx, &message, board).await?;
+    let data = ctx.data();
+
+    data.starboard.blacklist_message(config.id, message_id.get()).await?;
+
+    let starred = data.starboard.get_starred_message(config.id, message_id.get()).await?;
+    if let Some(starred) = &starred {
+        let _lock = data.starboard_state.lock_message(message_id.get()).await;
+        remove_starboard_entry(ctx.serenity_context(), data, starred).await?;
+    }
+
+    ctx.say(format!("{} **{}**, it won't be posted there again until `starboard restore`",
+                    if starred.is_some() { "Removed the message from" } else { "The message isn't on" },
+                    config.name)).await?;
+    Ok(())
+}
+
+/// Lets a removed message back on the board
+#[poise::command(slash_command, prefix_command, guild_only, rename = "restore", check = "mod_check")]
+pub async fn starboard_restore(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the message"]
+    message: serenity::Message,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let (config, _, message_id) = moderation_target(ctx, &message, board).await?;
+
+    if !ctx.data().starboard.unblacklist_message(config.id, message_id.get()).await? {
+        ctx.say(format!("That message wasn't removed from **{}**", config.name)).await?;
+        return Ok(());
+    }
+
+    ctx.say(format!("The message can be posted on **{}** again once it's starred", config.name)).await?;
+    Ok(())
+}
+
+/// Posts a message on the board now, whatever its stars, and keeps it up
+#[poise::command(slash_command, prefix_command, guild_only, rename = "force", check = "mod_check")]
+pub async fn starboard_force(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the message"]
+    message: serenity::Message,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let (config, channel_id, message_id) = moderation_target(ctx, &message, board).await?;
+    let data = ctx.data();
+
+    if config.starboard_channel_id.is_none() {
+        ctx.say(format!("**{}** has no channel, set one with `starboard config channel`", config.name)).await?;
+        return Err(format!("Starboard {} has no channel", config.name).into());
+    }
+
+    let original = match channel_id.message(ctx.http(), message_id).await {
+        Ok(original) => original,
+        Err(e) => {
+            ctx.say("Could not fetch the original message").await?;
+            return Err(e.into());
+        }
+    };
+
+    data.starboard.unblacklist_message(config.id, message_id.get()).await?;
+
+    {
+        let _lock = data.starboard_state.lock_message(message_id.get()).await;
+        let star_count = data.starboard.count_star_reactions(config.id, message_id.get()).await?;
+        update_starboard_message(ctx.serenity_context(), data, &config, &original, guild_id, star_count).await?;
+        data.starboard.set_forced(config.id, message_id.get(), true).await?;
+    }
+
+    ctx.say(format!("Posted on **{}**, it stays up whatever its stars until removed", config.name)).await?;
+    Ok(())
+}
+
+/// Stops a post's star count from changing
+#[poise::command(slash_command, prefix_command, guild_only, rename = "freeze", check = "mod_check")]
+pub async fn starboard_freeze(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the message or its starboard post"]
+    message: serenity::Message,
+    #[description = "False to unfreeze, defaults to true"]
+    frozen: Option<bool>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let frozen = frozen.unwrap_or(true);
+    let (config, _, message_id) = moderation_target(ctx, &message, board).await?;
+    let data = ctx.data();
+
+    if !data.starboard.set_frozen(config.id, message_id.get(), frozen).await? {
+        ctx.say(format!("That message isn't on **{}**", config.name)).await?;
+        return Err(format!("Message {} not on starboard {}", message_id, config.name).into());
+    }
+
+    // catch up on whatever changed while it was frozen
+    if !frozen {
+        refresh_starred_message(ctx.serenity_context(), data, &config, message_id.get()).await?;
+    }
+
+    ctx.say(format!("The post on **{}** is {}", config.name, if frozen { "frozen" } else { "counting stars again" })).await?;
+    Ok(())
+}
+
+/// Keeps a user's messages off the board, or stops their stars counting
+#[poise::command(slash_command, prefix_command, guild_only, rename = "block", check = "mod_check")]
+pub async fn starboard_block(
+    ctx: Context<'_>,
+    #[description = "User to block"]
+    user: serenity::User,
+    #[description = "What to block, defaults to both"]
+    mode: Option<BlockMode>,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let mode = mode.unwrap_or(BlockMode::Both);
+    let config = existing_board(ctx, board).await?;
+
+    ctx.data().starboard.block_user(config.id, user.id.get(), mode).await?;
+
+    let blocked = match mode {
+        BlockMode::Messages => "messages are kept off",
+        BlockMode::Stars => "stars don't count on",
+        BlockMode::Both => "messages and stars are kept off",
+    };
+    ctx.say(format!("{}'s {} **{}**", user.name, blocked, config.name)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "unblock", check = "mod_check")]
+pub async fn starboard_unblock(
+    ctx: Context<'_>,
+    #[description = "User to unblock"]
+    user: serenity::User,
+    #[description = "Board name, defaults to starboard"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let config = existing_board(ctx, board).await?;
+
+    if !ctx.data().starboard.unblock_user(config.id, user.id.get()).await? {
+        ctx.say(format!("{} isn't blocked on **{}**", user.name, config.name)).await?;
+        return Ok(());
+    }
+
+    ctx.say(format!("{} is no longer blocked on **{}**", user.name, config.name)).await?;
+    Ok(())
+}
+
+/// Most starred messages
+#[poise::command(slash_command, prefix_command, guild_only, rename = "top")]
+pub async fn starboard_top(
+    ctx: Context<'_>,
+    #[description = "Only count stars from this period"]
+    range: Option<TimeRange>,
+    #[description = "Only this board, defaults to all of them"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let scope = stats_scope(ctx, range, board).await?;
+    let boards = boards_by_id(ctx, guild_id).await?;
+
+    let lines = ctx.data().starboard.top_messages(&scope, STATS_LIMIT).await?
+        .iter()
+        .enumerate()
+        .map(|(rank, starred)| {
+            let emoji = boards.get(&starred.board_id).map(|config| config.star_emoji.as_str()).unwrap_or("⭐");
+            let author = starred.author_id.as_deref()
+                .map(|id| format!(" by <@{}>", id))
+                .unwrap_or_default();
+            format!("**{}.** {} {} [message]({}){} in <#{}>",
+                    rank + 1, starred.stars, emoji, starred.link(guild_id), author, starred.original_channel_id)
+        })
+        .collect();
+
+    send_pages(ctx, "Top starred messages", lines, "Nothing has been starred yet").await
+}
+
+/// Who gets and gives the most stars
+#[poise::command(slash_command, prefix_command, guild_only, rename = "leaderboard", aliases("lb"))]
+pub async fn starboard_leaderboard(
+    ctx: Context<'_>,
+    #[description = "Only count stars from this period"]
+    range: Option<TimeRange>,
+    #[description = "Only this board, defaults to all of them"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let scope = stats_scope(ctx, range, board).await?;
+
+    let receivers = ctx.data().starboard.top_receivers(&scope, STATS_LIMIT).await?;
+    let givers = ctx.data().starboard.top_givers(&scope, STATS_LIMIT).await?;
+
+    let tally_lines = |tallies: &[StarTally], unit: &str| -> Vec<String> {
+        tallies
+            .iter()
+            .enumerate()
+            .map(|(rank, tally)| format!("**{}.** <@{}> {} {} ({} messages)",
+                                         rank + 1, tally.user_id, tally.stars, unit, tally.messages))
+            .collect()
+    };
+
+    let mut lines = vec!["__Most stars received__".to_string()];
+    lines.extend(tally_lines(&receivers, "received"));
+    lines.push("__Most stars given__".to_string());
+    lines.extend(tally_lines(&givers, "given"));
+
+    if receivers.is_empty() && givers.is_empty() {
+        lines.clear();
+    }
+
+    send_pages(ctx, "Starboard leaderboard", lines, "Nothing has been starred yet").await
+}
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "stats")]
+pub async fn starboard_stats(
+    ctx: Context<'_>,
+    #[description = "User to look up, defaults to you"]
+    user: Option<serenity::User>,
+    #[description = "Only count stars from this period"]
+    range: Option<TimeRange>,
+    #[description = "Only this board, defaults to all of them"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+    let scope = stats_scope(ctx, range, board).await?;
+
+    let (received, posts, given) = ctx.data().starboard.user_stats(&scope, user.id.get()).await?;
+    let top = ctx.data().starboard.user_top_messages(&scope, user.id.get(), STATS_LIMIT).await?;
+
+    let mut lines = vec![
+        format!("**{}** stars received across **{}** starboard posts", received, posts),
+        format!("**{}** stars given", given),
+    ];
+    if !top.is_empty() {
+        lines.push("__Best messages__".to_string());
+        lines.extend(top.iter().enumerate().map(|(rank, starred)| {
+            format!("**{}.** {} stars, [message]({}) in <#{}>",
+                    rank + 1, starred.stars, starred.link(guild_id), starred.original_channel_id)
+        }));
+    }
+
+    send_pages(ctx, &format!("Starboard stats for {}", user.name), lines, "").await
+}
+
+/// Reposts a random message from the starboard
+#[poise::command(slash_command, prefix_command, guild_only, rename = "random")]
+pub async fn starboard_random(
+    ctx: Context<'_>,
+    #[description = "Only messages starred in this period"]
+    range: Option<TimeRange>,
+    #[description = "Only this board, defaults to all of them"]
+    #[autocomplete = "autocomplete_board"]
+    board: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let scope = stats_scope(ctx, range, board).await?;
+
+    let starred = match ctx.data().starboard.random_starred_message(&scope).await? {
+        Some(starred) => starred,
+        None => {
+            ctx.say("Nothing has been starred yet").await?;
+            return Ok(());
+        }
+    };
+
+    let boards = boards_by_id(ctx, guild_id).await?;
+    let config = boards.get(&starred.board_id).ok_or("Starred message has no board")?;
+
+    let channel_id: serenity::ChannelId = starred.original_channel_id.parse()?;
+    let message_id: serenity::MessageId = starred.original_message_id.parse()?;
+
+    match channel_id.message(ctx.http(), message_id).await {
+        Ok(message) => {
+            let post = render_starboard_post(&message, guild_id, starred.stars, config);
+            ctx.send(post.reply()).await?;
+        }
+        // the original is gone, the link still leads to the starboard copy if there is one
+        Err(_) => {
+            ctx.say(format!("{} {} stars, the original message was deleted: {}",
+                            config.star_emoji, starred.stars, starred.link(guild_id))).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Re-reads the real stars from discord and fixes up the starboard to match
+#[poise::command(slash_command, prefix_command, guild_only, rename = "rescan", check = "mod_check")]
+pub async fn starboard_rescan(
+    ctx: Context<'_>,
+    #[description = "Channel to scan, defaults to every channel the boards accept"]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "How far back to look, like 12h or 30d, defaults to 7d"]
+    since: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let since_input = since.unwrap_or_else(|| DEFAULT_RESCAN_WINDOW.to_string());
+
+    let window = match ParsedDuration::new(&since_input) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            ctx.say(format!("Could not parse since: {}", e)).await?;
+            return Err(format!("Invalid rescan window {}", since_input).into());
+        }
+    };
+    let since = Utc::now() - window.as_chrono_duration();
+
+    let boards = load_boards(ctx.data(), guild_id).await?;
+    if boards.is_empty() {
+        ctx.say("No enabled starboards to rescan").await?;
+        return Ok(());
+    }
+
+    let channels = match channel {
+        Some(channel) => vec![channel.id],
+        None => rescan_channels(ctx, &boards).await?,
+    };
+
+    let reply = ctx.say(format!("Rescanning {} channels from the last {}...",
+                                channels.len(), window.human_readable())).await?;
+
+    let serenity_ctx = ctx.serenity_context();
+    let mut progress = RescanProgress::default();
+
+    for (index, channel_id) in channels.iter().enumerate() {
+        let mut scan = ChannelScan::new(*channel_id, since);
+
+        while !scan.is_done() {
+            if let Err(e) = scan.next_page(serenity_ctx, ctx.data(), guild_id, &boards, &mut progress).await {
+                // usually missing access to the channel, move on to the next one
+                eprintln!("Error rescanning channel {}: {}", channel_id, e);
+                progress.failed += 1;
+                break;
+            }
+
+            reply.edit(ctx, poise::CreateReply::default().content(format!(
+                "Rescanning <#{}> ({}/{})\n{}", channel_id, index + 1, channels.len(), rescan_summary(&progress)
+            ))).await?;
+        }
+    }
+
+    reply.edit(ctx, poise::CreateReply::default().content(format!(
+        "Rescan done\n{}", rescan_summary(&progress)
+    ))).await?;
+    Ok(())
+}
+
+fn rescan_summary(progress: &RescanProgress) -> String {
+    format!("{} messages scanned, {} with stars, {} posted, {} removed, {} failed",
+            progress.scanned, progress.reconciled, progress.posted, progress.removed, progress.failed)
+}
+
+/// Text channels at least one board would take stars from
+async fn rescan_channels(
+    ctx: Context<'_>,
+    boards: &[(StarboardConfig, Vec<ChannelFilter>)],
+) -> Result<Vec<serenity::ChannelId>, Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let is_text = |channel: &serenity::GuildChannel| {
+        matches!(channel.kind, serenity::ChannelType::Text | serenity::ChannelType::News)
+    };
+
+    // the cache guard can't be held across an await, so copy the ids out first
+    let cached: Option<Vec<serenity::ChannelId>> = ctx.guild().map(|guild| {
+        guild.channels.values().filter(|channel| is_text(channel)).map(|channel| channel.id).collect()
+    });
+    let candidates = match cached {
+        Some(candidates) => candidates,
+        None => guild_id.channels(ctx.http()).await?
+            .into_values()
+            .filter(|channel| is_text(channel))
+            .map(|channel| channel.id)
+            .collect(),
+    };
+
+    let mut channels = Vec::new();
+    for channel_id in candidates {
+        let source = source_channel(ctx.serenity_context(), guild_id, channel_id).await?;
+        for (config, filters) in boards {
+            if accepts_source(ctx.serenity_context(), config, filters, &source).await {
+                channels.push(channel_id);
+                break;
+            }
+        }
+    }
+    Ok(channels)
+}
+
+async fn stats_scope(ctx: Context<'_>, range: Option<TimeRange>, board: Option<String>) -> Result<StatsScope, Error> {
+    let board_id = match board {
+        Some(name) => Some(existing_board(ctx, Some(name)).await?.id),
+        None => None,
+    };
+
+    // created_at columns hold sqlite CURRENT_TIMESTAMP text, which compares fine as a string
+    let since = range
+        .and_then(TimeRange::days)
+        .map(|days| (Utc::now() - chrono::Duration::days(days)).format("%Y-%m-%d %H:%M:%S").to_string());
+
+    Ok(StatsScope {
+        guild_id: ctx.guild_id().unwrap().get().to_string(),
+        board_id,
+        since,
+    })
+}
+
+async fn boards_by_id(ctx: Context<'_>, guild_id: u64) -> Result<HashMap<i64, StarboardConfig>, Error> {
+    Ok(ctx.data().starboard.get_starboard_configs(guild_id).await?
+        .into_iter()
+        .map(|config| (config.id, config))
+        .collect())
+}
+
+/// One embed when everything fits, otherwise poise's paginator
+async fn send_pages(ctx: Context<'_>, title: &str, lines: Vec<String>, empty: &str) -> Result<(), Error> {
+    if lines.is_empty() {
+        ctx.say(empty).await?;
+        return Ok(());
+    }
+
+    let pages: Vec<String> = lines
+        .chunks(PAGE_SIZE)
+        .map(|chunk| format!("**{}**\n{}", title, chunk.join("\n")))
+        .collect();
+
+    if pages.len() == 1 {
+        let embed = serenity::CreateEmbed::default()
+            .color(0x5865F2)
+            .description(&pages[0]);
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        return Ok(());
+    }
+
+    let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+    poise::builtins::paginate(ctx, &pages).await?;
+    Ok(())
+}
+
+/// Looks up a board by name, telling the user if it doesn't exist
+async fn existing_board(ctx: Context<'_>, board: Option<String>) -> Result<StarboardConfig, Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+    let name = board.unwrap_or_else(|| DEFAULT_BOARD.to_string()).to_lowercase();
+
+    match ctx.data().starboard.get_starboard_config(guild_id, &name).await? {
+        Some(config) => Ok(config),
+        None => {
+            ctx.say(format!("No board named **{}**, create it with `starboard setup`", name)).await?;
+            Err(format!("Starboard {} not found", name).into())
+        }
+    }
+}
+
+/// Board and original message a moderation command is about, a starboard post stands in
+/// for its original and picks the board itself
+async fn moderation_target(
+    ctx: Context<'_>,
+    message: &serenity::Message,
+    board: Option<String>,
+) -> Result<(StarboardConfig, serenity::ChannelId, serenity::MessageId), Error> {
+    let guild_id = ctx.guild_id().unwrap().get();
+
+    if let Some(starred) = ctx.data().starboard.get_starred_message_by_copy(message.id.get()).await? {
+        let config = ctx.data().starboard.get_starboard_config_by_id(starred.board_id).await?;
+        if let Some(config) = config.filter(|config| config.guild_id == guild_id.to_string()) {
+            return Ok((config, starred.original_channel_id.parse()?, starred.original_message_id.parse()?));
+        }
+    }
+
+    let config = existing_board(ctx, board).await?;
+    Ok((config, message.channel_id, message.id))
+}
+
+async fn validate_board_name(ctx: Context<'_>, name: &str) -> Result<String, Error> {
+    let name = name.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        ctx.say("Board names can only use letters, numbers, - and _ and be up to 32 long").await?;
+        return Err(format!("Invalid starboard name {}", name).into());
+    }
+    Ok(name)
+}
+
+/// Hex colour like #ffac33, "none" for no colour
+async fn validate_colour(ctx: Context<'_>, colour: &str) -> Result<Option<i64>, Error> {
+    if colour.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    match u32::from_str_radix(colour.trim_start_matches('#'), 16) {
+        Ok(value) if value <= 0xFFFFFF => Ok(Some(value as i64)),
+        _ => {
+            ctx.say(format!("Invalid hex colour: {}", colour)).await?;
+            Err(format!("Invalid starboard colour {}", colour).into())
+        }
+    }
+}
+
+/// `None` and 0 both turn the setting off
+async fn validate_days(ctx: Context<'_>, days: Option<i64>) -> Result<Option<i64>, Error> {
+    match days {
+        Some(days) if days < 0 => {
+            ctx.say("Days can't be negative").await?;
+            Err(format!("Invalid day count {}", days).into())
+        }
+        Some(0) | None => Ok(None),
+        Some(days) => Ok(Some(days)),
+    }
+}
+
+async fn validate_threshold(ctx: Context<'_>, threshold: i64) -> Result<(), Error> {
+    if !(1..=MAX_THRESHOLD).contains(&threshold) {
+        ctx.say(format!("Threshold has to be between 1 and {}", MAX_THRESHOLD)).await?;
+        return Err(format!("Invalid starboard threshold {}", threshold).into());
+    }
+    Ok(())
+}
+
+/// Accepts a unicode emoji or a custom one from this server, which the bot has to be able to see
+async fn validate_emoji(ctx: Context<'_>, emoji: &str) -> Result<String, Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let parsed = match StarEmoji::parse(emoji) {
+        Some(parsed) => parsed,
+        None => {
+            ctx.say("That doesn't look like an emoji").await?;
+            return Err(format!("Invalid starboard emoji {}", emoji).into());
+        }
+    };
+
+    let StarEmoji::Custom { id, .. } = parsed else {
+        return Ok(parsed.to_string());
+    };
+
+    // rebuilt from the guild's copy so the stored name and animated flag are current
+    match guild_id.emoji(ctx.http(), id).await {
+        Ok(guild_emoji) => Ok(StarEmoji::Custom {
+            id,
+            name: guild_emoji.name,
+            animated: guild_emoji.animated,
+        }.to_string()),
+        Err(_) => {
+            ctx.say("I can't see that emoji, custom emojis have to be from this server").await?;
+            Err(format!("Starboard emoji {} not found in guild {}", id, guild_id).into())
+        }
+    }
+}
+
+/// Makes sure the channel is a text channel in this guild the bot can actually post embeds in
+async fn validate_channel(ctx: Context<'_>, channel: &serenity::GuildChannel) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let problem = if channel.guild_id != guild_id {
+        Some("That channel isn't in this server".to_string())
+    } else if !matches!(channel.kind, serenity::ChannelType::Text | serenity::ChannelType::News) {
+        Some("The starboard has to be a text or announcement channel".to_string())
+    } else {
+        let guild = guild_id.to_partial_guild(ctx.http()).await?;
+        let bot = guild_id.member(ctx.http(), ctx.framework().bot_id).await?;
+        let permissions = guild.user_permissions_in(channel, &bot);
+
+        let needed = serenity::Permissions::VIEW_CHANNEL
+            | serenity::Permissions::SEND_MESSAGES
+            | serenity::Permissions::EMBED_LINKS;
+
+        (!permissions.contains(needed)).then(|| format!(
+            "I need View Channel, Send Messages and Embed Links in <#{}>", channel.id))
+    };
+
+    if let Some(problem) = problem {
+        ctx.say(problem).await?;
+        return Err(format!("Invalid starboard channel {}", channel.id).into());
+    }
+    Ok(())
+}
@@ -1,5 +1,36 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::{SqlitePool, Sqlite, QueryBuilder};
-use crate::structs::auth::{AuthConfig, AuthenticatedUser};
+use crate::helpers::schema::column_type;
+use crate::helpers::storage::AuthStorage;
+use crate::structs::auth::{hash_key, AuthConfig, AuthKey, AuthenticatedUser};
+use crate::types::Error;
+
+const CONFIGS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS auth_configs (
+        guild_id INTEGER PRIMARY KEY,
+        role_id INTEGER NOT NULL,
+        enabled BOOLEAN NOT NULL DEFAULT 1,
+        created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+"#;
+
+const KEYS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS auth_keys (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        key_hash TEXT NOT NULL,
+        label TEXT,
+        created_by INTEGER NOT NULL,
+        created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        expires_at DATETIME,
+        max_uses INTEGER,
+        uses INTEGER NOT NULL DEFAULT 0,
+        revoked BOOLEAN NOT NULL DEFAULT 0,
+        UNIQUE (guild_id, key_hash)
+    )
+"#;
 
 #[derive(Clone)]
 pub struct AuthDatabase {
@@ -12,20 +43,14 @@ impl AuthDatabase {
     }
 
     pub async fn create_tables(&self) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS auth_configs (
-                guild_id INTEGER PRIMARY KEY,
-                key_id TEXT NOT NULL,
-                role_id INTEGER NOT NULL,
-                enabled BOOLEAN NOT NULL DEFAULT 1,
-                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
-            )
-            "#
-        )
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(KEYS_TABLE).execute(&self.pool).await?;
+
+        // configs used to hold a single plaintext key
+        if column_type(&self.pool, "auth_configs", "key_id").await?.is_some() {
+            self.migrate_plaintext_keys().await?;
+        }
+
+        sqlx::query(CONFIGS_TABLE).execute(&self.pool).await?;
 
         sqlx::query(
             r#"
@@ -49,20 +74,48 @@ impl AuthDatabase {
             .execute(&self.pool)
             .await?;
 
+        Ok(())
+    }
+
+    /// Hashes each guild's old key into `auth_keys` and drops the column from the configs
+    async fn migrate_plaintext_keys(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let legacy: Vec<(i64, String)> = sqlx::query_as("SELECT guild_id, key_id FROM auth_configs")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        // nobody is on record as the creator of the old keys
+        for (guild_id, key_id) in legacy {
+            sqlx::query(
+                r#"INSERT OR IGNORE INTO auth_keys (guild_id, key_hash, label, created_by)
+                VALUES (?, ?, 'migrated', 0)"#
+            )
+                .bind(guild_id)
+                .bind(hash_key(&key_id))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        // the old key_id index goes with the legacy table
+        sqlx::query("ALTER TABLE auth_configs RENAME TO auth_configs_legacy").execute(&mut *tx).await?;
+        sqlx::query(CONFIGS_TABLE).execute(&mut *tx).await?;
         sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_auth_configs_key_id
-            ON auth_configs(key_id)
-            "#
+            r#"INSERT INTO auth_configs (guild_id, role_id, enabled, created_at, updated_at)
+            SELECT guild_id, role_id, enabled, created_at, updated_at FROM auth_configs_legacy"#
         )
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        sqlx::query("DROP TABLE auth_configs_legacy").execute(&mut *tx).await?;
 
-        Ok(())
+        tx.commit().await
     }
+}
 
-    pub async fn get_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, sqlx::Error> {
-        sqlx::query_as::<_, AuthConfig>(
+#[async_trait]
+impl AuthStorage for AuthDatabase {
+    async fn get_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, Error> {
+        Ok(sqlx::query_as::<_, AuthConfig>(
             r#"
             SELECT * FROM auth_configs
             WHERE guild_id = ?
@@ -70,24 +123,22 @@ impl AuthDatabase {
         )
             .bind(guild_id)
             .fetch_optional(&self.pool)
-            .await
+            .await?)
     }
 
-    pub async fn set_auth_config(
+    async fn set_auth_config(
         &self,
         guild_id: i64,
-        key_id: String,
         role_id: i64,
         enabled: bool,
-    ) -> Result<AuthConfig, sqlx::Error> {
+    ) -> Result<AuthConfig, Error> {
         let enabled_int = if enabled { 1 } else { 0 };
 
-        sqlx::query_as::<_, AuthConfig>(
+        Ok(sqlx::query_as::<_, AuthConfig>(
             r#"
-            INSERT INTO auth_configs (guild_id, key_id, role_id, enabled, created_at, updated_at)
-            VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+            INSERT INTO auth_configs (guild_id, role_id, enabled, created_at, updated_at)
+            VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
             ON CONFLICT(guild_id) DO UPDATE SET
-                key_id = excluded.key_id,
                 role_id = excluded.role_id,
                 enabled = excluded.enabled,
                 updated_at = CURRENT_TIMESTAMP
@@ -95,15 +146,21 @@ impl AuthDatabase {
             "#
         )
             .bind(guild_id)
-            .bind(key_id)
             .bind(role_id)
             .bind(enabled_int)
             .fetch_one(&self.pool)
-            .await
+            .await?)
     }
 
-    pub async fn delete_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, sqlx::Error> {
-        sqlx::query_as::<_, AuthConfig>(
+    async fn delete_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM auth_keys WHERE guild_id = ?")
+            .bind(guild_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let config = sqlx::query_as::<_, AuthConfig>(
             r#"
             DELETE FROM auth_configs
             WHERE guild_id = ?
@@ -111,11 +168,14 @@ impl AuthDatabase {
             "#
         )
             .bind(guild_id)
-            .fetch_optional(&self.pool)
-            .await
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(config)
     }
 
-    pub async fn is_user_authenticated(&self, user_id: i64, guild_id: i64) -> Result<bool, sqlx::Error> {
+    async fn is_user_authenticated(&self, user_id: i64, guild_id: i64) -> Result<bool, Error> {
         let result: Option<(i64,)> = sqlx::query_as(
             r#"
             SELECT 1 FROM authenticated_users
@@ -130,12 +190,12 @@ impl AuthDatabase {
         Ok(result.is_some())
     }
 
-    pub async fn add_authenticated_user(
+    async fn add_authenticated_user(
         &self,
         user_id: i64,
         guild_id: i64,
-    ) -> Result<AuthenticatedUser, sqlx::Error> {
-        sqlx::query_as::<_, AuthenticatedUser>(
+    ) -> Result<AuthenticatedUser, Error> {
+        Ok(sqlx::query_as::<_, AuthenticatedUser>(
             r#"
             INSERT INTO authenticated_users (user_id, guild_id, authenticated_at)
             VALUES (?, ?, CURRENT_TIMESTAMP)
@@ -146,11 +206,11 @@ impl AuthDatabase {
             .bind(user_id)
             .bind(guild_id)
             .fetch_one(&self.pool)
-            .await
+            .await?)
     }
 
-    pub async fn get_authenticated_users(&self, guild_id: i64) -> Result<Vec<AuthenticatedUser>, sqlx::Error> {
-        sqlx::query_as::<_, AuthenticatedUser>(
+    async fn get_authenticated_users(&self, guild_id: i64) -> Result<Vec<AuthenticatedUser>, Error> {
+        Ok(sqlx::query_as::<_, AuthenticatedUser>(
             r#"
             SELECT * FROM authenticated_users
             WHERE guild_id = ?
@@ -159,15 +219,15 @@ impl AuthDatabase {
         )
             .bind(guild_id)
             .fetch_all(&self.pool)
-            .await
+            .await?)
     }
 
-    pub async fn remove_authenticated_user(
+    async fn remove_authenticated_user(
         &self,
         user_id: i64,
         guild_id: i64,
-    ) -> Result<Option<AuthenticatedUser>, sqlx::Error> {
-        sqlx::query_as::<_, AuthenticatedUser>(
+    ) -> Result<Option<AuthenticatedUser>, Error> {
+        Ok(sqlx::query_as::<_, AuthenticatedUser>(
             r#"
             DELETE FROM authenticated_users
             WHERE user_id = ? AND guild_id = ?
@@ -177,18 +237,91 @@ impl AuthDatabase {
             .bind(user_id)
             .bind(guild_id)
             .fetch_optional(&self.pool)
-            .await
+            .await?)
     }
 
-    pub async fn get_config_by_key_id(&self, key_id: &str) -> Result<Option<AuthConfig>, sqlx::Error> {
-        sqlx::query_as::<_, AuthConfig>(
+    async fn add_auth_key(&self, key: &AuthKey) -> Result<AuthKey, Error> {
+        Ok(sqlx::query_as::<_, AuthKey>(
             r#"
-            SELECT * FROM auth_configs
-            WHERE key_id = ? AND enabled = 1
+            INSERT INTO auth_keys (guild_id, key_hash, label, created_by, expires_at, max_uses)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
             "#
         )
-            .bind(key_id)
+            .bind(key.guild_id)
+            .bind(&key.key_hash)
+            .bind(&key.label)
+            .bind(key.created_by)
+            .bind(key.expires_at)
+            .bind(key.max_uses)
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    async fn get_auth_keys(&self, guild_id: i64) -> Result<Vec<AuthKey>, Error> {
+        Ok(sqlx::query_as::<_, AuthKey>(
+            r#"
+            SELECT * FROM auth_keys
+            WHERE guild_id = ? AND revoked = 0
+            ORDER BY id DESC
+            "#
+        )
+            .bind(guild_id)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn find_auth_key(&self, guild_id: i64, key_hash: &str) -> Result<Option<AuthKey>, Error> {
+        Ok(sqlx::query_as::<_, AuthKey>(
+            r#"
+            SELECT * FROM auth_keys
+            WHERE guild_id = ? AND key_hash = ?
+            "#
+        )
+            .bind(guild_id)
+            .bind(key_hash)
             .fetch_optional(&self.pool)
-            .await
+            .await?)
     }
-}
\ No newline at end of file
+
+    async fn redeem_auth_key(&self, key_id: i64, now: DateTime<Utc>) -> Result<bool, Error> {
+        // checked in the update itself so two people can't both take the last use
+        let result = sqlx::query(
+            r#"
+            UPDATE auth_keys SET uses = uses + 1
+            WHERE id = ? AND revoked = 0
+                AND (expires_at IS NULL OR expires_at > ?)
+                AND (max_uses IS NULL OR uses < max_uses)
+            "#
+        )
+            .bind(key_id)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn release_auth_key(&self, key_id: i64) -> Result<(), Error> {
+        sqlx::query("UPDATE auth_keys SET uses = uses - 1 WHERE id = ? AND uses > 0")
+            .bind(key_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_auth_key(&self, guild_id: i64, key_id: i64) -> Result<bool, Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE auth_keys SET revoked = 1
+            WHERE id = ? AND guild_id = ? AND revoked = 0
+            "#
+        )
+            .bind(key_id)
+            .bind(guild_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
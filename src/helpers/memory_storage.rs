@@ -0,0 +1,855 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::UserId;
+use rand::rng;
+use rand::seq::IndexedRandom;
+
+use crate::helpers::starboard::{
+    BlockMode, ChannelFilter, StarSource, StarTally, StarboardConfig, StarredMessage, StatsScope,
+};
+use crate::helpers::storage::{AuthStorage, ReminderStorage, RoleColourStorage, StarboardStorage};
+use crate::structs::auth::{AuthConfig, AuthKey, AuthenticatedUser, KeyStatus};
+use crate::structs::reminders::{Reminder, ReminderId};
+use crate::types::Error;
+
+// same text format sqlite's CURRENT_TIMESTAMP uses, so `since` comparisons behave the same
+fn current_timestamp() -> String {
+    Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Reminders kept in memory, behaves like `ReminderStore` without a database
+#[derive(Default)]
+pub struct MemoryReminders {
+    state: Mutex<ReminderState>,
+}
+
+#[derive(Default)]
+struct ReminderState {
+    last_id: i64,
+    reminders: BTreeMap<ReminderId, Reminder>,
+    // in subscription order
+    subscribers: Vec<(ReminderId, UserId)>,
+}
+
+impl MemoryReminders {
+    fn update(&self, reminder_id: ReminderId, change: impl FnOnce(&mut Reminder)) {
+        if let Some(reminder) = self.state.lock().unwrap().reminders.get_mut(&reminder_id) {
+            change(reminder);
+        }
+    }
+}
+
+#[async_trait]
+impl ReminderStorage for MemoryReminders {
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<ReminderId, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.last_id += 1;
+        let id = ReminderId(state.last_id);
+
+        // only what the insert sets is kept, the rest starts at the table defaults
+        let stored = Reminder {
+            id,
+            sent: false,
+            created_at: Some(Utc::now()),
+            occurrences: 0,
+            attempts: 0,
+            retry_at: None,
+            failed: false,
+            failure_reason: None,
+            acknowledged: false,
+            ..reminder.clone()
+        };
+        state.reminders.insert(id, stored);
+        Ok(id)
+    }
+
+    async fn set_context_url(&self, reminder_id: ReminderId, url: &str) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| reminder.context_message_url = Some(url.to_string()));
+        Ok(())
+    }
+
+    async fn get_pending(&self) -> Result<Vec<Reminder>, Error> {
+        let state = self.state.lock().unwrap();
+        let mut pending: Vec<Reminder> = state.reminders.values()
+            .filter(|reminder| !reminder.sent && !reminder.failed)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|reminder| reminder.remind_at);
+        Ok(pending)
+    }
+
+    async fn get_reminder(&self, reminder_id: ReminderId) -> Result<Option<Reminder>, Error> {
+        Ok(self.state.lock().unwrap().reminders.get(&reminder_id).cloned())
+    }
+
+    async fn get_user_reminders(&self, user_id: UserId) -> Result<Vec<Reminder>, Error> {
+        let state = self.state.lock().unwrap();
+        let subscribed: HashSet<ReminderId> = state.subscribers.iter()
+            .filter(|(_, subscriber)| *subscriber == user_id)
+            .map(|(reminder_id, _)| *reminder_id)
+            .collect();
+
+        let mut reminders: Vec<Reminder> = state.reminders.values()
+            .filter(|reminder| !reminder.sent && (reminder.user_id == user_id || subscribed.contains(&reminder.id)))
+            .cloned()
+            .collect();
+        reminders.sort_by_key(|reminder| reminder.remind_at);
+        Ok(reminders)
+    }
+
+    async fn mark_due(&self, reminder_id: ReminderId) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.sent = true;
+            reminder.occurrences += 1;
+            reminder.attempts = 0;
+            reminder.retry_at = None;
+            reminder.failure_reason = None;
+        });
+        Ok(())
+    }
+
+    async fn reschedule(
+        &self,
+        reminder_id: ReminderId,
+        next_at: DateTime<Utc>,
+        failure_reason: Option<&str>,
+    ) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.remind_at = next_at;
+            reminder.occurrences += 1;
+            reminder.attempts = 0;
+            reminder.retry_at = None;
+            reminder.failure_reason = failure_reason.map(str::to_string);
+            reminder.acknowledged = false;
+        });
+        Ok(())
+    }
+
+    async fn record_failed_attempt(
+        &self,
+        reminder_id: ReminderId,
+        reason: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.attempts += 1;
+            reminder.failure_reason = Some(reason.to_string());
+            reminder.retry_at = Some(retry_at);
+        });
+        Ok(())
+    }
+
+    async fn mark_failed(&self, reminder_id: ReminderId, reason: &str) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.failed = true;
+            reminder.attempts += 1;
+            reminder.failure_reason = Some(reason.to_string());
+            reminder.retry_at = None;
+        });
+        Ok(())
+    }
+
+    async fn finish_snooze(&self, reminder_id: ReminderId, failure_reason: Option<&str>) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.attempts = 0;
+            reminder.retry_at = None;
+            reminder.failure_reason = failure_reason.map(str::to_string);
+        });
+        Ok(())
+    }
+
+    async fn snooze(&self, reminder_id: ReminderId, until: DateTime<Utc>) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.retry_at = Some(until);
+            reminder.sent = false;
+            reminder.failed = false;
+            reminder.attempts = 0;
+            reminder.acknowledged = false;
+        });
+        Ok(())
+    }
+
+    async fn acknowledge(&self, reminder_id: ReminderId) -> Result<(), Error> {
+        self.update(reminder_id, |reminder| {
+            reminder.acknowledged = true;
+            reminder.retry_at = None;
+            reminder.attempts = 0;
+            if reminder.recurrence.is_none() {
+                reminder.sent = true;
+            }
+        });
+        Ok(())
+    }
+
+    async fn cancel_reminder(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        let cancellable = state.reminders.get(&reminder_id)
+            .is_some_and(|reminder| reminder.user_id == user_id && !reminder.sent);
+        if !cancellable {
+            return Ok(false);
+        }
+
+        state.reminders.remove(&reminder_id);
+        state.subscribers.retain(|(subscribed, _)| *subscribed != reminder_id);
+        Ok(true)
+    }
+
+    async fn count_active_reminders(&self, user_id: UserId) -> Result<i64, Error> {
+        Ok(self.state.lock().unwrap().reminders.values()
+            .filter(|reminder| reminder.user_id == user_id && !reminder.sent && !reminder.failed)
+            .count() as i64)
+    }
+
+    async fn purge_sent(&self, before: DateTime<Utc>) -> Result<u64, Error> {
+        let mut state = self.state.lock().unwrap();
+        let purged: HashSet<ReminderId> = state.reminders.values()
+            .filter(|reminder| reminder.sent && reminder.remind_at < before)
+            .map(|reminder| reminder.id)
+            .collect();
+
+        state.reminders.retain(|reminder_id, _| !purged.contains(reminder_id));
+        state.subscribers.retain(|(reminder_id, _)| !purged.contains(reminder_id));
+        Ok(purged.len() as u64)
+    }
+
+    async fn add_subscriber(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.subscribers.contains(&(reminder_id, user_id)) {
+            return Ok(false);
+        }
+        state.subscribers.push((reminder_id, user_id));
+        Ok(true)
+    }
+
+    async fn remove_subscriber(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        let before = state.subscribers.len();
+        state.subscribers.retain(|subscription| *subscription != (reminder_id, user_id));
+        Ok(state.subscribers.len() < before)
+    }
+
+    async fn get_subscribers(&self, reminder_id: ReminderId) -> Result<Vec<UserId>, Error> {
+        Ok(self.state.lock().unwrap().subscribers.iter()
+            .filter(|(subscribed, _)| *subscribed == reminder_id)
+            .map(|(_, user_id)| *user_id)
+            .collect())
+    }
+}
+
+/// Auth configs, keys and verified users kept in memory
+#[derive(Default)]
+pub struct MemoryAuth {
+    state: Mutex<AuthState>,
+}
+
+#[derive(Default)]
+struct AuthState {
+    configs: HashMap<i64, AuthConfig>,
+    last_key_id: i64,
+    keys: Vec<AuthKey>,
+    users: Vec<AuthenticatedUser>,
+}
+
+#[async_trait]
+impl AuthStorage for MemoryAuth {
+    async fn get_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, Error> {
+        Ok(self.state.lock().unwrap().configs.get(&guild_id).cloned())
+    }
+
+    async fn set_auth_config(
+        &self,
+        guild_id: i64,
+        role_id: i64,
+        enabled: bool,
+    ) -> Result<AuthConfig, Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Utc::now();
+        let created_at = state.configs.get(&guild_id).map(|config| config.created_at).unwrap_or(now);
+
+        let config = AuthConfig { guild_id, role_id, enabled, created_at, updated_at: now };
+        state.configs.insert(guild_id, config.clone());
+        Ok(config)
+    }
+
+    async fn delete_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.keys.retain(|key| key.guild_id != guild_id);
+        Ok(state.configs.remove(&guild_id))
+    }
+
+    async fn is_user_authenticated(&self, user_id: i64, guild_id: i64) -> Result<bool, Error> {
+        Ok(self.state.lock().unwrap().users.iter()
+            .any(|user| user.user_id == user_id && user.guild_id == guild_id))
+    }
+
+    async fn add_authenticated_user(&self, user_id: i64, guild_id: i64) -> Result<AuthenticatedUser, Error> {
+        let mut state = self.state.lock().unwrap();
+        // the sqlite insert returns no row for a duplicate, which fails the same way
+        if state.users.iter().any(|user| user.user_id == user_id && user.guild_id == guild_id) {
+            return Err(format!("User {} is already authenticated in {}", user_id, guild_id).into());
+        }
+
+        let user = AuthenticatedUser { user_id, guild_id, authenticated_at: Utc::now() };
+        state.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn get_authenticated_users(&self, guild_id: i64) -> Result<Vec<AuthenticatedUser>, Error> {
+        let mut users: Vec<AuthenticatedUser> = self.state.lock().unwrap().users.iter()
+            .filter(|user| user.guild_id == guild_id)
+            .cloned()
+            .collect();
+        users.sort_by_key(|user| std::cmp::Reverse(user.authenticated_at));
+        Ok(users)
+    }
+
+    async fn remove_authenticated_user(&self, user_id: i64, guild_id: i64) -> Result<Option<AuthenticatedUser>, Error> {
+        let mut state = self.state.lock().unwrap();
+        let position = state.users.iter().position(|user| user.user_id == user_id && user.guild_id == guild_id);
+        Ok(position.map(|position| state.users.remove(position)))
+    }
+
+    async fn add_auth_key(&self, key: &AuthKey) -> Result<AuthKey, Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.keys.iter().any(|stored| stored.guild_id == key.guild_id && stored.key_hash == key.key_hash) {
+            return Err(format!("Key already exists in {}", key.guild_id).into());
+        }
+
+        state.last_key_id += 1;
+        let stored = AuthKey {
+            id: state.last_key_id,
+            created_at: Utc::now(),
+            uses: 0,
+            revoked: false,
+            ..key.clone()
+        };
+        state.keys.push(stored.clone());
+        Ok(stored)
+    }
+
+    async fn get_auth_keys(&self, guild_id: i64) -> Result<Vec<AuthKey>, Error> {
+        let mut keys: Vec<AuthKey> = self.state.lock().unwrap().keys.iter()
+            .filter(|key| key.guild_id == guild_id && !key.revoked)
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| std::cmp::Reverse(key.id));
+        Ok(keys)
+    }
+
+    async fn find_auth_key(&self, guild_id: i64, key_hash: &str) -> Result<Option<AuthKey>, Error> {
+        Ok(self.state.lock().unwrap().keys.iter()
+            .find(|key| key.guild_id == guild_id && key.key_hash == key_hash)
+            .cloned())
+    }
+
+    async fn redeem_auth_key(&self, key_id: i64, now: DateTime<Utc>) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        match state.keys.iter_mut().find(|key| key.id == key_id) {
+            Some(key) if key.status(now) == KeyStatus::Usable => {
+                key.uses += 1;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn release_auth_key(&self, key_id: i64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(key) = state.keys.iter_mut().find(|key| key.id == key_id && key.uses > 0) {
+            key.uses -= 1;
+        }
+        Ok(())
+    }
+
+    async fn revoke_auth_key(&self, guild_id: i64, key_id: i64) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        match state.keys.iter_mut().find(|key| key.id == key_id && key.guild_id == guild_id && !key.revoked) {
+            Some(key) => {
+                key.revoked = true;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+
+/// Guilds with role colours turned on
+#[derive(Default)]
+pub struct MemoryRoleColours {
+    enabled: Mutex<HashSet<u64>>,
+}
+
+#[async_trait]
+impl RoleColourStorage for MemoryRoleColours {
+    async fn set_feature_enabled(&self, guild_id: u64, enabled: bool) -> Result<(), Error> {
+        let mut guilds = self.enabled.lock().unwrap();
+        if enabled {
+            guilds.insert(guild_id);
+        } else {
+            guilds.remove(&guild_id);
+        }
+        Ok(())
+    }
+
+    async fn is_feature_enabled(&self, guild_id: u64) -> Result<bool, Error> {
+        Ok(self.enabled.lock().unwrap().contains(&guild_id))
+    }
+}
+
+/// Boards, posts and stars kept in memory, mirrors the starboard tables row for row
+#[derive(Default)]
+pub struct MemoryStarboard {
+    state: Mutex<BoardState>,
+}
+
+#[derive(Default)]
+struct BoardState {
+    last_board_id: i64,
+    last_message_id: i64,
+    configs: Vec<StarboardConfig>,
+    filters: Vec<(i64, ChannelFilter)>,
+    messages: Vec<StarredMessage>,
+    reactions: Vec<StoredReaction>,
+    blacklist: HashSet<(i64, u64)>,
+    blocks: HashMap<(i64, u64), BlockMode>,
+}
+
+struct StoredReaction {
+    board_id: i64,
+    message_id: u64,
+    channel_id: Option<u64>,
+    user_id: u64,
+    sources: i64,
+    created_at: String,
+}
+
+impl BoardState {
+    fn message_mut(&mut self, board_id: i64, message_id: u64) -> Option<&mut StarredMessage> {
+        self.messages.iter_mut()
+            .find(|message| message.board_id == board_id && message.original_message_id == message_id.to_string())
+    }
+
+    fn count(&self, board_id: i64, message_id: u64) -> i64 {
+        self.reactions.iter()
+            .filter(|reaction| reaction.board_id == board_id && reaction.message_id == message_id)
+            .count() as i64
+    }
+
+    fn add_star(&mut self, board_id: i64, message_id: u64, channel_id: u64, user_id: u64, source: StarSource) {
+        let existing = self.reactions.iter_mut().find(|reaction| {
+            reaction.board_id == board_id && reaction.message_id == message_id && reaction.user_id == user_id
+        });
+        match existing {
+            Some(reaction) => reaction.sources |= source.bit(),
+            None => self.reactions.push(StoredReaction {
+                board_id,
+                message_id,
+                channel_id: Some(channel_id),
+                user_id,
+                sources: source.bit(),
+                created_at: current_timestamp(),
+            }),
+        }
+    }
+
+    /// Same rules as `SCOPED_MESSAGES`, rows without a timestamp drop out once `since` is set
+    fn in_scope(&self, scope: &StatsScope, board_id: i64, created_at: Option<&str>) -> bool {
+        let in_guild = self.configs.iter().any(|config| config.id == board_id && config.guild_id == scope.guild_id);
+        let on_board = scope.board_id.is_none_or(|scoped| scoped == board_id);
+        let recent = match (&scope.since, created_at) {
+            (Some(since), Some(created_at)) => created_at >= since.as_str(),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        in_guild && on_board && recent
+    }
+
+    fn scoped_messages(&self, scope: &StatsScope) -> Vec<StarredMessage> {
+        self.messages.iter()
+            .filter(|message| self.in_scope(scope, message.board_id, message.created_at.as_deref()))
+            .cloned()
+            .collect()
+    }
+}
+
+fn best_first(messages: &mut [StarredMessage]) {
+    messages.sort_by(|a, b| b.stars.cmp(&a.stars).then_with(|| b.created_at.cmp(&a.created_at)));
+}
+
+#[async_trait]
+impl StarboardStorage for MemoryStarboard {
+    async fn get_starboard_configs(&self, guild_id: u64) -> Result<Vec<StarboardConfig>, Error> {
+        Ok(self.state.lock().unwrap().configs.iter()
+            .filter(|config| config.guild_id == guild_id.to_string())
+            .cloned()
+            .collect())
+    }
+
+    async fn get_starboard_config_by_id(&self, board_id: i64) -> Result<Option<StarboardConfig>, Error> {
+        Ok(self.state.lock().unwrap().configs.iter().find(|config| config.id == board_id).cloned())
+    }
+
+    async fn update_starboard_config(&self, config: &StarboardConfig) -> Result<i64, Error> {
+        let mut state = self.state.lock().unwrap();
+        let existing = state.configs.iter_mut()
+            .find(|stored| stored.guild_id == config.guild_id && stored.name == config.name);
+
+        if let Some(stored) = existing {
+            *stored = StarboardConfig { id: stored.id, ..config.clone() };
+            return Ok(stored.id);
+        }
+
+        state.last_board_id += 1;
+        let id = state.last_board_id;
+        state.configs.push(StarboardConfig { id, ..config.clone() });
+        Ok(id)
+    }
+
+    async fn set_starboard_channel(&self, board_id: i64, channel_id: u64) -> Result<(), Error> {
+        if let Some(config) = self.state.lock().unwrap().configs.iter_mut().find(|config| config.id == board_id) {
+            config.starboard_channel_id = Some(channel_id.to_string());
+        }
+        Ok(())
+    }
+
+    async fn set_starboard_threshold(&self, board_id: i64, threshold: i64) -> Result<(), Error> {
+        if let Some(config) = self.state.lock().unwrap().configs.iter_mut().find(|config| config.id == board_id) {
+            config.threshold = threshold;
+        }
+        Ok(())
+    }
+
+    async fn delete_starboard(&self, board_id: i64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.configs.retain(|config| config.id != board_id);
+        state.filters.retain(|(filtered, _)| *filtered != board_id);
+        state.messages.retain(|message| message.board_id != board_id);
+        state.reactions.retain(|reaction| reaction.board_id != board_id);
+        state.blacklist.retain(|(listed, _)| *listed != board_id);
+        state.blocks.retain(|(blocked, _), _| *blocked != board_id);
+        Ok(())
+    }
+
+    async fn get_channel_filters(&self, board_id: i64) -> Result<Vec<ChannelFilter>, Error> {
+        Ok(self.state.lock().unwrap().filters.iter()
+            .filter(|(filtered, _)| *filtered == board_id)
+            .map(|(_, filter)| filter.clone())
+            .collect())
+    }
+
+    async fn set_channel_filter(&self, board_id: i64, channel_id: u64, allow: bool) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let channel_id = channel_id.to_string();
+        match state.filters.iter_mut().find(|(filtered, filter)| *filtered == board_id && filter.channel_id == channel_id) {
+            Some((_, filter)) => filter.allow = allow,
+            None => state.filters.push((board_id, ChannelFilter { channel_id, allow })),
+        }
+        Ok(())
+    }
+
+    async fn remove_channel_filter(&self, board_id: i64, channel_id: u64) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        let before = state.filters.len();
+        state.filters.retain(|(filtered, filter)| !(*filtered == board_id && filter.channel_id == channel_id.to_string()));
+        Ok(state.filters.len() < before)
+    }
+
+    async fn get_starred_message(&self, board_id: i64, message_id: u64) -> Result<Option<StarredMessage>, Error> {
+        Ok(self.state.lock().unwrap().message_mut(board_id, message_id).map(|message| message.clone()))
+    }
+
+    async fn get_starred_message_by_copy(&self, starboard_message_id: u64) -> Result<Option<StarredMessage>, Error> {
+        let copy_id = starboard_message_id.to_string();
+        Ok(self.state.lock().unwrap().messages.iter()
+            .find(|message| message.starboard_message_id.as_ref() == Some(&copy_id))
+            .cloned())
+    }
+
+    async fn get_starred_messages(&self, message_id: u64) -> Result<Vec<StarredMessage>, Error> {
+        Ok(self.state.lock().unwrap().messages.iter()
+            .filter(|message| message.original_message_id == message_id.to_string())
+            .cloned()
+            .collect())
+    }
+
+    async fn add_starred_message(&self, message: &StarredMessage) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let duplicate = state.messages.iter()
+            .any(|stored| stored.board_id == message.board_id && stored.original_message_id == message.original_message_id);
+        if duplicate {
+            return Err(format!("Message {} is already on board {}", message.original_message_id, message.board_id).into());
+        }
+
+        state.last_message_id += 1;
+        let id = state.last_message_id;
+        // the insert doesn't set these, they start at the table defaults
+        state.messages.push(StarredMessage {
+            id,
+            created_at: Some(current_timestamp()),
+            frozen: false,
+            famed: false,
+            ..message.clone()
+        });
+        Ok(())
+    }
+
+    async fn update_starred_message(&self, message: &StarredMessage) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(stored) = state.messages.iter_mut()
+            .find(|stored| stored.board_id == message.board_id && stored.original_message_id == message.original_message_id)
+        {
+            stored.stars = message.stars;
+            stored.starboard_message_id = message.starboard_message_id.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete_starred_message(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        self.state.lock().unwrap().messages
+            .retain(|message| !(message.board_id == board_id && message.original_message_id == message_id.to_string()));
+        Ok(())
+    }
+
+    async fn set_frozen(&self, board_id: i64, message_id: u64, frozen: bool) -> Result<bool, Error> {
+        let mut state = self.state.lock().unwrap();
+        let message = state.message_mut(board_id, message_id);
+        let found = message.is_some();
+        if let Some(message) = message {
+            message.frozen = frozen;
+        }
+        Ok(found)
+    }
+
+    async fn set_famed(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        if let Some(message) = self.state.lock().unwrap().message_mut(board_id, message_id) {
+            message.famed = true;
+        }
+        Ok(())
+    }
+
+    async fn set_forced(&self, board_id: i64, message_id: u64, forced: bool) -> Result<(), Error> {
+        if let Some(message) = self.state.lock().unwrap().message_mut(board_id, message_id) {
+            message.forced = forced;
+        }
+        Ok(())
+    }
+
+    async fn is_blacklisted(&self, board_id: i64, message_id: u64) -> Result<bool, Error> {
+        Ok(self.state.lock().unwrap().blacklist.contains(&(board_id, message_id)))
+    }
+
+    async fn blacklist_message(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        self.state.lock().unwrap().blacklist.insert((board_id, message_id));
+        Ok(())
+    }
+
+    async fn unblacklist_message(&self, board_id: i64, message_id: u64) -> Result<bool, Error> {
+        Ok(self.state.lock().unwrap().blacklist.remove(&(board_id, message_id)))
+    }
+
+    async fn get_block(&self, board_id: i64, user_id: u64) -> Result<Option<BlockMode>, Error> {
+        Ok(self.state.lock().unwrap().blocks.get(&(board_id, user_id)).copied())
+    }
+
+    async fn block_user(&self, board_id: i64, user_id: u64, mode: BlockMode) -> Result<(), Error> {
+        self.state.lock().unwrap().blocks.insert((board_id, user_id), mode);
+        Ok(())
+    }
+
+    async fn unblock_user(&self, board_id: i64, user_id: u64) -> Result<bool, Error> {
+        Ok(self.state.lock().unwrap().blocks.remove(&(board_id, user_id)).is_some())
+    }
+
+    async fn star_blocked_users(&self, board_id: i64) -> Result<Vec<u64>, Error> {
+        Ok(self.state.lock().unwrap().blocks.iter()
+            .filter(|((blocked, _), mode)| *blocked == board_id && mode.blocks_stars())
+            .map(|((_, user_id), _)| *user_id)
+            .collect())
+    }
+
+    async fn add_star_reaction(
+        &self,
+        board_id: i64,
+        message_id: u64,
+        channel_id: u64,
+        user_id: u64,
+        source: StarSource,
+    ) -> Result<i64, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.add_star(board_id, message_id, channel_id, user_id, source);
+        Ok(state.count(board_id, message_id))
+    }
+
+    async fn remove_star_reaction(&self, board_id: i64, message_id: u64, user_id: u64, source: StarSource) -> Result<i64, Error> {
+        let mut state = self.state.lock().unwrap();
+        for reaction in &mut state.reactions {
+            if reaction.board_id == board_id && reaction.message_id == message_id && reaction.user_id == user_id {
+                reaction.sources &= !source.bit();
+            }
+        }
+        state.reactions.retain(|reaction| reaction.sources != 0);
+        Ok(state.count(board_id, message_id))
+    }
+
+    async fn clear_copy_reactions(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        for reaction in &mut state.reactions {
+            if reaction.board_id == board_id && reaction.message_id == message_id {
+                reaction.sources &= !StarSource::Copy.bit();
+            }
+        }
+        state.reactions.retain(|reaction| reaction.sources != 0);
+        Ok(())
+    }
+
+    async fn replace_star_reactions(
+        &self,
+        board_id: i64,
+        message_id: u64,
+        channel_id: u64,
+        original: &[u64],
+        copy: &[u64],
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.reactions.retain(|reaction| !(reaction.board_id == board_id && reaction.message_id == message_id));
+
+        let sources = original.iter().map(|user_id| (user_id, StarSource::Original))
+            .chain(copy.iter().map(|user_id| (user_id, StarSource::Copy)));
+        for (user_id, source) in sources {
+            state.add_star(board_id, message_id, channel_id, *user_id, source);
+        }
+        Ok(())
+    }
+
+    async fn boards_tracking(&self, message_id: u64) -> Result<Vec<i64>, Error> {
+        let state = self.state.lock().unwrap();
+        let boards: BTreeSet<i64> = state.reactions.iter()
+            .filter(|reaction| reaction.message_id == message_id)
+            .map(|reaction| reaction.board_id)
+            .chain(state.messages.iter()
+                .filter(|message| message.original_message_id == message_id.to_string())
+                .map(|message| message.board_id))
+            .collect();
+        Ok(boards.into_iter().collect())
+    }
+
+    async fn tracked_messages_since(&self, since: &str) -> Result<Vec<(String, String, String)>, Error> {
+        let state = self.state.lock().unwrap();
+        let reactions = state.reactions.iter()
+            .filter(|reaction| reaction.created_at.as_str() >= since)
+            .filter_map(|reaction| Some((reaction.board_id, reaction.channel_id?.to_string(), reaction.message_id.to_string())));
+        let messages = state.messages.iter()
+            .filter(|message| message.created_at.as_deref().is_some_and(|created_at| created_at >= since))
+            .map(|message| (message.board_id, message.original_channel_id.clone(), message.original_message_id.clone()));
+
+        let tracked: BTreeSet<(String, String, String)> = reactions.chain(messages)
+            .filter_map(|(board_id, channel_id, message_id)| {
+                let config = state.configs.iter().find(|config| config.id == board_id && config.enabled)?;
+                Some((config.guild_id.clone(), channel_id, message_id))
+            })
+            .collect();
+        Ok(tracked.into_iter().collect())
+    }
+
+    async fn clear_star_reactions(&self, message_id: u64) -> Result<(), Error> {
+        self.state.lock().unwrap().reactions.retain(|reaction| reaction.message_id != message_id);
+        Ok(())
+    }
+
+    async fn forget_message(&self, message_id: u64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.reactions.retain(|reaction| reaction.message_id != message_id);
+        state.messages.retain(|message| message.original_message_id != message_id.to_string());
+        Ok(())
+    }
+
+    async fn count_star_reactions(&self, board_id: i64, message_id: u64) -> Result<i64, Error> {
+        Ok(self.state.lock().unwrap().count(board_id, message_id))
+    }
+
+    async fn top_messages(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarredMessage>, Error> {
+        let mut messages = self.state.lock().unwrap().scoped_messages(scope);
+        best_first(&mut messages);
+        messages.truncate(limit.max(0) as usize);
+        Ok(messages)
+    }
+
+    async fn top_receivers(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarTally>, Error> {
+        let mut tallies: HashMap<String, StarTally> = HashMap::new();
+        for message in self.state.lock().unwrap().scoped_messages(scope) {
+            let Some(author_id) = message.author_id else {
+                continue;
+            };
+            let tally = tallies.entry(author_id.clone())
+                .or_insert_with(|| StarTally { user_id: author_id, stars: 0, messages: 0 });
+            tally.stars += message.stars;
+            tally.messages += 1;
+        }
+
+        let mut tallies: Vec<StarTally> = tallies.into_values().collect();
+        tallies.sort_by_key(|tally| std::cmp::Reverse(tally.stars));
+        tallies.truncate(limit.max(0) as usize);
+        Ok(tallies)
+    }
+
+    async fn top_givers(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarTally>, Error> {
+        let state = self.state.lock().unwrap();
+        let mut given: HashMap<u64, (i64, HashSet<u64>)> = HashMap::new();
+        for reaction in &state.reactions {
+            if state.in_scope(scope, reaction.board_id, Some(&reaction.created_at)) {
+                let (stars, messages) = given.entry(reaction.user_id).or_default();
+                *stars += 1;
+                messages.insert(reaction.message_id);
+            }
+        }
+
+        let mut tallies: Vec<StarTally> = given.into_iter()
+            .map(|(user_id, (stars, messages))| StarTally {
+                user_id: user_id.to_string(),
+                stars,
+                messages: messages.len() as i64,
+            })
+            .collect();
+        tallies.sort_by_key(|tally| std::cmp::Reverse(tally.stars));
+        tallies.truncate(limit.max(0) as usize);
+        Ok(tallies)
+    }
+
+    async fn user_stats(&self, scope: &StatsScope, user_id: u64) -> Result<(i64, i64, i64), Error> {
+        let state = self.state.lock().unwrap();
+        let author_id = user_id.to_string();
+
+        let posts: Vec<StarredMessage> = state.scoped_messages(scope).into_iter()
+            .filter(|message| message.author_id.as_ref() == Some(&author_id))
+            .collect();
+        let received = posts.iter().map(|message| message.stars).sum();
+
+        let given = state.reactions.iter()
+            .filter(|reaction| reaction.user_id == user_id)
+            .filter(|reaction| state.in_scope(scope, reaction.board_id, Some(&reaction.created_at)))
+            .count() as i64;
+
+        Ok((received, posts.len() as i64, given))
+    }
+
+    async fn user_top_messages(&self, scope: &StatsScope, user_id: u64, limit: i64) -> Result<Vec<StarredMessage>, Error> {
+        let author_id = user_id.to_string();
+        let mut messages: Vec<StarredMessage> = self.state.lock().unwrap().scoped_messages(scope).into_iter()
+            .filter(|message| message.author_id.as_ref() == Some(&author_id))
+            .collect();
+        messages.sort_by_key(|message| std::cmp::Reverse(message.stars));
+        messages.truncate(limit.max(0) as usize);
+        Ok(messages)
+    }
+
+    async fn random_starred_message(&self, scope: &StatsScope) -> Result<Option<StarredMessage>, Error> {
+        let posted: Vec<StarredMessage> = self.state.lock().unwrap().scoped_messages(scope).into_iter()
+            .filter(|message| message.starboard_message_id.is_some())
+            .collect();
+        Ok(posted.choose(&mut rng()).cloned())
+    }
+}
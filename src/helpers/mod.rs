@@ -1,9 +1,17 @@
 pub mod reminder;
 pub mod reminder_task;
+pub mod reminder_scheduler;
+pub mod reminder_components;
 
 pub mod starboard;
 pub mod starboard_manager;
+pub mod starboard_rescan;
+pub mod starboard_render;
+pub mod starboard_activity;
+pub mod starboard_state;
 pub(crate) mod auth;
 pub(crate) mod role_colours;
-
-use crate::types;
\ No newline at end of file
+pub(crate) mod schema;
+pub mod storage;
+#[cfg(test)]
+pub mod memory_storage;
@@ -1,93 +1,430 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
-use sqlx::{SqlitePool, Error, Row};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::UserId;
+use sqlx::SqlitePool;
 
-use crate::structs::reminders::Reminder;
+use crate::helpers::schema::{add_column_if_missing, column_type};
+use crate::helpers::storage::ReminderStorage;
+use crate::structs::reminders::{Reminder, ReminderId};
+use crate::structs::snowflake::Snowflake;
+use crate::types::Error;
+
+// per user cap on reminders that haven't gone out yet, subscriptions don't count
+pub const MAX_ACTIVE_REMINDERS: i64 = 25;
+// how far ahead a reminder can be set
+pub const MAX_REMINDER_HORIZON_DAYS: i64 = 5 * 365;
+// delivered reminders are purged once they are this old
+pub const SENT_RETENTION_DAYS: i64 = 30;
 
 #[derive(Clone)]
 pub struct ReminderStore {
     pool: SqlitePool,
 }
 
+// timestamps are stored as UTC unix seconds and ids as integers
+const REMINDERS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS reminders (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        context_message_url TEXT,
+        remind_at INTEGER NOT NULL,
+        reminder_message TEXT NOT NULL,
+        sent BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at INTEGER DEFAULT (unixepoch()),
+        recurrence TEXT,
+        recur_until INTEGER,
+        max_occurrences INTEGER,
+        occurrences INTEGER NOT NULL DEFAULT 0,
+        channel_id INTEGER,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        retry_at INTEGER,
+        failed BOOLEAN NOT NULL DEFAULT FALSE,
+        failure_reason TEXT,
+        acknowledged BOOLEAN NOT NULL DEFAULT FALSE,
+        target_channel_id INTEGER,
+        role_id INTEGER,
+        context_channel_id INTEGER,
+        context_message_id INTEGER
+    )
+"#;
+
+const SUBSCRIBERS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS reminder_subscribers (
+        reminder_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        created_at INTEGER DEFAULT (unixepoch()),
+        PRIMARY KEY (reminder_id, user_id)
+    )
+"#;
 
+// legacy rows hold "%Y-%m-%d %H:%M:%S" text, anything unparseable becomes NULL
+fn epoch_of(column: &str) -> String {
+    format!("CASE typeof({0}) WHEN 'integer' THEN {0} ELSE unixepoch({0}) END", column)
+}
 
-// was gonna refactor to use timeparse.rs but i realise i cba to do all that rn :wilted_rose:
 impl ReminderStore {
     pub fn new(pool: SqlitePool) -> Self {
         Self { pool }
     }
 
-    pub async fn add_reminder(&self, reminder: &Reminder) -> Result<i64, Error> {
-        let convertformat = reminder.remind_at.format("%Y-%m-%d %H:%M:%S").to_string();
+    pub async fn create_tables(&self) -> Result<(), sqlx::Error> {
+        match column_type(&self.pool, "reminders", "remind_at").await? {
+            Some(kind) if !kind.eq_ignore_ascii_case("INTEGER") => self.migrate_text_schema().await?,
+            _ => {}
+        }
+
+        sqlx::query(REMINDERS_TABLE).execute(&self.pool).await?;
+        sqlx::query(SUBSCRIBERS_TABLE).execute(&self.pool).await?;
+
+        add_column_if_missing(&self.pool, "reminders", "context_channel_id", "INTEGER").await?;
+        add_column_if_missing(&self.pool, "reminders", "context_message_id", "INTEGER").await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_reminders_user ON reminders(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Rebuilds the old text timestamp / text id tables as integers, keeping every row
+    async fn migrate_text_schema(&self) -> Result<(), sqlx::Error> {
+        // the table may predate any of the later columns
+        for (column, definition) in [
+            ("recurrence", "TEXT"),
+            ("recur_until", "TIMESTAMP"),
+            ("max_occurrences", "INTEGER"),
+            ("occurrences", "INTEGER NOT NULL DEFAULT 0"),
+            ("channel_id", "TEXT"),
+            ("attempts", "INTEGER NOT NULL DEFAULT 0"),
+            ("retry_at", "TIMESTAMP"),
+            ("failed", "BOOLEAN NOT NULL DEFAULT FALSE"),
+            ("failure_reason", "TEXT"),
+            ("acknowledged", "BOOLEAN NOT NULL DEFAULT FALSE"),
+            ("target_channel_id", "TEXT"),
+            ("role_id", "TEXT"),
+        ] {
+            add_column_if_missing(&self.pool, "reminders", column, definition).await?;
+        }
+        sqlx::query(SUBSCRIBERS_TABLE).execute(&self.pool).await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("ALTER TABLE reminders RENAME TO reminders_legacy").execute(&mut *tx).await?;
+        sqlx::query("ALTER TABLE reminder_subscribers RENAME TO reminder_subscribers_legacy").execute(&mut *tx).await?;
+        sqlx::query(REMINDERS_TABLE).execute(&mut *tx).await?;
+        sqlx::query(SUBSCRIBERS_TABLE).execute(&mut *tx).await?;
+
+        sqlx::query(&format!(
+            r#"INSERT INTO reminders (id, user_id, context_message_url, remind_at, reminder_message, sent,
+                created_at, recurrence, recur_until, max_occurrences, occurrences, channel_id, attempts,
+                retry_at, failed, failure_reason, acknowledged, target_channel_id, role_id)
+            SELECT id, CAST(user_id AS INTEGER), context_message_url,
+                COALESCE({remind_at}, unixepoch()), reminder_message, sent, {created_at},
+                recurrence, {recur_until}, max_occurrences, occurrences, CAST(channel_id AS INTEGER),
+                attempts, {retry_at}, failed, failure_reason, acknowledged,
+                CAST(target_channel_id AS INTEGER), CAST(role_id AS INTEGER)
+            FROM reminders_legacy"#,
+            remind_at = epoch_of("remind_at"),
+            created_at = epoch_of("created_at"),
+            recur_until = epoch_of("recur_until"),
+            retry_at = epoch_of("retry_at"),
+        ))
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&format!(
+            r#"INSERT INTO reminder_subscribers (reminder_id, user_id, created_at)
+            SELECT reminder_id, CAST(user_id AS INTEGER), {}
+            FROM reminder_subscribers_legacy"#,
+            epoch_of("created_at"),
+        ))
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DROP TABLE reminders_legacy").execute(&mut *tx).await?;
+        sqlx::query("DROP TABLE reminder_subscribers_legacy").execute(&mut *tx).await?;
+
+        tx.commit().await
+    }
+}
 
+#[async_trait]
+impl ReminderStorage for ReminderStore {
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<ReminderId, Error> {
         let query = sqlx::query(
-            r#"INSERT INTO reminders (user_id, context_message_url, remind_at, reminder_message)
-            VALUES (?, ?, ?, ?)"#,
+            r#"INSERT INTO reminders (user_id, context_message_url, remind_at, reminder_message,
+                recurrence, recur_until, max_occurrences, channel_id, target_channel_id, role_id,
+                context_channel_id, context_message_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
         )
-            .bind(&reminder.user_id)
+            .bind(reminder.user_id)
             .bind(&reminder.context_message_url)
-            .bind(&convertformat)
+            .bind(reminder.remind_at.timestamp())
             .bind(&reminder.reminder_message)
+            .bind(&reminder.recurrence)
+            .bind(reminder.recur_until.map(|t| t.timestamp()))
+            .bind(reminder.max_occurrences)
+            .bind(reminder.channel_id)
+            .bind(reminder.target_channel_id)
+            .bind(reminder.role_id)
+            .bind(reminder.context_channel_id)
+            .bind(reminder.context_message_id)
             .execute(&self.pool)
             .await?;
 
-        Ok(query.last_insert_rowid())
+        Ok(ReminderId(query.last_insert_rowid()))
+    }
+
+    /// Slash reminders only get a jump link once the confirmation has been posted
+    async fn set_context_url(&self, reminder_id: ReminderId, url: &str) -> Result<(), Error> {
+        sqlx::query("UPDATE reminders SET context_message_url = ? WHERE id = ?")
+            .bind(url)
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Every reminder that still has to fire, used to fill the scheduler on startup
+    async fn get_pending(&self) -> Result<Vec<Reminder>, Error> {
+        Ok(sqlx::query_as::<_, Reminder>(
+            r#"SELECT * FROM reminders
+            WHERE sent = FALSE AND failed = FALSE
+            ORDER BY remind_at
+            "#,
+        )
+            .fetch_all(&self.pool)
+            .await?)
     }
 
-    pub async fn get_dues(&self) -> Result<Vec<Reminder>, Error> {
-        let convertformat = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    async fn get_reminder(&self, reminder_id: ReminderId) -> Result<Option<Reminder>, Error> {
+        Ok(sqlx::query_as::<_, Reminder>("SELECT * FROM reminders WHERE id = ?")
+            .bind(reminder_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
 
-        let rows = sqlx::query(
+    async fn get_user_reminders(&self, user_id: UserId) -> Result<Vec<Reminder>, Error> {
+        Ok(sqlx::query_as::<_, Reminder>(
             r#"SELECT * FROM reminders
             WHERE sent = FALSE
-            AND remind_at <= ?
+            AND (user_id = ?1 OR id IN (SELECT reminder_id FROM reminder_subscribers WHERE user_id = ?1))
             ORDER BY remind_at
             "#,
         )
-            .bind(&convertformat)
+            .bind(Snowflake(user_id))
             .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn mark_due(&self, reminder_id: ReminderId) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE reminders
+            SET sent = TRUE, occurrences = occurrences + 1,
+                attempts = 0, retry_at = NULL, failure_reason = NULL
+            WHERE id = ?"#,
+        )
+            .bind(reminder_id)
+            .execute(&self.pool)
             .await?;
 
-        let mut reminders = Vec::new();
-        for row in rows {
-            let remind_at_str: String = row.try_get("remind_at")?;
-            let remind_at = NaiveDateTime::parse_from_str(&remind_at_str, "%Y-%m-%d %H:%M:%S")
-                .map(|ndt| DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc))
-                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
-
-            let created_at_str: Option<String> = row.try_get("created_at")?;
-            let created_at = match created_at_str {
-                Some(s) => {
-                    Some(NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S")
-                        .map(|ndt| DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc))
-                        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?)
-                }
-                None => None,
-            };
-
-            reminders.push(Reminder {
-                id: row.try_get("id")?,
-                user_id: row.try_get("user_id")?,
-                context_message_url: row.try_get("context_message_url")?,
-                remind_at,
-                reminder_message: row.try_get("reminder_message")?,
-                sent: row.try_get("sent")?,
-                created_at,
-            });
-        }
+        Ok(())
+    }
+
+    /// Moves a recurring reminder on to its next occurrence after one has been delivered
+    ///
+    /// `failure_reason` is kept when the occurrence was given up on instead, so the list can show it.
+    async fn reschedule(
+        &self,
+        reminder_id: ReminderId,
+        next_at: DateTime<Utc>,
+        failure_reason: Option<&str>,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE reminders
+            SET remind_at = ?, occurrences = occurrences + 1,
+                attempts = 0, retry_at = NULL, failure_reason = ?, acknowledged = FALSE
+            WHERE id = ?"#,
+        )
+            .bind(next_at.timestamp())
+            .bind(failure_reason)
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
 
-        Ok(reminders)
+        Ok(())
     }
 
-    pub async fn mark_due(&self, reminder_id: i64) -> Result<(), Error> {
+    async fn record_failed_attempt(
+        &self,
+        reminder_id: ReminderId,
+        reason: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
         sqlx::query(
             r#"UPDATE reminders
-            SET sent = TRUE
+            SET attempts = attempts + 1, failure_reason = ?, retry_at = ?
             WHERE id = ?"#,
         )
+            .bind(reason)
+            .bind(retry_at.timestamp())
             .bind(reminder_id)
             .execute(&self.pool)
             .await?;
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Dead-letters a reminder, it stays in the owner's list with the reason until cancelled
+    async fn mark_failed(&self, reminder_id: ReminderId, reason: &str) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE reminders
+            SET failed = TRUE, attempts = attempts + 1, failure_reason = ?, retry_at = NULL
+            WHERE id = ?"#,
+        )
+            .bind(reason)
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Clears a pending snooze redelivery of a recurring reminder without touching the series
+    async fn finish_snooze(&self, reminder_id: ReminderId, failure_reason: Option<&str>) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE reminders
+            SET attempts = 0, retry_at = NULL, failure_reason = ?
+            WHERE id = ?"#,
+        )
+            .bind(failure_reason)
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Pushes a delivered reminder back by redelivering it at `until`, for recurring reminders
+    /// the series itself is left alone
+    async fn snooze(&self, reminder_id: ReminderId, until: DateTime<Utc>) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE reminders
+            SET retry_at = ?, sent = FALSE, failed = FALSE, attempts = 0, acknowledged = FALSE
+            WHERE id = ?"#,
+        )
+            .bind(until.timestamp())
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks the delivered reminder as done, which also drops any snooze still pending on it
+    async fn acknowledge(&self, reminder_id: ReminderId) -> Result<(), Error> {
+        sqlx::query(
+            r#"UPDATE reminders
+            SET acknowledged = TRUE, retry_at = NULL, attempts = 0,
+                sent = CASE WHEN recurrence IS NULL THEN TRUE ELSE sent END
+            WHERE id = ?"#,
+        )
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn cancel_reminder(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error> {
+        let result = sqlx::query(
+            r#"DELETE FROM reminders
+            WHERE id = ? AND user_id = ? AND sent = FALSE"#,
+        )
+            .bind(reminder_id)
+            .bind(Snowflake(user_id))
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM reminder_subscribers WHERE reminder_id = ?")
+            .bind(reminder_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn count_active_reminders(&self, user_id: UserId) -> Result<i64, Error> {
+        Ok(sqlx::query_scalar(
+            r#"SELECT COUNT(*) FROM reminders
+            WHERE user_id = ? AND sent = FALSE AND failed = FALSE"#,
+        )
+            .bind(Snowflake(user_id))
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    /// Deletes delivered reminders last due before `before` along with their subscribers,
+    /// dead-lettered ones stay until the owner cancels them
+    async fn purge_sent(&self, before: DateTime<Utc>) -> Result<u64, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"DELETE FROM reminder_subscribers WHERE reminder_id IN (
+                SELECT id FROM reminders WHERE sent = TRUE AND remind_at < ?
+            )"#,
+        )
+            .bind(before.timestamp())
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM reminders WHERE sent = TRUE AND remind_at < ?")
+            .bind(before.timestamp())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Returns `false` if the user was already subscribed
+    async fn add_subscriber(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO reminder_subscribers (reminder_id, user_id) VALUES (?, ?)"
+        )
+            .bind(reminder_id)
+            .bind(Snowflake(user_id))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove_subscriber(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "DELETE FROM reminder_subscribers WHERE reminder_id = ? AND user_id = ?"
+        )
+            .bind(reminder_id)
+            .bind(Snowflake(user_id))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_subscribers(&self, reminder_id: ReminderId) -> Result<Vec<UserId>, Error> {
+        let rows: Vec<(Snowflake<UserId>,)> = sqlx::query_as(
+            "SELECT user_id FROM reminder_subscribers WHERE reminder_id = ? ORDER BY created_at"
+        )
+            .bind(reminder_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|(user_id,)| user_id.0).collect())
+    }
+}
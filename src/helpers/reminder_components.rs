@@ -0,0 +1,156 @@
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateQuickModal, InputTextStyle,
+};
+
+use crate::helpers::reminder::MAX_REMINDER_HORIZON_DAYS;
+use crate::structs::reminders::ReminderId;
+use crate::structs::time_parse::ParsedDuration;
+use crate::types::{Data, Error};
+
+// custom ids look like `reminder:<action>:<reminder id>[:<seconds>]`
+pub const REMINDER_COMPONENT_PREFIX: &str = "reminder:";
+
+pub fn reminder_buttons(reminder_id: ReminderId) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("reminder:snooze:{}:600", reminder_id))
+            .label("Snooze 10m")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(format!("reminder:snooze:{}:3600", reminder_id))
+            .label("Snooze 1h")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(format!("reminder:custom:{}", reminder_id))
+            .label("Snooze...")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(format!("reminder:done:{}", reminder_id))
+            .label("Done")
+            .style(ButtonStyle::Success),
+    ])]
+}
+
+/// "Remind me too" button attached to the reminder confirmation
+pub fn subscribe_button(reminder_id: ReminderId) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("reminder:subscribe:{}", reminder_id))
+            .label("Remind me too")
+            .style(ButtonStyle::Primary),
+    ])
+}
+
+fn ephemeral(content: impl Into<String>) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    )
+}
+
+pub(crate) async fn handle_reminder_component(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let mut parts = interaction.data.custom_id.split(':').skip(1);
+    let action = parts.next().unwrap_or_default();
+    let reminder_id = match parts.next().and_then(|id| id.parse::<i64>().ok()) {
+        Some(id) => ReminderId(id),
+        None => return Ok(()),
+    };
+
+    let reminder = match data.reminders.get_reminder(reminder_id).await? {
+        Some(reminder) => reminder,
+        None => {
+            interaction.create_response(&ctx.http, ephemeral("This reminder no longer exists")).await?;
+            return Ok(());
+        }
+    };
+
+    let user_id = interaction.user.id;
+
+    if action == "subscribe" {
+        let content = if reminder.user_id == user_id {
+            "You already get this reminder".to_string()
+        } else if reminder.sent || reminder.failed {
+            "This reminder has already gone out".to_string()
+        } else if data.reminders.add_subscriber(reminder.id, user_id).await? {
+            format!("You'll be reminded too <t:{}:R>, press again to unsubscribe", reminder.remind_at.timestamp())
+        } else {
+            data.reminders.remove_subscriber(reminder.id, user_id).await?;
+            "You won't be reminded about this anymore".to_string()
+        };
+
+        interaction.create_response(&ctx.http, ephemeral(content)).await?;
+        return Ok(());
+    }
+
+    if reminder.user_id != user_id {
+        interaction.create_response(&ctx.http, ephemeral("This isn't your reminder")).await?;
+        return Ok(());
+    }
+
+    match action {
+        "done" => {
+            data.reminders.acknowledge(reminder.id).await?;
+
+            let response = CreateInteractionResponseMessage::new()
+                .content("✅ Done")
+                .components(vec![]);
+            interaction.create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(response)).await?;
+        }
+        "snooze" => {
+            let seconds = parts.next().and_then(|s| s.parse::<i64>().ok()).unwrap_or(600);
+            let until = Utc::now() + chrono::Duration::seconds(seconds);
+
+            data.reminders.snooze(reminder.id, until).await?;
+            data.reminder_scheduler.schedule(reminder.id, until);
+
+            let response = CreateInteractionResponseMessage::new()
+                .content(format!("⏰ Snoozed until <t:{}:t>", until.timestamp()))
+                .components(vec![]);
+            interaction.create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(response)).await?;
+        }
+        "custom" => {
+            let modal = CreateQuickModal::new("Snooze reminder")
+                .timeout(std::time::Duration::from_secs(300))
+                .field(
+                    CreateInputText::new(InputTextStyle::Short, "Snooze for", "")
+                        .placeholder("15m, 2h, 1d")
+                        .max_length(10),
+                );
+
+            let response = match interaction.quick_modal(ctx, modal).await? {
+                Some(response) => response,
+                None => return Ok(()),
+            };
+
+            let input = response.inputs.first().map(|s| s.trim()).unwrap_or_default();
+            let parsed = match ParsedDuration::new(input) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    response.interaction.create_response(&ctx.http, ephemeral(format!("Could not parse snooze: {}", e))).await?;
+                    return Ok(());
+                }
+            };
+
+            let until = parsed.until_datetime();
+            if until > Utc::now() + chrono::Duration::days(MAX_REMINDER_HORIZON_DAYS) {
+                let content = format!("Reminders can be set at most {} years ahead", MAX_REMINDER_HORIZON_DAYS / 365);
+                response.interaction.create_response(&ctx.http, ephemeral(content)).await?;
+                return Ok(());
+            }
+
+            data.reminders.snooze(reminder.id, until).await?;
+            data.reminder_scheduler.schedule(reminder.id, until);
+
+            let message = CreateInteractionResponseMessage::new()
+                .content(format!("⏰ Snoozed for {}, until <t:{}:f>", parsed.human_readable(), until.timestamp()))
+                .components(vec![]);
+            response.interaction.create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(message)).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
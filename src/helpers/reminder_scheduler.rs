@@ -0,0 +1,63 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::Notify;
+
+use crate::structs::reminders::ReminderId;
+
+// fire time first so the heap orders by it
+type QueueEntry = Reverse<(DateTime<Utc>, ReminderId)>;
+
+/// In-memory queue of upcoming reminder fire times
+///
+/// The store stays the source of truth, entries here are only hints. Anything popped is
+/// re-read from the store before delivery, so cancelled or moved reminders can be left in
+/// the queue and get dropped when they come up.
+#[derive(Default)]
+pub struct ReminderScheduler {
+    queue: Mutex<BinaryHeap<QueueEntry>>,
+    wake: Notify,
+}
+
+impl ReminderScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schedule(&self, reminder_id: ReminderId, at: DateTime<Utc>) {
+        let mut queue = self.queue.lock().unwrap();
+        let is_sooner = queue.peek().is_none_or(|Reverse((next, _))| at < *next);
+        queue.push(Reverse((at, reminder_id)));
+        drop(queue);
+
+        if is_sooner {
+            self.wake.notify_one();
+        }
+    }
+
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.queue.lock().unwrap().peek().map(|Reverse((at, _))| *at)
+    }
+
+    pub fn pop_due(&self, now: DateTime<Utc>) -> Vec<ReminderId> {
+        let mut queue = self.queue.lock().unwrap();
+        let mut due = Vec::new();
+
+        while queue.peek().is_some_and(|Reverse((at, _))| *at <= now) {
+            if let Some(Reverse((_, reminder_id))) = queue.pop() {
+                due.push(reminder_id);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        due.retain(|reminder_id| seen.insert(*reminder_id));
+        due
+    }
+
+    /// Resolves once something sooner than the current head has been scheduled
+    pub async fn woken(&self) {
+        self.wake.notified().await;
+    }
+}
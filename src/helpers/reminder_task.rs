@@ -1,58 +1,339 @@
-use std::ptr::null;
 use std::sync::Arc;
-use serenity::all::{Colour, CreateMessage, UserId};
+use chrono::Utc;
+use serenity::all::{Colour, CreateAllowedMentions, CreateMessage, Mentionable, UserId};
 use poise::serenity_prelude as serenity;
 use tokio::time::{sleep, Duration};
+use crate::helpers::reminder::SENT_RETENTION_DAYS;
+use crate::helpers::reminder_components::reminder_buttons;
+use crate::structs::reminders::Reminder;
 use crate::types::Data;
 
-pub async fn reminder_task(data: Arc<Data>) -> Result<serenity::CreateEmbed, Box<dyn std::error::Error + Send + Sync>> {
+// how long to wait before trying a reminder again after the store errored
+const RETRY_DELAY: i64 = 60;
+
+// delivery backoff doubles from the base delay on every failed attempt
+const RETRY_BASE_DELAY: i64 = 60;
+const MAX_DELIVERY_ATTEMPTS: i64 = 6;
+
+// how often delivered reminders past retention are purged
+const HOUSEKEEPING_INTERVAL: u64 = 60 * 60;
+
+// anything delivered later than this after its due time gets a note saying so
+const LATE_THRESHOLD: i64 = 2 * 60;
+
+// leaves room for attachment links within discord's field limit
+const QUOTE_LIMIT: usize = 700;
+const FIELD_LIMIT: usize = 1024;
+
+pub async fn reminder_task(data: Arc<Data>) {
+    // anything missed while the bot was offline is already due and fires straight away
+    match data.reminders.get_pending().await {
+        Ok(reminders) => {
+            for reminder in reminders {
+                data.reminder_scheduler.schedule(reminder.id, reminder.fire_at());
+            }
+        }
+        Err(e) => {
+            eprintln!("Error loading pending reminders: {}", e);
+        }
+    }
+
+    let mut housekeeping = tokio::time::interval(Duration::from_secs(HOUSEKEEPING_INTERVAL));
+
     loop {
-        sleep(Duration::from_secs(60)).await;
-
-        match data.reminders.get_dues().await {
-            Ok(reminders) => {
-                for reminder in reminders {
-                    let user_id = match reminder.user_id.parse::<u64>() {
-                        Ok(id) => UserId::new(id),
-                        Err(e) => {
-                            eprintln!("Invalid user ID caught in reminder feedback loop for user {}, E: {}", reminder.user_id, e);
-                            continue;
-                        }
-                    };
-
-                    let readable = reminder.created_at.map(|t| format!("<t:{}:F>", t.timestamp()))
-                        .unwrap_or_else(|| "Unknown time".into());
-
-                    //let footer = serenity::CreateEmbedFooter::new(format!("Reminder created on {}", readable));
-
-                    let embed = serenity::CreateEmbed::default()
-                        .colour(Colour::new(0x800080))
-                        .title(format!("Reminder set at {}:", readable))
-                        .description(reminder.reminder_message)
-                        .field("Context", format!("{}", &reminder.context_message_url.unwrap().to_string()), true);
-
-                    match user_id.create_dm_channel(&data.http_client).await {
-                        Ok(dm_channel) => {
-                            if let Err(e) = dm_channel
-                                .send_message(&data.http_client, CreateMessage::new().content("").embed(embed))
-                                .await
-                            {
-                            eprintln!("Error sending reminder E: {}", e);
-                        } else {
-                            if let Err(e) = data.reminders.mark_due(reminder.id).await {
-                            eprintln!("Error marking reminders: {}", e);}
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("Error creating dm_channel for reminder: {}", e);
-                        }
-                    }
+        for reminder_id in data.reminder_scheduler.pop_due(Utc::now()) {
+            match data.reminders.get_reminder(reminder_id).await {
+                // stale queue entry, the reminder was cancelled, sent or moved since
+                Ok(Some(reminder)) if reminder.is_due() => deliver_reminder(&data, reminder).await,
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error fetching reminder {}: {}", reminder_id, e);
+                    data.reminder_scheduler.schedule(reminder_id, Utc::now() + chrono::Duration::seconds(RETRY_DELAY));
+                }
+            }
+        }
+
+        let next_due = data.reminder_scheduler.next_due();
+        let until_due = async {
+            match next_due {
+                Some(next) => sleep((next - Utc::now()).to_std().unwrap_or(Duration::ZERO)).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = until_due => {}
+            _ = data.reminder_scheduler.woken() => {}
+            _ = housekeeping.tick() => purge_old_reminders(&data).await,
+        }
+    }
+}
+
+async fn purge_old_reminders(data: &Data) {
+    let before = Utc::now() - chrono::Duration::days(SENT_RETENTION_DAYS);
+    match data.reminders.purge_sent(before).await {
+        Ok(0) => {}
+        Ok(purged) => println!("Purged {} delivered reminders", purged),
+        Err(e) => eprintln!("Error purging old reminders: {}", e),
+    }
+}
+
+/// Rough "3 days 4 hours" style length, only the two largest units
+fn format_lateness(late: chrono::Duration) -> String {
+    let units = [
+        (late.num_days(), "day"),
+        (late.num_hours() % 24, "hour"),
+        (late.num_minutes() % 60, "minute"),
+    ];
+
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(amount, _)| *amount == 0)
+        .take(2)
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| format!("{} {}{}", amount, unit, if *amount == 1 { "" } else { "s" }))
+        .collect();
+
+    if parts.is_empty() {
+        "under a minute".into()
+    } else {
+        parts.join(" ")
+    }
+}
+
+async fn deliver_reminder(data: &Data, reminder: Reminder) {
+    let user_id = *reminder.user_id;
+
+    let readable = reminder.created_at.map(|t| format!("<t:{}:F>", t.timestamp()))
+        .unwrap_or_else(|| "Unknown time".into());
+
+    //let footer = serenity::CreateEmbedFooter::new(format!("Reminder created on {}", readable));
+
+    let now = Utc::now();
+    let snoozed = reminder.is_snoozed_occurrence(now);
+    let next_occurrence = if snoozed { None } else { reminder.next_occurrence(now) };
+
+    let mut embed = serenity::CreateEmbed::default()
+        .colour(Colour::new(0x800080))
+        .title(format!("Reminder set at {}:", readable))
+        .description(&reminder.reminder_message);
+
+    if let Some(url) = &reminder.context_message_url {
+        embed = embed.field("Context", format!("[Jump to message]({})", url), true);
+    }
+
+    embed = quote_context_message(data, &reminder, embed).await;
+
+    // retries are late on purpose, only the first attempt points out the delay
+    let late = now - reminder.fire_at();
+    if reminder.attempts == 0 && late.num_seconds() > LATE_THRESHOLD {
+        embed = embed.field(
+            "Delivered late",
+            format!("This was due <t:{}:F>, {} ago",
+                    reminder.fire_at().timestamp(), format_lateness(late)),
+            false,
+        );
+    }
+
+    if let Some(recurrence) = &reminder.recurrence {
+        let upcoming = if snoozed { Some(reminder.remind_at) } else { next_occurrence };
+        let next = upcoming
+            .map(|t| format!("<t:{}:R>", t.timestamp()))
+            .unwrap_or_else(|| "Final occurrence".into());
+        embed = embed
+            .field("Repeats", recurrence, true)
+            .field("Next", next, true);
+    }
 
+    match send_reminder(data, &reminder, user_id, embed).await {
+        Ok(()) => {
+            let result = match next_occurrence {
+                _ if snoozed => data.reminders.finish_snooze(reminder.id, None).await,
+                Some(next) => data.reminders.reschedule(reminder.id, next, None).await,
+                None => data.reminders.mark_due(reminder.id).await,
+            };
+            match result {
+                Ok(()) => {
+                    if let Some(next) = next_occurrence {
+                        data.reminder_scheduler.schedule(reminder.id, next);
+                    }
                 }
+                Err(e) => eprintln!("Error marking reminders: {}", e),
+            }
+        }
+        Err(reason) => {
+            eprintln!("Error delivering reminder {} (attempt {}): {}", reminder.id, reminder.attempts + 1, reason);
+
+            let result = if reminder.attempts + 1 < MAX_DELIVERY_ATTEMPTS {
+                let delay = RETRY_BASE_DELAY * 2_i64.pow(reminder.attempts as u32);
+                let retry_at = now + chrono::Duration::seconds(delay);
+                data.reminder_scheduler.schedule(reminder.id, retry_at);
+                data.reminders.record_failed_attempt(reminder.id, &reason, retry_at).await
+            } else if snoozed {
+                data.reminders.finish_snooze(reminder.id, Some(&reason)).await
+            } else if let Some(next) = next_occurrence {
+                // give up on this occurrence but keep the series going
+                data.reminder_scheduler.schedule(reminder.id, next);
+                data.reminders.reschedule(reminder.id, next, Some(&reason)).await
+            } else {
+                data.reminders.mark_failed(reminder.id, &reason).await
+            };
+
+            if let Err(e) = result {
+                eprintln!("Error recording failed reminder {}: {}", reminder.id, e);
             }
-            Err(e) => {
-                eprintln!("Error fetching dm_channel for reminders: {}", e);
+        }
+    }
+}
+
+/// Quotes the message the reminder is about, it may have been deleted since
+async fn quote_context_message(
+    data: &Data,
+    reminder: &Reminder,
+    embed: serenity::CreateEmbed,
+) -> serenity::CreateEmbed {
+    let (Some(channel_id), Some(message_id)) = (reminder.context_channel_id, reminder.context_message_id) else {
+        return embed;
+    };
+
+    let message = match channel_id.message(&data.http_client, *message_id).await {
+        Ok(message) => message,
+        Err(e) => {
+            eprintln!("Error fetching context message for reminder {}: {}", reminder.id, e);
+            return embed.field("Message", "*The original message is no longer available*", false);
+        }
+    };
+
+    let mut quote: String = message.content
+        .lines()
+        .map(|line| format!("> {}\n", line))
+        .collect();
+
+    if quote.chars().count() > QUOTE_LIMIT {
+        quote = quote.chars().take(QUOTE_LIMIT - 1).collect();
+        quote.push('…');
+    }
+
+    for attachment in &message.attachments {
+        let link = format!("\n📎 [{}]({})", attachment.filename, attachment.url);
+        if quote.chars().count() + link.chars().count() > FIELD_LIMIT {
+            break;
+        }
+        quote.push_str(&link);
+    }
+
+    if quote.is_empty() {
+        quote.push_str("*No text content*");
+    }
+
+    let mut embed = embed.field(format!("Message from {}", message.author.name), quote, false);
+
+    let image = message.attachments
+        .iter()
+        .find(|attachment| attachment.content_type.as_deref().is_some_and(|kind| kind.starts_with("image/")));
+    if let Some(image) = image {
+        embed = embed.image(&image.url);
+    }
+
+    embed
+}
+
+/// Posts the reminder in its target channel, or DMs it to the owner and every subscriber
+///
+/// Only the owner's delivery counts towards retries, subscriber DMs are best effort and only
+/// go out on the first attempt so retries and snoozes don't spam them.
+async fn send_reminder(
+    data: &Data,
+    reminder: &Reminder,
+    user_id: UserId,
+    embed: serenity::CreateEmbed,
+) -> Result<(), String> {
+    let subscribers = match data.reminders.get_subscribers(reminder.id).await {
+        Ok(subscribers) => subscribers,
+        Err(e) => {
+            eprintln!("Error fetching subscribers for reminder {}: {}", reminder.id, e);
+            Vec::new()
+        }
+    };
+
+    if let Some(channel_id) = reminder.target_channel_id {
+        let mut users = vec![user_id];
+        users.extend(subscribers);
+
+        let mut mentions: Vec<String> = users.iter().map(|user| user.mention().to_string()).collect();
+        let mut allowed_mentions = CreateAllowedMentions::new().users(users);
+
+        if let Some(role_id) = reminder.role_id {
+            let role_id = *role_id;
+            mentions.push(role_id.mention().to_string());
+            allowed_mentions = allowed_mentions.roles(vec![role_id]);
+        }
+
+        let message = CreateMessage::new()
+            .content(mentions.join(" "))
+            .embed(embed)
+            .components(reminder_buttons(reminder.id))
+            .allowed_mentions(allowed_mentions);
+
+        return channel_id
+            .send_message(&data.http_client, message)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Could not post in channel: {}", e));
+    }
+
+    if reminder.retry_at.is_none() {
+        for subscriber in subscribers {
+            let result = match subscriber.create_dm_channel(&data.http_client).await {
+                Ok(dm_channel) => dm_channel
+                    .send_message(&data.http_client, CreateMessage::new().embed(embed.clone()))
+                    .await
+                    .map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("Error sending reminder {} to subscriber {}: {}", reminder.id, subscriber, e);
             }
         }
     }
+
+    send_owner_dm(data, reminder, user_id, embed).await
+}
+
+/// DMs the reminder, falling back to a ping in the channel it was created in
+async fn send_owner_dm(
+    data: &Data,
+    reminder: &Reminder,
+    user_id: UserId,
+    embed: serenity::CreateEmbed,
+) -> Result<(), String> {
+    let dm_error = match user_id.create_dm_channel(&data.http_client).await {
+        Ok(dm_channel) => match dm_channel
+            .send_message(&data.http_client, CreateMessage::new()
+                .embed(embed.clone())
+                .components(reminder_buttons(reminder.id)))
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => format!("Could not DM user: {}", e),
+        },
+        Err(e) => format!("Could not open DM channel: {}", e),
+    };
+
+    let channel_id = match reminder.channel_id {
+        Some(id) => *id,
+        None => return Err(dm_error),
+    };
+
+    let message = CreateMessage::new()
+        .content(format!("{} I couldn't DM you this reminder", user_id.mention()))
+        .embed(embed)
+        .components(reminder_buttons(reminder.id))
+        .allowed_mentions(CreateAllowedMentions::new().users(vec![user_id]));
+
+    channel_id
+        .send_message(&data.http_client, message)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("{}, channel fallback failed: {}", dm_error, e))
 }
@@ -0,0 +1,41 @@
+use sqlx::{Row, SqlitePool};
+
+// tables created before a column existed dont pick it up from CREATE TABLE IF NOT EXISTS
+pub(crate) async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?;
+
+    let exists = columns
+        .iter()
+        .any(|row| row.try_get::<String, _>("name").is_ok_and(|name| name == column));
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Declared type of a column, `None` if the table or column doesn't exist
+pub(crate) async fn column_type(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(columns
+        .iter()
+        .find(|row| row.try_get::<String, _>("name").is_ok_and(|name| name == column))
+        .and_then(|row| row.try_get::<String, _>("type").ok()))
+}
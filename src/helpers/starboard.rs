@@ -1,188 +1,907 @@
-use sqlx::{sqlite::SqlitePool, prelude::*};
-pub(crate) use crate::structs::starboard_message::{StarboardConfig, StarredMessage};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use sqlx::sqlite::SqlitePool;
+use crate::helpers::schema::{add_column_if_missing, column_type};
+use crate::helpers::storage::StarboardStorage;
+use crate::types::Error;
+pub(crate) use crate::structs::starboard_message::{
+    BlockMode, ChannelFilter, DeletePolicy, StarSource, StarTier, StarTally, StarboardConfig, StarredMessage, StatsScope,
+};
 
 #[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
+    // boards and filters are read on every reaction but rarely change, every write below
+    // that touches them drops the cached copy
+    configs: Arc<Mutex<HashMap<u64, Vec<StarboardConfig>>>>,
+    filters: Arc<Mutex<HashMap<i64, Vec<ChannelFilter>>>>,
 }
 
+// one row per board, a guild can have several with their own emoji and channel
+const CONFIG_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_config (
+        id INTEGER PRIMARY KEY,
+        guild_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        starboard_channel_id TEXT,
+        threshold INTEGER NOT NULL DEFAULT 2,
+        star_emoji TEXT NOT NULL DEFAULT '⭐',
+        colour INTEGER,
+        self_star_allowed BOOLEAN NOT NULL DEFAULT FALSE,
+        enabled BOOLEAN NOT NULL DEFAULT TRUE,
+        ignore_bots BOOLEAN NOT NULL DEFAULT FALSE,
+        on_delete TEXT NOT NULL DEFAULT 'mark',
+        max_age_days INTEGER,
+        activity_scale INTEGER,
+        keep_after_days INTEGER,
+        tiers TEXT NOT NULL DEFAULT '',
+        fame_stars INTEGER,
+        fame_channel_id TEXT,
+        fame_pin BOOLEAN NOT NULL DEFAULT FALSE,
+        UNIQUE(guild_id, name)
+    )
+"#;
+
+const MESSAGES_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_messages (
+        id INTEGER PRIMARY KEY,
+        board_id INTEGER NOT NULL,
+        original_message_id TEXT NOT NULL,
+        original_channel_id TEXT NOT NULL,
+        starboard_message_id TEXT,
+        starboard_channel_id TEXT,
+        stars INTEGER DEFAULT 1,
+        starred_by TEXT NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        author_id TEXT,
+        frozen BOOLEAN NOT NULL DEFAULT FALSE,
+        forced BOOLEAN NOT NULL DEFAULT FALSE,
+        famed BOOLEAN NOT NULL DEFAULT FALSE,
+        UNIQUE(board_id, original_message_id)
+    )
+"#;
+
+const REACTIONS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_reactions (
+        id INTEGER PRIMARY KEY,
+        board_id INTEGER NOT NULL,
+        message_id TEXT NOT NULL,
+        user_id TEXT NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        channel_id TEXT,
+        sources INTEGER NOT NULL DEFAULT 1,
+        UNIQUE(board_id, message_id, user_id)
+    )
+"#;
+
+// stars from before multiple boards that couldn't be tied to a guild, one row per board they
+// might belong to until a reaction on the message shows which guild it's in
+const PENDING_REACTIONS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_reactions_pending (
+        board_id INTEGER NOT NULL,
+        message_id TEXT NOT NULL,
+        user_id TEXT NOT NULL,
+        created_at TIMESTAMP,
+        UNIQUE(board_id, message_id, user_id)
+    )
+"#;
+
+// channel_id can also be a category, which covers every channel in it
+const FILTERS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_channel_filters (
+        board_id INTEGER NOT NULL,
+        channel_id TEXT NOT NULL,
+        allow BOOLEAN NOT NULL,
+        PRIMARY KEY (board_id, channel_id)
+    )
+"#;
+
+// messages moderators took off a board, they aren't posted again while listed
+const BLACKLIST_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_blacklist (
+        board_id INTEGER NOT NULL,
+        message_id TEXT NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (board_id, message_id)
+    )
+"#;
+
+const BLOCKS_TABLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS starboard_blocks (
+        board_id INTEGER NOT NULL,
+        user_id TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        PRIMARY KEY (board_id, user_id)
+    )
+"#;
+
+// ?1 guild id, ?2 optional board id, ?3 optional "since" timestamp, queries number the rest from ?4
+const SCOPED_MESSAGES: &str = r#"m.board_id IN (SELECT id FROM starboard_config WHERE guild_id = ?1)
+    AND (?2 IS NULL OR m.board_id = ?2)
+    AND (?3 IS NULL OR m.created_at >= ?3)"#;
+
+pub const DEFAULT_BOARD: &str = "starboard";
+
 impl Database {
-    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
-        let pool = SqlitePool::connect(database_url).await?;
+    pub async fn new(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        let database = Self { pool, configs: Arc::default(), filters: Arc::default() };
+
+        // single board per guild tables are keyed by guild_id and have no id column
+        if column_type(&database.pool, "starboard_config", "guild_id").await?.is_some()
+            && column_type(&database.pool, "starboard_config", "id").await?.is_none()
+        {
+            database.migrate_single_board_schema().await?;
+        }
+
+        sqlx::query(CONFIG_TABLE).execute(&database.pool).await?;
+        sqlx::query(MESSAGES_TABLE).execute(&database.pool).await?;
+        sqlx::query(REACTIONS_TABLE).execute(&database.pool).await?;
+        sqlx::query(PENDING_REACTIONS_TABLE).execute(&database.pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_starboard_reactions_pending_message ON starboard_reactions_pending(message_id)")
+            .execute(&database.pool)
+            .await?;
+        sqlx::query(FILTERS_TABLE).execute(&database.pool).await?;
+        sqlx::query(BLACKLIST_TABLE).execute(&database.pool).await?;
+        sqlx::query(BLOCKS_TABLE).execute(&database.pool).await?;
+
+        add_column_if_missing(&database.pool, "starboard_config", "ignore_bots", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "on_delete", "TEXT NOT NULL DEFAULT 'mark'").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "max_age_days", "INTEGER").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "activity_scale", "INTEGER").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "keep_after_days", "INTEGER").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "tiers", "TEXT NOT NULL DEFAULT ''").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "fame_stars", "INTEGER").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "fame_channel_id", "TEXT").await?;
+        add_column_if_missing(&database.pool, "starboard_config", "fame_pin", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+        add_column_if_missing(&database.pool, "starboard_messages", "author_id", "TEXT").await?;
+        add_column_if_missing(&database.pool, "starboard_messages", "frozen", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+        add_column_if_missing(&database.pool, "starboard_messages", "forced", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+        add_column_if_missing(&database.pool, "starboard_messages", "famed", "BOOLEAN NOT NULL DEFAULT FALSE").await?;
+        add_column_if_missing(&database.pool, "starboard_reactions", "channel_id", "TEXT").await?;
+        add_column_if_missing(&database.pool, "starboard_reactions", "sources", "INTEGER NOT NULL DEFAULT 1").await?;
 
+        // reactions in the starboard channel are looked up by the copy they're on
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_starboard_messages_copy ON starboard_messages(starboard_message_id)")
+            .execute(&database.pool)
+            .await?;
+
+        Ok(database)
+    }
+
+    /// Turns each guild's old config row into a board named `starboard` and moves the
+    /// messages and reactions onto it
+    async fn migrate_single_board_schema(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("ALTER TABLE starboard_config RENAME TO starboard_config_legacy").execute(&mut *tx).await?;
+        sqlx::query(CONFIG_TABLE).execute(&mut *tx).await?;
         sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS starboard_messages (
-                id INTEGER PRIMARY KEY,
-                original_message_id TEXT NOT NULL UNIQUE,
-                original_channel_id TEXT NOT NULL,
-                starboard_message_id TEXT,
-                starboard_channel_id TEXT,
-                stars INTEGER DEFAULT 1,
-                starred_by TEXT NOT NULL,
-                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            r#"INSERT INTO starboard_config (guild_id, name, starboard_channel_id, threshold, star_emoji, self_star_allowed, enabled)
+            SELECT guild_id, ?, starboard_channel_id, COALESCE(threshold, 2), COALESCE(star_emoji, '⭐'),
+                COALESCE(self_star_allowed, FALSE), COALESCE(enabled, TRUE)
+            FROM starboard_config_legacy"#
+        )
+            .bind(DEFAULT_BOARD)
+            .execute(&mut *tx)
+            .await?;
+
+        // old rows don't know their guild, posted messages can be matched on the starboard
+        // channel and if there's only one board everything belongs to it
+        let only_board = "(SELECT MIN(id) FROM starboard_config HAVING COUNT(*) = 1)";
+
+        sqlx::query("ALTER TABLE starboard_messages RENAME TO starboard_messages_legacy").execute(&mut *tx).await?;
+        sqlx::query(MESSAGES_TABLE).execute(&mut *tx).await?;
+        sqlx::query(&format!(
+            r#"INSERT INTO starboard_messages (board_id, original_message_id, original_channel_id,
+                starboard_message_id, starboard_channel_id, stars, starred_by, created_at)
+            SELECT board_id, original_message_id, original_channel_id, starboard_message_id,
+                starboard_channel_id, stars, starred_by, created_at
+            FROM (
+                SELECT m.*, COALESCE(
+                    (SELECT c.id FROM starboard_config c WHERE c.starboard_channel_id = m.starboard_channel_id),
+                    {}
+                ) AS board_id
+                FROM starboard_messages_legacy m
             )
-            "#
-        ).execute(&pool).await?;
+            WHERE board_id IS NOT NULL"#,
+            only_board,
+        ))
+            .execute(&mut *tx)
+            .await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS starboard_reactions (
-                id INTEGER PRIMARY KEY,
-                message_id TEXT NOT NULL,
-                user_id TEXT NOT NULL,
-                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                UNIQUE(message_id, user_id)
+        sqlx::query("ALTER TABLE starboard_reactions RENAME TO starboard_reactions_legacy").execute(&mut *tx).await?;
+        sqlx::query(REACTIONS_TABLE).execute(&mut *tx).await?;
+        sqlx::query(&format!(
+            r#"INSERT INTO starboard_reactions (board_id, message_id, user_id, created_at)
+            SELECT board_id, message_id, user_id, created_at
+            FROM (
+                SELECT r.*, COALESCE(
+                    (SELECT m.board_id FROM starboard_messages m WHERE m.original_message_id = r.message_id),
+                    {}
+                ) AS board_id
+                FROM starboard_reactions_legacy r
             )
-            "#
-        ).execute(&pool).await?;
+            WHERE board_id IS NOT NULL"#,
+            only_board,
+        ))
+            .execute(&mut *tx)
+            .await?;
 
+        // stars below the threshold don't say which guild they're from when there's more than
+        // one, they wait on every board and get claimed once the message is starred again
+        sqlx::query(PENDING_REACTIONS_TABLE).execute(&mut *tx).await?;
         sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS starboard_config (
-                guild_id TEXT PRIMARY KEY,
-                starboard_channel_id TEXT,
-                threshold INTEGER DEFAULT 2,
-                star_emoji TEXT DEFAULT '⭐',
-                self_star_allowed BOOLEAN DEFAULT FALSE,
-                enabled BOOLEAN DEFAULT TRUE
-            )
-            "#
-        ).execute(&pool).await?;
+            r#"INSERT OR IGNORE INTO starboard_reactions_pending (board_id, message_id, user_id, created_at)
+            SELECT c.id, r.message_id, r.user_id, r.created_at
+            FROM starboard_reactions_legacy r, starboard_config c
+            WHERE NOT EXISTS (
+                SELECT 1 FROM starboard_reactions s WHERE s.message_id = r.message_id AND s.user_id = r.user_id
+            )"#
+        )
+            .execute(&mut *tx)
+            .await?;
 
-        Ok(Self { pool })
+        sqlx::query("DROP TABLE starboard_config_legacy").execute(&mut *tx).await?;
+        sqlx::query("DROP TABLE starboard_messages_legacy").execute(&mut *tx).await?;
+        sqlx::query("DROP TABLE starboard_reactions_legacy").execute(&mut *tx).await?;
+
+        tx.commit().await
     }
 
-    pub async fn get_starboard_config(&self, guild_id: u64) -> Result<Option<StarboardConfig>, sqlx::Error> {
-        sqlx::query_as::<_, StarboardConfig>(
-            "SELECT * FROM starboard_config WHERE guild_id = ?"
+    /// Moves migrated stars on `message_id` onto the boards of `board_id`'s guild, the copies
+    /// waiting on other guilds' boards were never theirs
+    async fn claim_pending_reactions(
+        tx: &mut sqlx::SqliteConnection,
+        board_id: i64,
+        message_id: u64,
+        channel_id: Option<u64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"INSERT OR IGNORE INTO starboard_reactions (board_id, message_id, channel_id, user_id, created_at)
+            SELECT p.board_id, p.message_id, ?3, p.user_id, p.created_at
+            FROM starboard_reactions_pending p
+            JOIN starboard_config c ON c.id = p.board_id
+            WHERE p.message_id = ?2 AND c.guild_id = (SELECT guild_id FROM starboard_config WHERE id = ?1)"#
+        )
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .bind(channel_id.map(|id| id.to_string()))
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM starboard_reactions_pending WHERE message_id = ?")
+            .bind(message_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        Ok(())
+    }
+
+    fn invalidate_configs(&self) {
+        self.configs.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl StarboardStorage for Database {
+    async fn get_starboard_configs(&self, guild_id: u64) -> Result<Vec<StarboardConfig>, Error> {
+        if let Some(configs) = self.configs.lock().unwrap().get(&guild_id) {
+            return Ok(configs.clone());
+        }
+
+        let configs = sqlx::query_as::<_, StarboardConfig>(
+            "SELECT * FROM starboard_config WHERE guild_id = ? ORDER BY id"
         )
             .bind(guild_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+
+        self.configs.lock().unwrap().insert(guild_id, configs.clone());
+        Ok(configs)
+    }
+
+    async fn get_starboard_config_by_id(&self, board_id: i64) -> Result<Option<StarboardConfig>, Error> {
+        Ok(sqlx::query_as::<_, StarboardConfig>("SELECT * FROM starboard_config WHERE id = ?")
+            .bind(board_id)
             .fetch_optional(&self.pool)
-            .await
+            .await?)
     }
-    /*
-        pub async fn set_starboard_channel(&self, guild_id: u64, channel_id: u64) -> Result<(), sqlx::Error> {
-            sqlx::query(
-                "INSERT OR REPLACE INTO starboard_config (guild_id, starboard_channel_id) VALUES (?, ?)"
-            )
-                .bind(guild_id.to_string())
-                .bind(channel_id.to_string())
-                .execute(&self.pool)
-                .await?;
-            Ok(())
-        }
 
-        pub async fn set_starboard_threshold(&self, guild_id: u64, threshold: i64) -> Result<(), sqlx::Error> {
-            sqlx::query(
-                "INSERT OR REPLACE INTO starboard_config (guild_id, threshold) VALUES (?, ?)"
-            )
-                .bind(guild_id.to_string())
-                .bind(threshold)
-                .execute(&self.pool)
+    /// Inserts a new board or saves an existing one, returns its id
+    async fn update_starboard_config(&self, config: &StarboardConfig) -> Result<i64, Error> {
+        let (id,): (i64,) = sqlx::query_as(
+            "INSERT INTO starboard_config (guild_id, name, starboard_channel_id, threshold, star_emoji, colour, self_star_allowed, enabled, ignore_bots, on_delete,
+                max_age_days, activity_scale, keep_after_days, tiers, fame_stars, fame_channel_id, fame_pin)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(guild_id, name) DO UPDATE SET
+                starboard_channel_id = excluded.starboard_channel_id,
+                threshold = excluded.threshold,
+                star_emoji = excluded.star_emoji,
+                colour = excluded.colour,
+                self_star_allowed = excluded.self_star_allowed,
+                enabled = excluded.enabled,
+                ignore_bots = excluded.ignore_bots,
+                on_delete = excluded.on_delete,
+                max_age_days = excluded.max_age_days,
+                activity_scale = excluded.activity_scale,
+                keep_after_days = excluded.keep_after_days,
+                tiers = excluded.tiers,
+                fame_stars = excluded.fame_stars,
+                fame_channel_id = excluded.fame_channel_id,
+                fame_pin = excluded.fame_pin
+             RETURNING id"
+        )
+            .bind(&config.guild_id)
+            .bind(&config.name)
+            .bind(&config.starboard_channel_id)
+            .bind(config.threshold)
+            .bind(&config.star_emoji)
+            .bind(config.colour)
+            .bind(config.self_star_allowed)
+            .bind(config.enabled)
+            .bind(config.ignore_bots)
+            .bind(config.on_delete)
+            .bind(config.max_age_days)
+            .bind(config.activity_scale)
+            .bind(config.keep_after_days)
+            .bind(&config.tiers)
+            .bind(config.fame_stars)
+            .bind(&config.fame_channel_id)
+            .bind(config.fame_pin)
+            .fetch_one(&self.pool)
+            .await?;
+        self.invalidate_configs();
+        Ok(id)
+    }
+
+    async fn set_starboard_channel(&self, board_id: i64, channel_id: u64) -> Result<(), Error> {
+        sqlx::query("UPDATE starboard_config SET starboard_channel_id = ? WHERE id = ?")
+            .bind(channel_id.to_string())
+            .bind(board_id)
+            .execute(&self.pool)
+            .await?;
+        self.invalidate_configs();
+        Ok(())
+    }
+
+    async fn set_starboard_threshold(&self, board_id: i64, threshold: i64) -> Result<(), Error> {
+        sqlx::query("UPDATE starboard_config SET threshold = ? WHERE id = ?")
+            .bind(threshold)
+            .bind(board_id)
+            .execute(&self.pool)
+            .await?;
+        self.invalidate_configs();
+        Ok(())
+    }
+
+    /// Removes a board along with everything it tracked, the posts themselves stay in discord
+    async fn delete_starboard(&self, board_id: i64) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        for table in ["starboard_reactions", "starboard_reactions_pending", "starboard_messages", "starboard_channel_filters", "starboard_blacklist", "starboard_blocks"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE board_id = ?", table))
+                .bind(board_id)
+                .execute(&mut *tx)
                 .await?;
-            Ok(())
         }
+        sqlx::query("DELETE FROM starboard_config WHERE id = ?")
+            .bind(board_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
 
-        // dont think this is necessary since exec exists, backup tho
-        pub async fn update_starboard_config(&self, config: &StarboardConfig) -> Result<(), sqlx::Error> {
-            sqlx::query(
-                "INSERT OR REPLACE INTO starboard_config (guild_id, starboard_channel_id, threshold, star_emoji, self_star_allowed, enabled)
-                 VALUES (?, ?, ?, ?, ?, ?)"
-            )
-                .bind(&config.guild_id)
-                .bind(&config.starboard_channel_id)
-                .bind(config.threshold)
-                .bind(&config.star_emoji)
-                .bind(config.self_star_allowed)
-                .bind(config.enabled)
-                .execute(&self.pool)
-                .await?;
-            Ok(())
+        self.invalidate_configs();
+        self.filters.lock().unwrap().remove(&board_id);
+        Ok(())
+    }
+
+    async fn get_channel_filters(&self, board_id: i64) -> Result<Vec<ChannelFilter>, Error> {
+        if let Some(filters) = self.filters.lock().unwrap().get(&board_id) {
+            return Ok(filters.clone());
         }
-    */
-    pub async fn get_starred_message(&self, message_id: u64) -> Result<Option<StarredMessage>, sqlx::Error> {
-        sqlx::query_as::<_, StarredMessage>(
-            "SELECT * FROM starboard_messages WHERE original_message_id = ?"
+
+        let filters = sqlx::query_as::<_, ChannelFilter>(
+            "SELECT channel_id, allow FROM starboard_channel_filters WHERE board_id = ?"
         )
+            .bind(board_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        self.filters.lock().unwrap().insert(board_id, filters.clone());
+        Ok(filters)
+    }
+
+    async fn set_channel_filter(&self, board_id: i64, channel_id: u64, allow: bool) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO starboard_channel_filters (board_id, channel_id, allow) VALUES (?, ?, ?)
+             ON CONFLICT(board_id, channel_id) DO UPDATE SET allow = excluded.allow"
+        )
+            .bind(board_id)
+            .bind(channel_id.to_string())
+            .bind(allow)
+            .execute(&self.pool)
+            .await?;
+        self.filters.lock().unwrap().remove(&board_id);
+        Ok(())
+    }
+
+    /// Returns `false` if the channel wasn't listed
+    async fn remove_channel_filter(&self, board_id: i64, channel_id: u64) -> Result<bool, Error> {
+        let result = sqlx::query(
+            "DELETE FROM starboard_channel_filters WHERE board_id = ? AND channel_id = ?"
+        )
+            .bind(board_id)
+            .bind(channel_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        self.filters.lock().unwrap().remove(&board_id);
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_starred_message(&self, board_id: i64, message_id: u64) -> Result<Option<StarredMessage>, Error> {
+        Ok(sqlx::query_as::<_, StarredMessage>(
+            "SELECT * FROM starboard_messages WHERE board_id = ? AND original_message_id = ?"
+        )
+            .bind(board_id)
             .bind(message_id.to_string())
             .fetch_optional(&self.pool)
-            .await
+            .await?)
     }
 
-    pub async fn add_starred_message(&self, message: &StarredMessage) -> Result<(), sqlx::Error> {
+    /// The entry a starboard copy was posted for
+    async fn get_starred_message_by_copy(&self, starboard_message_id: u64) -> Result<Option<StarredMessage>, Error> {
+        Ok(sqlx::query_as::<_, StarredMessage>(
+            "SELECT * FROM starboard_messages WHERE starboard_message_id = ?"
+        )
+            .bind(starboard_message_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    /// Every board's entry for an original message
+    async fn get_starred_messages(&self, message_id: u64) -> Result<Vec<StarredMessage>, Error> {
+        Ok(sqlx::query_as::<_, StarredMessage>(
+            "SELECT * FROM starboard_messages WHERE original_message_id = ?"
+        )
+            .bind(message_id.to_string())
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn add_starred_message(&self, message: &StarredMessage) -> Result<(), Error> {
         sqlx::query(
-            "INSERT INTO starboard_messages (original_message_id, original_channel_id, starboard_message_id, starboard_channel_id, stars, starred_by)
-             VALUES (?, ?, ?, ?, ?, ?)"
+            "INSERT INTO starboard_messages (board_id, original_message_id, original_channel_id, starboard_message_id, starboard_channel_id, stars, starred_by, author_id, forced)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
         )
+            .bind(message.board_id)
             .bind(&message.original_message_id)
             .bind(&message.original_channel_id)
             .bind(&message.starboard_message_id)
             .bind(&message.starboard_channel_id)
             .bind(message.stars)
             .bind(&message.starred_by)
+            .bind(&message.author_id)
+            .bind(message.forced)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    pub async fn update_starred_message(&self, message: &StarredMessage) -> Result<(), sqlx::Error> {
+    async fn update_starred_message(&self, message: &StarredMessage) -> Result<(), Error> {
         sqlx::query(
-            "UPDATE starboard_messages SET stars = ?, starboard_message_id = ? WHERE original_message_id = ?"
+            "UPDATE starboard_messages SET stars = ?, starboard_message_id = ? WHERE board_id = ? AND original_message_id = ?"
         )
             .bind(message.stars)
             .bind(&message.starboard_message_id)
+            .bind(message.board_id)
             .bind(&message.original_message_id)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    pub async fn delete_starred_message(&self, message_id: u64) -> Result<(), sqlx::Error> {
-        sqlx::query("DELETE FROM starboard_messages WHERE original_message_id = ?")
+    async fn delete_starred_message(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        sqlx::query("DELETE FROM starboard_messages WHERE board_id = ? AND original_message_id = ?")
+            .bind(board_id)
             .bind(message_id.to_string())
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    pub async fn add_star_reaction(&self, message_id: u64, user_id: u64) -> Result<(), sqlx::Error> {
+    /// Returns `false` if the message isn't on the board
+    async fn set_frozen(&self, board_id: i64, message_id: u64, frozen: bool) -> Result<bool, Error> {
+        let result = sqlx::query("UPDATE starboard_messages SET frozen = ? WHERE board_id = ? AND original_message_id = ?")
+            .bind(frozen)
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_famed(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        sqlx::query("UPDATE starboard_messages SET famed = TRUE WHERE board_id = ? AND original_message_id = ?")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_forced(&self, board_id: i64, message_id: u64, forced: bool) -> Result<(), Error> {
+        sqlx::query("UPDATE starboard_messages SET forced = ? WHERE board_id = ? AND original_message_id = ?")
+            .bind(forced)
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn is_blacklisted(&self, board_id: i64, message_id: u64) -> Result<bool, Error> {
+        let result = sqlx::query("SELECT 1 FROM starboard_blacklist WHERE board_id = ? AND message_id = ?")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(result.is_some())
+    }
+
+    async fn blacklist_message(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        sqlx::query("INSERT OR IGNORE INTO starboard_blacklist (board_id, message_id) VALUES (?, ?)")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns `false` if the message wasn't blacklisted
+    async fn unblacklist_message(&self, board_id: i64, message_id: u64) -> Result<bool, Error> {
+        let result = sqlx::query("DELETE FROM starboard_blacklist WHERE board_id = ? AND message_id = ?")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_block(&self, board_id: i64, user_id: u64) -> Result<Option<BlockMode>, Error> {
+        let row: Option<(BlockMode,)> = sqlx::query_as("SELECT mode FROM starboard_blocks WHERE board_id = ? AND user_id = ?")
+            .bind(board_id)
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|(mode,)| mode))
+    }
+
+    async fn block_user(&self, board_id: i64, user_id: u64, mode: BlockMode) -> Result<(), Error> {
         sqlx::query(
-            "INSERT OR IGNORE INTO starboard_reactions (message_id, user_id) VALUES (?, ?)"
+            "INSERT INTO starboard_blocks (board_id, user_id, mode) VALUES (?, ?, ?)
+             ON CONFLICT(board_id, user_id) DO UPDATE SET mode = excluded.mode"
         )
-            .bind(message_id.to_string())
+            .bind(board_id)
             .bind(user_id.to_string())
+            .bind(mode)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    pub async fn remove_star_reaction(&self, message_id: u64, user_id: u64) -> Result<(), sqlx::Error> {
+    /// Returns `false` if the user wasn't blocked
+    async fn unblock_user(&self, board_id: i64, user_id: u64) -> Result<bool, Error> {
+        let result = sqlx::query("DELETE FROM starboard_blocks WHERE board_id = ? AND user_id = ?")
+            .bind(board_id)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Users whose stars don't count on a board
+    async fn star_blocked_users(&self, board_id: i64) -> Result<Vec<u64>, Error> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT user_id FROM starboard_blocks WHERE board_id = ? AND mode IN ('stars', 'both')"
+        )
+            .bind(board_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().filter_map(|(user_id,)| user_id.parse().ok()).collect())
+    }
+
+    /// Stars are always stored against the original message, `source` says where the reaction
+    /// is. Returns the board's star count for the message, read by the upsert itself so
+    /// concurrent stars can't both see the same count
+    async fn add_star_reaction(
+        &self,
+        board_id: i64,
+        message_id: u64,
+        channel_id: u64,
+        user_id: u64,
+        source: StarSource,
+    ) -> Result<i64, Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::claim_pending_reactions(&mut tx, board_id, message_id, Some(channel_id)).await?;
+        let (count,): (i64,) = sqlx::query_as(
+            "INSERT INTO starboard_reactions (board_id, message_id, channel_id, user_id, sources) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(board_id, message_id, user_id) DO UPDATE SET sources = sources | excluded.sources
+             RETURNING (SELECT COUNT(*) FROM starboard_reactions WHERE board_id = ?1 AND message_id = ?2)"
+        )
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .bind(channel_id.to_string())
+            .bind(user_id.to_string())
+            .bind(source.bit())
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    /// The star only goes away once the user has no reaction left on either message, returns
+    /// the count left afterwards
+    async fn remove_star_reaction(&self, board_id: i64, message_id: u64, user_id: u64, source: StarSource) -> Result<i64, Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::claim_pending_reactions(&mut tx, board_id, message_id, None).await?;
         sqlx::query(
-            "DELETE FROM starboard_reactions WHERE message_id = ? AND user_id = ?"
+            "UPDATE starboard_reactions SET sources = sources & ~?4 WHERE board_id = ?1 AND message_id = ?2 AND user_id = ?3"
         )
+            .bind(board_id)
             .bind(message_id.to_string())
             .bind(user_id.to_string())
+            .bind(source.bit())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "DELETE FROM starboard_reactions WHERE board_id = ?1 AND message_id = ?2 AND user_id = ?3 AND sources = 0"
+        )
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .bind(user_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM starboard_reactions WHERE board_id = ? AND message_id = ?"
+        )
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    /// Drops the copy's side of a board's stars, used when discord clears the copy's reactions
+    async fn clear_copy_reactions(&self, board_id: i64, message_id: u64) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE starboard_reactions SET sources = sources & ~?3 WHERE board_id = ?1 AND message_id = ?2")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .bind(StarSource::Copy.bit())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM starboard_reactions WHERE board_id = ?1 AND message_id = ?2 AND sources = 0")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Swaps a board's reaction rows for a message with the users discord actually reports on
+    /// the original and on the starboard copy
+    async fn replace_star_reactions(
+        &self,
+        board_id: i64,
+        message_id: u64,
+        channel_id: u64,
+        original: &[u64],
+        copy: &[u64],
+    ) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM starboard_reactions WHERE board_id = ? AND message_id = ?")
+            .bind(board_id)
+            .bind(message_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        let sources = original.iter().map(|user_id| (user_id, StarSource::Original))
+            .chain(copy.iter().map(|user_id| (user_id, StarSource::Copy)));
+        for (user_id, source) in sources {
+            sqlx::query(
+                "INSERT INTO starboard_reactions (board_id, message_id, channel_id, user_id, sources) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(board_id, message_id, user_id) DO UPDATE SET sources = sources | excluded.sources"
+            )
+                .bind(board_id)
+                .bind(message_id.to_string())
+                .bind(channel_id.to_string())
+                .bind(user_id.to_string())
+                .bind(source.bit())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Boards that have reactions or a post stored for a message
+    async fn boards_tracking(&self, message_id: u64) -> Result<Vec<i64>, Error> {
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            "SELECT board_id FROM starboard_reactions WHERE message_id = ?1
+             UNION SELECT board_id FROM starboard_messages WHERE original_message_id = ?1"
+        )
+            .bind(message_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|(board_id,)| board_id).collect())
+    }
+
+    /// (guild, channel, message) for everything starred since `since`, used by the startup rescan
+    async fn tracked_messages_since(&self, since: &str) -> Result<Vec<(String, String, String)>, Error> {
+        Ok(sqlx::query_as(
+            r#"SELECT DISTINCT c.guild_id, t.channel_id, t.message_id FROM (
+                SELECT board_id, channel_id, message_id FROM starboard_reactions
+                WHERE channel_id IS NOT NULL AND created_at >= ?1
+                UNION
+                SELECT board_id, original_channel_id, original_message_id FROM starboard_messages
+                WHERE created_at >= ?1
+            ) t
+            JOIN starboard_config c ON c.id = t.board_id
+            WHERE c.enabled = TRUE"#
+        )
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    /// Drops every board's reactions for a message, used when discord clears them all
+    async fn clear_star_reactions(&self, message_id: u64) -> Result<(), Error> {
+        sqlx::query("DELETE FROM starboard_reactions WHERE message_id = ?")
+            .bind(message_id.to_string())
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    pub async fn count_star_reactions(&self, message_id: u64) -> Result<i64, sqlx::Error> {
+    /// Drops every board's entry and reactions for a message that no longer exists
+    async fn forget_message(&self, message_id: u64) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM starboard_reactions WHERE message_id = ?")
+            .bind(message_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM starboard_messages WHERE original_message_id = ?")
+            .bind(message_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn count_star_reactions(&self, board_id: i64, message_id: u64) -> Result<i64, Error> {
         let count: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM starboard_reactions WHERE message_id = ?"
+            "SELECT COUNT(*) FROM starboard_reactions WHERE board_id = ? AND message_id = ?"
         )
+            .bind(board_id)
             .bind(message_id.to_string())
             .fetch_one(&self.pool)
             .await?;
         Ok(count.0)
     }
 
+    /// Most starred posts in the scope, best first
+    async fn top_messages(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarredMessage>, Error> {
+        Ok(sqlx::query_as::<_, StarredMessage>(&format!(
+            "SELECT * FROM starboard_messages m WHERE {} ORDER BY stars DESC, created_at DESC LIMIT ?4",
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    /// Authors by stars on their starboard posts
+    async fn top_receivers(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarTally>, Error> {
+        Ok(sqlx::query_as::<_, StarTally>(&format!(
+            r#"SELECT author_id AS user_id, SUM(stars) AS stars, COUNT(*) AS messages
+            FROM starboard_messages m
+            WHERE {} AND author_id IS NOT NULL
+            GROUP BY author_id ORDER BY stars DESC LIMIT ?4"#,
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    /// Users by stars handed out, `messages` is how many distinct messages they starred
+    async fn top_givers(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarTally>, Error> {
+        Ok(sqlx::query_as::<_, StarTally>(&format!(
+            r#"SELECT user_id, COUNT(*) AS stars, COUNT(DISTINCT message_id) AS messages
+            FROM starboard_reactions m
+            WHERE {}
+            GROUP BY user_id ORDER BY stars DESC LIMIT ?4"#,
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    /// (stars received, posts on the board, stars given)
+    async fn user_stats(&self, scope: &StatsScope, user_id: u64) -> Result<(i64, i64, i64), Error> {
+        let (received, posts): (i64, i64) = sqlx::query_as(&format!(
+            "SELECT COALESCE(SUM(stars), 0), COUNT(*) FROM starboard_messages m WHERE {} AND author_id = ?4",
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .bind(user_id.to_string())
+            .fetch_one(&self.pool)
+            .await?;
+
+        let (given,): (i64,) = sqlx::query_as(&format!(
+            "SELECT COUNT(*) FROM starboard_reactions m WHERE {} AND user_id = ?4",
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .bind(user_id.to_string())
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((received, posts, given))
+    }
+
+    async fn user_top_messages(&self, scope: &StatsScope, user_id: u64, limit: i64) -> Result<Vec<StarredMessage>, Error> {
+        Ok(sqlx::query_as::<_, StarredMessage>(&format!(
+            "SELECT * FROM starboard_messages m WHERE {} AND author_id = ?4 ORDER BY stars DESC LIMIT ?5",
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .bind(user_id.to_string())
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn random_starred_message(&self, scope: &StatsScope) -> Result<Option<StarredMessage>, Error> {
+        Ok(sqlx::query_as::<_, StarredMessage>(&format!(
+            "SELECT * FROM starboard_messages m WHERE {} AND starboard_message_id IS NOT NULL ORDER BY RANDOM() LIMIT 1",
+            SCOPED_MESSAGES,
+        ))
+            .bind(&scope.guild_id)
+            .bind(scope.board_id)
+            .bind(&scope.since)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+}
+
+impl Database {
     // no functionality its way too late to do it now
-    pub async fn has_user_starred(&self, message_id: u64, user_id: u64) -> Result<bool, sqlx::Error> {
+    pub async fn has_user_starred(&self, board_id: i64, message_id: u64, user_id: u64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query(
-            "SELECT 1 FROM starboard_reactions WHERE message_id = ? AND user_id = ? LIMIT 1"
+            "SELECT 1 FROM starboard_reactions WHERE board_id = ? AND message_id = ? AND user_id = ? LIMIT 1"
         )
+            .bind(board_id)
             .bind(message_id.to_string())
             .bind(user_id.to_string())
             .fetch_optional(&self.pool)
@@ -190,4 +909,3 @@ impl Database {
         Ok(result.is_some())
     }
 }
-
@@ -0,0 +1,56 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+
+use crate::helpers::starboard::StarboardConfig;
+
+// activity is counted per minute over the last hour, in memory so it starts over on restart
+const WINDOW_MINUTES: i64 = 60;
+// scaling never asks for more than this many times the board threshold
+pub(crate) const MAX_SCALE: i64 = 3;
+
+// (minute, messages) oldest first
+type Buckets = VecDeque<(i64, usize)>;
+
+static ACTIVITY: LazyLock<Mutex<HashMap<serenity::ChannelId, Buckets>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn current_minute() -> i64 {
+    Utc::now().timestamp() / 60
+}
+
+pub(crate) fn record_message(channel_id: serenity::ChannelId) {
+    let minute = current_minute();
+    let mut activity = ACTIVITY.lock().unwrap();
+
+    let buckets = activity.entry(channel_id).or_default();
+    match buckets.back_mut() {
+        Some((last, count)) if *last == minute => *count += 1,
+        _ => buckets.push_back((minute, 1)),
+    }
+    while buckets.front().is_some_and(|(start, _)| *start <= minute - WINDOW_MINUTES) {
+        buckets.pop_front();
+    }
+}
+
+/// Messages seen in a channel over the last hour
+pub(crate) fn recent_messages(channel_id: serenity::ChannelId) -> usize {
+    let cutoff = current_minute() - WINDOW_MINUTES;
+    ACTIVITY.lock().unwrap()
+        .get(&channel_id)
+        .map(|buckets| buckets.iter().filter(|(minute, _)| *minute > cutoff).map(|(_, count)| count).sum())
+        .unwrap_or(0)
+}
+
+/// Stars a message in `channel_id` needs to get posted, busy channels add a star for every
+/// `activity_scale` messages in the last hour
+pub(crate) fn scaled_threshold(config: &StarboardConfig, channel_id: serenity::ChannelId) -> i64 {
+    let Some(per_star) = config.activity_scale.filter(|per_star| *per_star > 0) else {
+        return config.threshold;
+    };
+
+    let extra = recent_messages(channel_id) as i64 / per_star;
+    (config.threshold + extra).min(config.threshold * MAX_SCALE)
+}
@@ -1,5 +1,13 @@
-use crate::helpers::starboard::{Database, StarboardConfig, StarredMessage};
+use crate::helpers::starboard::{ChannelFilter, DeletePolicy, StarSource, StarboardConfig, StarredMessage};
+use crate::helpers::starboard_activity::scaled_threshold;
+use crate::helpers::starboard_render::{jump_button, render_starboard_post, star_colour};
+use crate::helpers::starboard_state::{PendingEdit, StarboardState};
 use poise::serenity_prelude as serenity;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+
+// count edits wait this long so a burst of stars ends up as one edit
+const EDIT_DELAY: Duration = Duration::from_secs(2);
 
 pub(crate) async fn handle_reaction_add(
     ctx: &serenity::Context,
@@ -11,46 +19,193 @@ pub(crate) async fn handle_reaction_add(
         None => return Ok(()),
     };
 
+    let boards = matching_boards(data, guild_id, &add_reaction.emoji).await?;
+    if boards.is_empty() {
+        return Ok(());
+    }
 
-    let config = match data.starboard.get_starboard_config(guild_id.into()).await? {
-        Some(config) => config,
-        None => return Ok(()),
-    };
+    let message_id = add_reaction.message_id.into();
+    let user_id = add_reaction.user_id.unwrap().into();
 
-    if !config.enabled {
-        return Ok(());
+    // stars on a starboard post count towards the message it's a copy of
+    if let Some(starred) = data.starboard.get_starred_message_by_copy(message_id).await? {
+        return handle_copy_reaction_add(ctx, add_reaction, data, &boards, starred).await;
     }
 
-    let emoji_string = add_reaction.emoji.to_string();
-    if emoji_string != config.star_emoji {
+    // everything that can rule a board out without http happens first
+    let source = source_channel(ctx, add_reaction.guild_id.unwrap(), add_reaction.channel_id).await?;
+    let author_is_bot = add_reaction.message_author_id
+        .and_then(|author_id| ctx.cache.user(author_id).map(|user| user.bot));
+
+    let mut accepting = Vec::new();
+    for config in boards {
+        if author_is_bot == Some(true) && config.ignore_bots {
+            continue;
+        }
+        let filters = data.starboard.get_channel_filters(config.id).await?;
+        if accepts_source(ctx, &config, &filters, &source).await {
+            accepting.push(config);
+        }
+    }
+    if accepting.is_empty() {
         return Ok(());
     }
 
-    let message_id = add_reaction.message_id.into();
-    let user_id = add_reaction.user_id.unwrap().into();
+    // the author comes with the event, the message itself is only fetched once it has to be posted
+    let mut message = None;
+    let (author_id, author_is_bot) = match (add_reaction.message_author_id, author_is_bot) {
+        (Some(author_id), is_bot) if is_bot.is_some() || accepting.iter().all(|config| !config.ignore_bots) => {
+            (author_id, is_bot.unwrap_or(false))
+        }
+        _ => {
+            let fetched = add_reaction.message(&ctx.http).await?;
+            let author = (fetched.author.id, fetched.author.bot);
+            message = Some(fetched);
+            author
+        }
+    };
+    let is_self_star = author_id.get() == user_id;
 
+    let boards: Vec<StarboardConfig> = accepting
+        .into_iter()
+        .filter(|config| !(author_is_bot && config.ignore_bots))
+        .collect();
 
-    let message = add_reaction.message(&ctx.http).await?;
-    if message.author.id.get() == user_id && !config.self_star_allowed {
+    // the reaction is shared by every board using the emoji, only drop it if none accept it
+    if is_self_star && !boards.is_empty() && boards.iter().all(|config| !config.self_star_allowed) {
         add_reaction.delete(&ctx.http).await?;
         return Ok(());
     }
 
-    data.starboard.add_star_reaction(message_id, user_id).await?;
-
-    let star_count = data.starboard.count_star_reactions(message_id).await?;
+    for config in boards.iter().filter(|config| config.self_star_allowed || !is_self_star) {
+        if message_blocked(data, config.id, message_id, author_id.get()).await?
+            || stars_blocked(data, config.id, user_id).await?
+        {
+            continue;
+        }
 
-    if star_count >= config.threshold {
-        let _lock = data.starboard_lock.lock().await;
-        let star_count = data.starboard.count_star_reactions(message_id).await?;
-        if star_count >= config.threshold {
-            update_starboard_message(ctx, &data.starboard, &config, &message, guild_id, star_count).await?;
+        let _lock = data.starboard_state.lock_message(message_id).await;
+        let star_count = data.starboard.add_star_reaction(config.id, message_id, add_reaction.channel_id.get(), user_id, StarSource::Original).await?;
+
+        match data.starboard.get_starred_message(config.id, message_id).await? {
+            Some(starred) => apply_star_count(ctx, data, config, starred, star_count).await?,
+            None if post_action(config, None, star_count, add_reaction.channel_id, add_reaction.message_id) == PostAction::Post => {
+                let message = match &message {
+                    Some(message) => message,
+                    None => message.insert(add_reaction.message(&ctx.http).await?),
+                };
+                update_starboard_message(ctx, data, config, message, guild_id, star_count).await?;
+            }
+            None => {}
         }
     }
 
     Ok(())
 }
 
+async fn handle_copy_reaction_add(
+    ctx: &serenity::Context,
+    add_reaction: &serenity::Reaction,
+    data: &crate::Data,
+    boards: &[StarboardConfig],
+    starred: StarredMessage,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // a different board's emoji on the post, nothing to count
+    let Some(config) = boards.iter().find(|config| config.id == starred.board_id) else {
+        return Ok(());
+    };
+
+    let user_id = add_reaction.user_id.unwrap();
+    let original_message_id = starred.original_message_id.parse::<u64>()?;
+    let original_channel_id = starred.original_channel_id.parse::<u64>()?;
+
+    if !config.self_star_allowed && original_author(ctx, &starred).await == Some(user_id) {
+        add_reaction.delete(&ctx.http).await?;
+        return Ok(());
+    }
+    if stars_blocked(data, config.id, user_id.get()).await? {
+        return Ok(());
+    }
+
+    let _lock = data.starboard_state.lock_message(original_message_id).await;
+    let star_count = data.starboard.add_star_reaction(config.id, original_message_id, original_channel_id, user_id.get(), StarSource::Copy).await?;
+    apply_if_posted(ctx, data, config, original_message_id, star_count).await
+}
+
+/// Blacklisted messages and messages by authors blocked from a board never get posted on it
+pub(crate) async fn message_blocked(
+    data: &crate::Data,
+    board_id: i64,
+    message_id: u64,
+    author_id: u64,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    if data.starboard.is_blacklisted(board_id, message_id).await? {
+        return Ok(true);
+    }
+    Ok(data.starboard.get_block(board_id, author_id).await?.is_some_and(|mode| mode.blocks_messages()))
+}
+
+async fn stars_blocked(
+    data: &crate::Data,
+    board_id: i64,
+    user_id: u64,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(data.starboard.get_block(board_id, user_id).await?.is_some_and(|mode| mode.blocks_stars()))
+}
+
+/// What a board should do with a message's post after its count changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PostAction {
+    Post,
+    Update,
+    Remove,
+    Nothing,
+}
+
+/// Decides a message's post from its new count, `starred` is the board's entry if it has one
+pub(crate) fn post_action(
+    config: &StarboardConfig,
+    starred: Option<&StarredMessage>,
+    star_count: i64,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+) -> PostAction {
+    // the age limit and activity scaling only decide what goes up, posts already there keep counting
+    let Some(starred) = starred else {
+        return if star_count >= scaled_threshold(config, channel_id) && !config.is_too_old(message_id) {
+            PostAction::Post
+        } else {
+            PostAction::Nothing
+        };
+    };
+
+    if starred.frozen {
+        PostAction::Nothing
+    } else if star_count < config.threshold && !starred.forced && !past_keep_age(config, starred) {
+        PostAction::Remove
+    } else {
+        PostAction::Update
+    }
+}
+
+/// Whether a post is old enough that losing stars no longer takes it down
+pub(crate) fn past_keep_age(config: &StarboardConfig, starred: &StarredMessage) -> bool {
+    starred.starboard_message_id.as_deref()
+        .and_then(|id| id.parse::<serenity::MessageId>().ok())
+        .is_some_and(|id| config.keeps_post(id))
+}
+
+/// Author of a posted message, rows from before authors were stored need a fetch
+async fn original_author(ctx: &serenity::Context, starred: &StarredMessage) -> Option<serenity::UserId> {
+    if let Some(author_id) = starred.author_id.as_deref().and_then(|id| id.parse().ok()) {
+        return Some(author_id);
+    }
+
+    let channel_id = starred.original_channel_id.parse::<serenity::ChannelId>().ok()?;
+    let message_id = starred.original_message_id.parse::<serenity::MessageId>().ok()?;
+    channel_id.message(&ctx.http, message_id).await.ok().map(|message| message.author.id)
+}
+
 pub(crate) async fn handle_reaction_remove(
     ctx: &serenity::Context,
     removed_reaction: &serenity::Reaction,
@@ -61,105 +216,513 @@ pub(crate) async fn handle_reaction_remove(
         None => return Ok(()),
     };
 
-    let config = match data.starboard.get_starboard_config(guild_id.into()).await? {
-        Some(config) => config,
-        None => return Ok(()),
-    };
-
-    if !config.enabled {
-        return Ok(());
-    }
-
-    let emoji_string = removed_reaction.emoji.to_string();
-    if emoji_string != config.star_emoji {
+    let boards = matching_boards(data, guild_id.get(), &removed_reaction.emoji).await?;
+    if boards.is_empty() {
         return Ok(());
     }
 
     let message_id = removed_reaction.message_id.into();
     let user_id = removed_reaction.user_id.unwrap().into();
 
-    data.starboard.remove_star_reaction(message_id, user_id).await?;
+    if let Some(starred) = data.starboard.get_starred_message_by_copy(message_id).await? {
+        let Some(config) = boards.iter().find(|config| config.id == starred.board_id) else {
+            return Ok(());
+        };
+        let original_message_id = starred.original_message_id.parse::<u64>()?;
+
+        let _lock = data.starboard_state.lock_message(original_message_id).await;
+        let star_count = data.starboard.remove_star_reaction(config.id, original_message_id, user_id, StarSource::Copy).await?;
+        return apply_if_posted(ctx, data, config, original_message_id, star_count).await;
+    }
+
+    for config in &boards {
+        let _lock = data.starboard_state.lock_message(message_id).await;
+        let star_count = data.starboard.remove_star_reaction(config.id, message_id, user_id, StarSource::Original).await?;
+        apply_if_posted(ctx, data, config, message_id, star_count).await?;
+    }
 
-    let star_count = data.starboard.count_star_reactions(message_id).await?;
+    Ok(())
+}
 
-    let starred_message = data.starboard.get_starred_message(message_id).await?;
+/// Recounts a posted message's stars and updates or takes down its post
+pub(crate) async fn refresh_starred_message(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    config: &StarboardConfig,
+    original_message_id: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let _lock = data.starboard_state.lock_message(original_message_id).await;
+    let star_count = data.starboard.count_star_reactions(config.id, original_message_id).await?;
+    apply_if_posted(ctx, data, config, original_message_id, star_count).await
+}
 
-    match starred_message {
-        Some(mut starred) => {
-            if star_count >= config.threshold {
-                starred.stars = star_count;
-                data.starboard.update_starred_message(&starred).await?;
+/// Only posted messages have anything to update, the caller holds the message's lock
+async fn apply_if_posted(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    config: &StarboardConfig,
+    original_message_id: u64,
+    star_count: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match data.starboard.get_starred_message(config.id, original_message_id).await? {
+        Some(starred) => apply_star_count(ctx, data, config, starred, star_count).await,
+        None => Ok(()),
+    }
+}
 
-                if let (Some(starboard_channel_id), Some(starboard_message_id)) =
-                    (&starred.starboard_channel_id, &starred.starboard_message_id)
-                {
-                    let starboard_channel = starboard_channel_id.parse::<u64>()?;
-                    let starboard_message_id = starboard_message_id.parse::<u64>()?;
-
-                    update_existing_starboard_message(
-                        ctx,
-                        starboard_channel,
-                        starboard_message_id,
-                        star_count,
-                        &config.star_emoji,
-                    ).await?;
-                }
-            } else {
-                // If below threshold, delete from starboard and database
-                if let (Some(starboard_channel_id), Some(starboard_message_id)) =
-                    (&starred.starboard_channel_id, &starred.starboard_message_id)
-                {
-                    let starboard_channel = starboard_channel_id.parse::<u64>()?;
-                    let starboard_message_id = starboard_message_id.parse::<u64>()?;
-
-                    delete_starboard_message(
-                        ctx,
-                        starboard_channel,
-                        starboard_message_id,
-                    ).await?;
-                }
+/// Saves a posted message's new count and edits the post, or takes it down below the threshold
+/// unless a moderator forced it up
+async fn apply_star_count(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    config: &StarboardConfig,
+    mut starred: StarredMessage,
+    star_count: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let channel_id = starred.original_channel_id.parse()?;
+    let message_id = starred.original_message_id.parse()?;
 
-                data.starboard.delete_starred_message(message_id).await?;
-            }
+    match post_action(config, Some(&starred), star_count, channel_id, message_id) {
+        PostAction::Remove => remove_starboard_entry(ctx, data, &starred).await,
+        PostAction::Update => save_star_count(ctx, data, config, &mut starred, star_count).await,
+        PostAction::Post | PostAction::Nothing => Ok(()),
+    }
+}
+
+/// Stores the count right away, the post itself catches up through a debounced edit
+async fn save_star_count(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    config: &StarboardConfig,
+    starred: &mut StarredMessage,
+    star_count: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if starred.stars == star_count {
+        return promote_to_fame(ctx, data, config, starred, star_count).await;
+    }
+
+    starred.stars = star_count;
+    data.starboard.update_starred_message(starred).await?;
+
+    if let (Some(starboard_channel_id), Some(starboard_message_id)) =
+        (&starred.starboard_channel_id, &starred.starboard_message_id)
+    {
+        schedule_post_update(ctx, &data.starboard_state, starboard_message_id.parse()?, PendingEdit {
+            original_message_id: starred.original_message_id.parse()?,
+            channel_id: starboard_channel_id.parse()?,
+            star_count,
+            config: config.clone(),
+        });
+    }
+
+    promote_to_fame(ctx, data, config, starred, star_count).await
+}
+
+/// Queues the newest count for a post, the first star of a burst schedules the edit and the
+/// rest only replace the count it will write
+fn schedule_post_update(
+    ctx: &serenity::Context,
+    state: &Arc<StarboardState>,
+    starboard_message_id: serenity::MessageId,
+    edit: PendingEdit,
+) {
+    let original_message_id = edit.original_message_id;
+    if !state.queue_edit(starboard_message_id, edit) {
+        return;
+    }
+
+    let ctx = ctx.clone();
+    let state = Arc::clone(state);
+    tokio::spawn(async move {
+        sleep(EDIT_DELAY).await;
+
+        let _lock = state.lock_message(original_message_id).await;
+        // the post may have been taken down in the meantime
+        let Some(edit) = state.take_edit(starboard_message_id) else {
+            return;
+        };
+        if let Err(e) = update_existing_starboard_message(
+            &ctx, &state, edit.channel_id, starboard_message_id, edit.star_count, &edit.config,
+        ).await {
+            eprintln!("Error updating starboard post {}: {}", starboard_message_id, e);
         }
-        None => {
+    });
+}
+
+/// Pins the post and copies it to the hall of fame channel the first time it reaches
+/// `fame_stars`, a failure is only logged so it doesn't retry on every star
+async fn promote_to_fame(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    config: &StarboardConfig,
+    starred: &StarredMessage,
+    star_count: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if starred.famed || config.fame_stars.is_none_or(|fame_stars| star_count < fame_stars) {
+        return Ok(());
+    }
+    let (Some(starboard_channel_id), Some(starboard_message_id)) =
+        (&starred.starboard_channel_id, &starred.starboard_message_id)
+    else {
+        return Ok(());
+    };
+
+    let channel = serenity::ChannelId::new(starboard_channel_id.parse()?);
+    let message = serenity::MessageId::new(starboard_message_id.parse()?);
+
+    if config.fame_pin
+        && let Err(e) = channel.pin(&ctx.http, message).await
+    {
+        eprintln!("Error pinning starboard post {}: {}", message, e);
+    }
+
+    if let Some(fame_channel_id) = &config.fame_channel_id {
+        let fame_channel = serenity::ChannelId::new(fame_channel_id.parse()?);
+        let embeds = match data.starboard_state.cached_post(message) {
+            Some(embeds) => Ok(embeds),
+            None => channel.message(&ctx.http, message).await
+                .map(|post| post.embeds.into_iter().map(serenity::CreateEmbed::from).collect()),
+        };
+        let result = match embeds {
+            Ok(embeds) => {
+                let copy = serenity::CreateMessage::new()
+                    .embeds(embeds)
+                    .components(vec![jump_button(starred.link(config.guild_id.parse()?))]);
+                fame_channel.send_message(&ctx.http, copy).await.map(|_| ())
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Error posting {} to the hall of fame: {}", message, e);
         }
     }
 
+    data.starboard.set_famed(config.id, starred.original_message_id.parse()?).await?;
     Ok(())
 }
 
 pub(crate) async fn handle_reaction_remove_all(
     ctx: &serenity::Context,
-    channel_id: serenity::ChannelId,
+    _channel_id: serenity::ChannelId,
     message_id: serenity::MessageId,
     data: &crate::Data,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let starred_message = data.starboard.get_starred_message(message_id.into()).await?;
+    // cleared on the copy, the stars left on the original still stand
+    if let Some(starred) = data.starboard.get_starred_message_by_copy(message_id.get()).await? {
+        let Some(config) = data.starboard.get_starboard_config_by_id(starred.board_id).await? else {
+            return Ok(());
+        };
+        let original_message_id = starred.original_message_id.parse::<u64>()?;
+
+        let _lock = data.starboard_state.lock_message(original_message_id).await;
+        data.starboard.clear_copy_reactions(config.id, original_message_id).await?;
+        let star_count = data.starboard.count_star_reactions(config.id, original_message_id).await?;
+        return apply_star_count(ctx, data, &config, starred, star_count).await;
+    }
+
+    let _lock = data.starboard_state.lock_message(message_id.get()).await;
+    for starred in data.starboard.get_starred_messages(message_id.into()).await? {
+        remove_starboard_entry(ctx, data, &starred).await?;
+    }
+
+    data.starboard.clear_star_reactions(message_id.into()).await?;
+
+    Ok(())
+}
+
+/// Re-renders the starboard posts of an edited message, link previews showing up late land
+/// here as well
+pub(crate) async fn handle_message_update(
+    ctx: &serenity::Context,
+    event: &serenity::MessageUpdateEvent,
+    data: &crate::Data,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if event.content.is_none() && event.attachments.is_none() && event.embeds.is_none() {
+        return Ok(());
+    }
+    let guild_id = match event.guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let tracked = data.starboard.get_starred_messages(event.id.get()).await?;
+    if tracked.is_empty() {
+        return Ok(());
+    }
+
+    // the update event only carries what changed, so render from the full message
+    let message = event.channel_id.message(&ctx.http, event.id).await?;
+    let boards = data.starboard.get_starboard_configs(guild_id.get()).await?;
 
-    if let Some(starred) = starred_message {
-        if let (Some(starboard_channel_id), Some(starboard_message_id)) =
+    let _lock = data.starboard_state.lock_message(event.id.get()).await;
+    for starred in &tracked {
+        let Some(config) = boards.iter().find(|config| config.id == starred.board_id) else {
+            continue;
+        };
+        let (Some(starboard_channel_id), Some(starboard_message_id)) =
             (&starred.starboard_channel_id, &starred.starboard_message_id)
-        {
-            let starboard_channel = starboard_channel_id.parse::<u64>()?;
-            let starboard_message_id = starboard_message_id.parse::<u64>()?;
-
-            delete_starboard_message(
-                ctx,
-                starboard_channel,
-                starboard_message_id,
-            ).await?;
+        else {
+            continue;
+        };
+
+        let channel = serenity::ChannelId::new(starboard_channel_id.parse()?);
+        let starboard_message_id = serenity::MessageId::new(starboard_message_id.parse()?);
+        let post = render_starboard_post(&message, guild_id.get(), starred.stars, config);
+        let embeds = post.embeds.clone();
+        match channel.edit_message(&ctx.http, starboard_message_id, post.edit_message()).await {
+            Ok(_) => data.starboard_state.cache_post(starboard_message_id, embeds),
+            Err(e) => eprintln!("Error updating starboard post for edited message {}: {}", event.id, e),
         }
+    }
 
-        data.starboard.delete_starred_message(message_id.into()).await?;
+    Ok(())
+}
+
+/// Applies each board's delete policy to the posts of deleted messages and forgets them,
+/// a deleted starboard post only loses its entry so it can be posted again
+pub(crate) async fn handle_message_delete(
+    ctx: &serenity::Context,
+    guild_id: Option<serenity::GuildId>,
+    message_ids: &[serenity::MessageId],
+    data: &crate::Data,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let guild_id = match guild_id {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let mut boards: Option<Vec<StarboardConfig>> = None;
+
+    for message_id in message_ids {
+        if let Some(copy) = data.starboard.get_starred_message_by_copy(message_id.get()).await? {
+            forget_starboard_copy(data, &copy).await?;
+            continue;
+        }
+
+        let tracked = data.starboard.get_starred_messages(message_id.get()).await?;
+
+        let _lock = data.starboard_state.lock_message(message_id.get()).await;
+        if !tracked.is_empty() {
+            if boards.is_none() {
+                boards = Some(data.starboard.get_starboard_configs(guild_id.get()).await?);
+            }
+            let boards = boards.as_deref().unwrap_or_default();
+
+            for starred in &tracked {
+                let policy = boards.iter()
+                    .find(|config| config.id == starred.board_id)
+                    .map(|config| config.on_delete)
+                    .unwrap_or(DeletePolicy::Keep);
+
+                let (Some(starboard_channel_id), Some(starboard_message_id)) =
+                    (&starred.starboard_channel_id, &starred.starboard_message_id)
+                else {
+                    continue;
+                };
+                let starboard_channel_id = starboard_channel_id.parse::<u64>()?;
+                let starboard_message_id = starboard_message_id.parse::<u64>()?;
+                data.starboard_state.forget_post(starboard_message_id.into());
+
+                let result = match policy {
+                    DeletePolicy::Keep => Ok(()),
+                    DeletePolicy::Mark => mark_starboard_message_deleted(ctx, starboard_channel_id, starboard_message_id).await,
+                    DeletePolicy::Remove => delete_starboard_message(ctx, starboard_channel_id, starboard_message_id).await,
+                };
+                // the post may already be gone, the rows still have to go
+                if let Err(e) = result {
+                    eprintln!("Error applying delete policy to starboard post {}: {}", starboard_message_id, e);
+                }
+            }
+        }
+
+        data.starboard.forget_message(message_id.get()).await?;
+    }
+
+    Ok(())
+}
+
+/// Drops the entry of a post deleted by hand along with the stars left on it, so the next star
+/// posts the message again
+async fn forget_starboard_copy(
+    data: &crate::Data,
+    copy: &StarredMessage,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let original_message_id = copy.original_message_id.parse::<u64>()?;
+    let _lock = data.starboard_state.lock_message(original_message_id).await;
+
+    if let Some(starboard_message_id) = &copy.starboard_message_id {
+        data.starboard_state.forget_post(starboard_message_id.parse::<u64>()?.into());
+    }
+    data.starboard.delete_starred_message(copy.board_id, original_message_id).await?;
+    data.starboard.clear_copy_reactions(copy.board_id, original_message_id).await?;
+    Ok(())
+}
+
+/// Deletes the starboard post and forgets it, the reaction rows are left alone
+pub(crate) async fn remove_starboard_entry(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    starred: &StarredMessage,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let (Some(starboard_channel_id), Some(starboard_message_id)) =
+        (&starred.starboard_channel_id, &starred.starboard_message_id)
+    {
+        let starboard_channel = starboard_channel_id.parse::<u64>()?;
+        let starboard_message_id = starboard_message_id.parse::<u64>()?;
+        data.starboard_state.forget_post(starboard_message_id.into());
+
+        delete_starboard_message(
+            ctx,
+            starboard_channel,
+            starboard_message_id,
+        ).await?;
     }
 
+    data.starboard.delete_starred_message(starred.board_id, starred.original_message_id.parse()?).await?;
     Ok(())
 }
 
-async fn update_starboard_message(
+/// Where a starred message was posted, threads count as their parent channel too
+pub(crate) struct SourceChannel {
+    // the channel itself, its parent channel for threads and its category
+    ids: Vec<serenity::ChannelId>,
+    nsfw: bool,
+}
+
+/// Resolves the channel from the cache, only going over http if it isn't cached yet
+pub(crate) async fn source_channel(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Result<SourceChannel, Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(source) = cached_source_channel(ctx, guild_id, channel_id) {
+        return Ok(source);
+    }
+
+    let channel = channel_id.to_channel(&ctx.http).await?
+        .guild()
+        .ok_or("Starred message isn't in a guild channel")?;
+
+    // threads are never marked nsfw themselves, one whose parent can't be found is rejected
+    let parent = match (channel.thread_metadata.is_some(), channel.parent_id) {
+        (true, Some(parent_id)) => Some(
+            parent_id.to_channel(&ctx.http).await?
+                .guild()
+                .ok_or("Starred thread's parent isn't a guild channel")?
+        ),
+        (true, None) => return Err("Starred thread has no parent channel".into()),
+        _ => None,
+    };
+
+    Ok(match parent {
+        Some(parent) => SourceChannel {
+            ids: [Some(channel.id), Some(parent.id), parent.parent_id].into_iter().flatten().collect(),
+            nsfw: parent.nsfw,
+        },
+        None => SourceChannel {
+            ids: [Some(channel.id), channel.parent_id].into_iter().flatten().collect(),
+            nsfw: channel.nsfw,
+        },
+    })
+}
+
+fn cached_source_channel(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Option<SourceChannel> {
+    let guild = ctx.cache.guild(guild_id)?;
+
+    if let Some(channel) = guild.channels.get(&channel_id) {
+        return Some(SourceChannel {
+            ids: [Some(channel.id), channel.parent_id].into_iter().flatten().collect(),
+            nsfw: channel.nsfw,
+        });
+    }
+
+    let thread = guild.threads.iter().find(|thread| thread.id == channel_id)?;
+    let parent = guild.channels.get(&thread.parent_id?)?;
+
+    Some(SourceChannel {
+        ids: [Some(thread.id), Some(parent.id), parent.parent_id].into_iter().flatten().collect(),
+        nsfw: parent.nsfw,
+    })
+}
+
+/// Allow lists win over nothing, deny lists win over everything, and NSFW messages only ever
+/// go to a board whose channel is NSFW too
+pub(crate) async fn accepts_source(
     ctx: &serenity::Context,
-    starboard: &Database,
+    config: &StarboardConfig,
+    filters: &[ChannelFilter],
+    source: &SourceChannel,
+) -> bool {
+    let listed = |allow: bool| filters
+        .iter()
+        .filter(|filter| filter.allow == allow)
+        .any(|filter| source.ids.iter().any(|id| filter.channel_id == id.to_string()));
+
+    if listed(false) {
+        return false;
+    }
+    if filters.iter().any(|filter| filter.allow) && !listed(true) {
+        return false;
+    }
+
+    if source.nsfw {
+        let board_channel = config.starboard_channel_id
+            .as_deref()
+            .and_then(|id| id.parse::<serenity::ChannelId>().ok())
+            .zip(config.guild_id.parse::<serenity::GuildId>().ok());
+
+        // a board channel that can't be looked up doesn't get nsfw messages
+        let board_nsfw = match board_channel {
+            Some((channel_id, guild_id)) => channel_is_nsfw(ctx, guild_id, channel_id).await.unwrap_or(false),
+            None => false,
+        };
+
+        if !board_nsfw {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// From the cache if it's there, `None` if discord can't tell us either
+async fn channel_is_nsfw(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Option<bool> {
+    let cached = ctx.cache.guild(guild_id)
+        .and_then(|guild| guild.channels.get(&channel_id).map(|channel| channel.nsfw));
+    if cached.is_some() {
+        return cached;
+    }
+
+    channel_id.to_channel(&ctx.http).await.ok()?
+        .guild()
+        .map(|channel| channel.nsfw)
+}
+
+/// Enabled boards in the guild that count this emoji
+async fn matching_boards(
+    data: &crate::Data,
+    guild_id: u64,
+    emoji: &serenity::ReactionType,
+) -> Result<Vec<StarboardConfig>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(data.starboard.get_starboard_configs(guild_id).await?
+        .into_iter()
+        .filter(|config| config.enabled && config.emoji().matches(emoji))
+        .collect())
+}
+
+/// Posts the message, or saves the new count of a post that's already up. The caller holds
+/// the message's lock
+pub(crate) async fn update_starboard_message(
+    ctx: &serenity::Context,
+    data: &crate::Data,
     config: &StarboardConfig,
     original_message: &serenity::Message,
     guild_id: u64,
@@ -173,37 +736,25 @@ async fn update_starboard_message(
     let starboard_channel = serenity::ChannelId::new(starboard_channel_id);
     let original_message_id: u64 = original_message.id.into();
 
-    let existing = starboard.get_starred_message(original_message_id).await?;
-
+    let existing = data.starboard.get_starred_message(config.id, original_message_id).await?;
 
     match existing {
+        Some(starred_message) if starred_message.frozen => {}
         Some(mut starred_message) => {
-            starred_message.stars = star_count;
-            starboard.update_starred_message(&starred_message).await?;
-
-            if let Some(starboard_message_id) = &starred_message.starboard_message_id {
-                let starboard_message_id = starboard_message_id.parse::<u64>()?;
-                update_existing_starboard_message(
-                    ctx,
-                    starboard_channel.into(),
-                    starboard_message_id,
-                    star_count,
-                    &config.star_emoji,
-                ).await?;
-            }
+            save_star_count(ctx, data, config, &mut starred_message, star_count).await?;
         }
         None => {
-            let embed = create_starboard_embed(original_message, guild_id, star_count, &config.star_emoji).await?;
-
-            let message_builder = serenity::CreateMessage::new()
-                .embed(embed);
+            let post = render_starboard_post(original_message, guild_id, star_count, config);
+            let embeds = post.embeds.clone();
 
             let starboard_message = starboard_channel
-                .send_message(&ctx.http, message_builder)
+                .send_message(&ctx.http, post.create_message())
                 .await?;
+            data.starboard_state.cache_post(starboard_message.id, embeds);
 
             let starred_message = StarredMessage {
                 id: 0,
+                board_id: config.id,
                 original_message_id: original_message_id.to_string(),
                 original_channel_id: original_message.channel_id.get().to_string(),
                 starboard_message_id: Some(starboard_message.id.get().to_string()),
@@ -211,114 +762,80 @@ async fn update_starboard_message(
                 stars: star_count,
                 starred_by: "".to_string(),
                 created_at: None,
+                author_id: Some(original_message.author.id.get().to_string()),
+                frozen: false,
+                forced: false,
+                famed: false,
             };
-            starboard.add_starred_message(&starred_message).await?;
+            data.starboard.add_starred_message(&starred_message).await?;
+            promote_to_fame(ctx, data, config, &starred_message, star_count).await?;
         }
     }
 
     Ok(())
 }
 
-async fn create_starboard_embed(
-    message: &serenity::Message,
-    guild_id: u64,
+async fn update_existing_starboard_message(
+    ctx: &serenity::Context,
+    state: &StarboardState,
+    channel: serenity::ChannelId,
+    message: serenity::MessageId,
     star_count: i64,
-    star_emoji: &str,
-) -> Result<serenity::CreateEmbed, Box<dyn std::error::Error + Send + Sync>> {
-
-    let author = serenity::CreateEmbedAuthor::new(&message.author.name)
-        .icon_url(&message.author.face());
-
-
-    let footer = serenity::CreateEmbedFooter::new(format!("{} {}", star_emoji, star_count));
-
-    let channel_id = message.channel_id.get();
-    let message_id = message.id.get();
+    config: &StarboardConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // posts rendered since startup are cached, older ones are fetched once
+    let embeds = match state.cached_post(message) {
+        Some(embeds) => embeds,
+        None => channel.message(&ctx.http, message).await?
+            .embeds
+            .into_iter()
+            .map(serenity::CreateEmbed::from)
+            .collect(),
+    };
 
-    let mut embed = serenity::CreateEmbed::default()
-        .author(author)
-        .description(&message.content)
-        .field("Original", format!("https://discord.com/channels/{}/{}/{}",
-                                   guild_id, channel_id, message_id),
-                            false)
-        .footer(footer)
-        .timestamp(message.timestamp);
+    // only the footer and colour follow the count and tier, the gallery embeds and jump button
+    // stay as they are
+    let mut embeds = embeds.into_iter();
+    let Some(first) = embeds.next() else {
+        return Ok(());
+    };
 
-    if !message.attachments.is_empty() {
-        if let Some(attachment) = message.attachments.first() {
-            if attachment.width.is_some() && attachment.height.is_some() {
-                embed = embed.image(&attachment.url);
-            } else {
-                embed = embed.field("Attachment", format!("[{}]({})", &attachment.filename, &attachment.url), false);
-            }
-        }
-    }
+    let mut new_embeds = vec![first
+        .footer(config.footer_emoji(star_count).footer(star_count))
+        .colour(star_colour(star_count, config))];
+    new_embeds.extend(embeds);
 
+    let edit_builder = serenity::EditMessage::new().embeds(new_embeds.clone());
+    channel.edit_message(&ctx.http, message, edit_builder).await?;
+    state.cache_post(message, new_embeds);
 
-    Ok(embed)
+    Ok(())
 }
 
-async fn update_existing_starboard_message(
+async fn mark_starboard_message_deleted(
     ctx: &serenity::Context,
     starboard_channel_id: u64,
     starboard_message_id: u64,
-    star_count: i64,
-    star_emoji: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let channel = serenity::ChannelId::new(starboard_channel_id);
     let message = serenity::MessageId::new(starboard_message_id);
 
     let existing_message = channel.message(&ctx.http, message).await?;
 
-    if let Some(existing_embed) = existing_message.embeds.first() {
-        let mut new_embed = serenity::CreateEmbed::default();
-
-        // clone embed manually, i cant get the init thing right, i dont get it ah
-        if let Some(ref title) = existing_embed.title {
-            new_embed = new_embed.title(title);
-        }
-
-        if let Some(ref description) = existing_embed.description {
-            new_embed = new_embed.description(description);
-        }
-
-        if let Some(ref author) = existing_embed.author {
-            let mut new_author = serenity::CreateEmbedAuthor::new(&author.name);
-
-            if let Some(ref url) = author.icon_url {
-                new_author = new_author.icon_url(url);
-            }
-
-            new_embed = new_embed.author(new_author);
-        }
-
-
-        for fields in &existing_embed.fields {
-            new_embed = new_embed.field(&fields.name, &fields.value, fields.inline);
-        }
-
-        let new_footer = serenity::CreateEmbedFooter::new(format!("{} {}", star_emoji, star_count));
-        new_embed = new_embed.footer(new_footer);
-
-        if let Some(ref timestamp) = existing_embed.timestamp {
-            new_embed = new_embed.timestamp(*timestamp);
-        }
-
-        if let Some(ref color) = existing_embed.colour {
-            new_embed = new_embed.color(*color);
-        }
-
-        if let Some(ref image) = existing_embed.image {
-            new_embed = new_embed.image(image.url.clone());
-        }
+    let mut embeds = existing_message.embeds.into_iter();
+    let Some(first) = embeds.next() else {
+        return Ok(());
+    };
 
-        if let Some(ref thumbnail) = existing_embed.thumbnail {
-            new_embed = new_embed.thumbnail(thumbnail.url.clone());
-        }
+    let mut new_embeds = vec![serenity::CreateEmbed::from(first)
+        .field("Original message", "*Deleted*", false)];
+    new_embeds.extend(embeds.map(serenity::CreateEmbed::from));
 
-        let edit_builder = serenity::EditMessage::new().embed(new_embed);
-        channel.edit_message(&ctx.http, message, edit_builder).await?;
-    }
+    // the jump button would only lead to nothing now
+    let edit_builder = serenity::EditMessage::new()
+        .embeds(new_embeds)
+        .components(vec![]);
+    channel.edit_message(&ctx.http, message, edit_builder).await?;
 
     Ok(())
 }
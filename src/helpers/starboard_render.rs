@@ -0,0 +1,234 @@
+use poise::serenity_prelude as serenity;
+
+use crate::helpers::starboard::StarboardConfig;
+
+// discord's own limits
+const DESCRIPTION_LIMIT: usize = 4096;
+const FIELD_LIMIT: usize = 1024;
+const MAX_EMBEDS: usize = 10;
+// embeds sharing a url are shown as one gallery of up to 4 images
+const GALLERY_SIZE: usize = 4;
+
+const REPLY_QUOTE_LIMIT: usize = 300;
+const DEFAULT_COLOUR: u32 = 0xFFAC33;
+// stars over the threshold at which the colour stops getting stronger
+const FULL_COLOUR_MULTIPLIER: i64 = 4;
+
+/// Everything that goes into a starboard post, shared by the create and edit paths
+pub(crate) struct StarboardPost {
+    pub embeds: Vec<serenity::CreateEmbed>,
+    pub components: Vec<serenity::CreateActionRow>,
+}
+
+impl StarboardPost {
+    pub fn create_message(self) -> serenity::CreateMessage {
+        serenity::CreateMessage::new()
+            .embeds(self.embeds)
+            .components(self.components)
+    }
+
+    pub fn edit_message(self) -> serenity::EditMessage {
+        serenity::EditMessage::new()
+            .embeds(self.embeds)
+            .components(self.components)
+    }
+
+    pub fn reply(self) -> poise::CreateReply {
+        let mut reply = poise::CreateReply::default().components(self.components);
+        for embed in self.embeds {
+            reply = reply.embed(embed);
+        }
+        reply
+    }
+}
+
+pub(crate) fn render_starboard_post(
+    message: &serenity::Message,
+    guild_id: u64,
+    star_count: i64,
+    config: &StarboardConfig,
+) -> StarboardPost {
+    let jump_url = format!("https://discord.com/channels/{}/{}/{}",
+                           guild_id, message.channel_id.get(), message.id.get());
+
+    let author = serenity::CreateEmbedAuthor::new(&message.author.name)
+        .icon_url(message.author.face());
+
+    let mut description = message.content.clone();
+    // bot messages are often just an embed, show its text instead of nothing
+    if let (true, Some(embed)) = (description.is_empty(), message.embeds.first()) {
+        description = [embed.title.as_deref(), embed.description.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    let mut embed = serenity::CreateEmbed::default()
+        .author(author)
+        .url(&jump_url)
+        .description(truncate(&description, DESCRIPTION_LIMIT))
+        .footer(config.footer_emoji(star_count).footer(star_count))
+        .colour(star_colour(star_count, config))
+        .timestamp(message.timestamp);
+
+    if let Some(reply) = reply_field(message, guild_id) {
+        embed = embed.field(reply.0, reply.1, false);
+    }
+
+    let mut images = Vec::new();
+    let mut files = Vec::new();
+
+    for attachment in &message.attachments {
+        let spoiler = attachment.filename.starts_with("SPOILER_");
+        let kind = attachment.content_type.as_deref().unwrap_or_default();
+
+        if spoiler {
+            files.push(format!("||🙈 [{}]({})||", attachment.filename, attachment.url));
+        } else if kind.starts_with("image/") || (kind.is_empty() && attachment.width.is_some()) {
+            images.push(attachment.url.clone());
+        } else if kind.starts_with("video/") {
+            files.push(format!("🎞️ [{}]({})", attachment.filename, attachment.url));
+        } else {
+            files.push(format!("📎 [{}]({})", attachment.filename, attachment.url));
+        }
+    }
+
+    let hidden = spoilered_text(&message.content);
+    for preview in &message.embeds {
+        if preview.url.as_ref().is_some_and(|url| hidden.iter().any(|spoiler| spoiler.contains(url.as_str()))) {
+            continue;
+        }
+
+        let url = preview.image.as_ref().map(|image| image.url.clone())
+            .or_else(|| preview.thumbnail.as_ref().map(|thumbnail| thumbnail.url.clone()));
+        if let Some(url) = url {
+            images.push(url);
+        }
+        if let Some(video) = &preview.video {
+            files.push(format!("🎞️ [{}]({})", preview.title.as_deref().unwrap_or("Video"),
+                               preview.url.as_deref().unwrap_or(&video.url)));
+        }
+    }
+
+    let mut stickers = Vec::new();
+    for sticker in &message.sticker_items {
+        // lottie stickers have no image we can show
+        match sticker.image_url() {
+            Some(url) => {
+                images.push(url);
+                stickers.push(sticker.name.clone());
+            }
+            None => stickers.push(format!("{} (animated)", sticker.name)),
+        }
+    }
+    if !stickers.is_empty() {
+        embed = embed.field("Stickers", truncate(&stickers.join(", "), FIELD_LIMIT), false);
+    }
+
+    // anything past the last embed slot is linked instead
+    let overflow = images.split_off(images.len().min(MAX_EMBEDS));
+    files.extend(overflow.into_iter().map(|url| format!("🖼️ [image]({})", url)));
+
+    if !files.is_empty() {
+        embed = embed.field("Attachments", join_within(&files, FIELD_LIMIT), false);
+    }
+
+    let mut images = images.into_iter();
+    if let Some(first) = images.next() {
+        embed = embed.image(first);
+    }
+
+    let mut embeds = vec![embed];
+    for (index, image) in images.enumerate() {
+        let mut extra = serenity::CreateEmbed::default().image(image);
+        // the first few join the main embed's gallery, the rest get their own
+        if index + 1 < GALLERY_SIZE {
+            extra = extra.url(&jump_url);
+        }
+        embeds.push(extra);
+    }
+
+    StarboardPost { embeds, components: vec![jump_button(jump_url)] }
+}
+
+pub(crate) fn jump_button(url: impl Into<String>) -> serenity::CreateActionRow {
+    serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new_link(url).label("Jump to message"),
+    ])
+}
+
+/// The tier's colour if it has one, otherwise the board colour faded towards white near the
+/// threshold and at full strength at FULL_COLOUR_MULTIPLIER times it
+pub(crate) fn star_colour(star_count: i64, config: &StarboardConfig) -> u32 {
+    if let Some(colour) = config.tier_for(star_count).and_then(|tier| tier.colour) {
+        return colour as u32;
+    }
+
+    let base = config.colour.map(|colour| colour as u32).unwrap_or(DEFAULT_COLOUR);
+
+    let threshold = config.threshold.max(1);
+    let full = threshold * FULL_COLOUR_MULTIPLIER;
+    let progress = (star_count - threshold).clamp(0, full - threshold) as f64 / (full - threshold) as f64;
+    let strength = 0.4 + 0.6 * progress;
+
+    let channel = |shift: u32| {
+        let value = ((base >> shift) & 0xFF) as f64;
+        let mixed = 255.0 - (255.0 - value) * strength;
+        (mixed.round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+fn reply_field(message: &serenity::Message, guild_id: u64) -> Option<(String, String)> {
+    // pins and crossposts carry a reference too, only replies get quoted
+    if message.kind != serenity::MessageType::InlineReply {
+        return None;
+    }
+
+    let Some(replied) = message.referenced_message.as_deref() else {
+        return Some(("Replying to".to_string(), "*The original message was deleted*".to_string()));
+    };
+
+    let mut quote: String = truncate(&replied.content, REPLY_QUOTE_LIMIT)
+        .lines()
+        .map(|line| format!("> {}\n", line))
+        .collect();
+    if quote.is_empty() && !replied.attachments.is_empty() {
+        quote.push_str("> *attachment*\n");
+    }
+
+    let link = format!("[Jump to replied message](https://discord.com/channels/{}/{}/{})",
+                       guild_id, replied.channel_id.get(), replied.id.get());
+    quote.push_str(&link);
+
+    Some((format!("Replying to {}", replied.author.name), quote))
+}
+
+/// Text inside ||spoiler|| markers
+fn spoilered_text(content: &str) -> Vec<&str> {
+    content.split("||").skip(1).step_by(2).collect()
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(limit - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn join_within(lines: &[String], limit: usize) -> String {
+    let mut joined = String::new();
+    for line in lines {
+        if joined.chars().count() + line.chars().count() + 1 > limit {
+            break;
+        }
+        if !joined.is_empty() {
+            joined.push('\n');
+        }
+        joined.push_str(line);
+    }
+    joined
+}
@@ -0,0 +1,308 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude as serenity;
+use tokio::time::{sleep, Duration};
+
+use crate::helpers::starboard_activity::scaled_threshold;
+use crate::helpers::starboard::{ChannelFilter, StarboardConfig, StarredMessage};
+use crate::helpers::starboard_manager::{
+    accepts_source, message_blocked, past_keep_age, remove_starboard_entry, source_channel, update_starboard_message,
+};
+use crate::types::{Data, Error};
+
+// pause before every reaction/message fetch so a rescan leaves room in the rate limit for
+// everything else, serenity still waits out any 429s on top of this
+const REQUEST_DELAY: Duration = Duration::from_millis(250);
+const PAGE_LIMIT: u8 = 100;
+
+static STARTUP_RESCAN_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct RescanProgress {
+    pub scanned: usize,
+    pub reconciled: usize,
+    pub posted: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+/// Enabled boards of a guild with their channel filters
+pub(crate) async fn load_boards(
+    data: &Data,
+    guild_id: serenity::GuildId,
+) -> Result<Vec<(StarboardConfig, Vec<ChannelFilter>)>, Error> {
+    let mut boards = Vec::new();
+    for config in data.starboard.get_starboard_configs(guild_id.get()).await? {
+        if config.enabled {
+            let filters = data.starboard.get_channel_filters(config.id).await?;
+            boards.push((config, filters));
+        }
+    }
+    Ok(boards)
+}
+
+/// Walks a channel's history backwards one page at a time so callers can report in between
+pub(crate) struct ChannelScan {
+    channel_id: serenity::ChannelId,
+    since: DateTime<Utc>,
+    before: Option<serenity::MessageId>,
+    done: bool,
+}
+
+impl ChannelScan {
+    pub fn new(channel_id: serenity::ChannelId, since: DateTime<Utc>) -> Self {
+        Self { channel_id, since, before: None, done: false }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub async fn next_page(
+        &mut self,
+        ctx: &serenity::Context,
+        data: &Data,
+        guild_id: serenity::GuildId,
+        boards: &[(StarboardConfig, Vec<ChannelFilter>)],
+        progress: &mut RescanProgress,
+    ) -> Result<(), Error> {
+        let mut request = serenity::GetMessages::new().limit(PAGE_LIMIT);
+        if let Some(before) = self.before {
+            request = request.before(before);
+        }
+
+        sleep(REQUEST_DELAY).await;
+        let messages = self.channel_id.messages(&ctx.http, request).await?;
+
+        self.before = messages.last().map(|message| message.id);
+        self.done = messages.len() < PAGE_LIMIT as usize;
+
+        for message in &messages {
+            if message.timestamp.unix_timestamp() < self.since.timestamp() {
+                self.done = true;
+                break;
+            }
+
+            progress.scanned += 1;
+            if let Err(e) = reconcile_message(ctx, data, guild_id, message, boards, progress).await {
+                eprintln!("Error reconciling starboard message {}: {}", message.id, e);
+                progress.failed += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rewrites a message's reaction rows from discord and creates, updates or removes its
+/// starboard posts to match
+pub(crate) async fn reconcile_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    message: &serenity::Message,
+    boards: &[(StarboardConfig, Vec<ChannelFilter>)],
+    progress: &mut RescanProgress,
+) -> Result<(), Error> {
+    // starboard posts are counted as part of their original
+    if data.starboard.get_starred_message_by_copy(message.id.get()).await?.is_some() {
+        return Ok(());
+    }
+
+    let tracked = data.starboard.boards_tracking(message.id.get()).await?;
+
+    let candidates: Vec<_> = boards
+        .iter()
+        .filter(|(config, _)| {
+            tracked.contains(&config.id)
+                || message.reactions.iter().any(|reaction| config.emoji().matches(&reaction.reaction_type))
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return Ok(());
+    }
+    progress.reconciled += 1;
+
+    let source = source_channel(ctx, guild_id, message.channel_id).await?;
+
+    for (config, filters) in candidates {
+        // held from reading the reactions until the post is settled, live stars wait for it
+        // instead of being overwritten by the rescan's copy
+        let _lock = data.starboard_state.lock_message(message.id.get()).await;
+
+        let reaction = message.reactions
+            .iter()
+            .find(|reaction| config.emoji().matches(&reaction.reaction_type));
+
+        let mut users = match reaction {
+            Some(reaction) => reaction_users(ctx, message, &reaction.reaction_type).await?,
+            None => Vec::new(),
+        };
+
+        let existing = data.starboard.get_starred_message(config.id, message.id.get()).await?;
+        let mut copy_users = match existing.as_ref() {
+            Some(starred) => starboard_copy_users(ctx, config, starred).await?,
+            None => Vec::new(),
+        };
+
+        if !config.self_star_allowed {
+            users.retain(|user_id| *user_id != message.author.id.get());
+            copy_users.retain(|user_id| *user_id != message.author.id.get());
+        }
+        let blocked = data.starboard.star_blocked_users(config.id).await?;
+        users.retain(|user_id| !blocked.contains(user_id));
+        copy_users.retain(|user_id| !blocked.contains(user_id));
+
+        data.starboard.replace_star_reactions(config.id, message.id.get(), message.channel_id.get(), &users, &copy_users).await?;
+
+        let star_count = users.iter().chain(&copy_users).collect::<HashSet<_>>().len() as i64;
+        // same rules as a live reaction, the age limit and scaling only apply to new posts
+        let eligible = accepts_source(ctx, config, filters, &source).await
+            && !(config.ignore_bots && message.author.bot)
+            && !message_blocked(data, config.id, message.id.get(), message.author.id.get()).await?
+            && (existing.is_some() || !config.is_too_old(message.id));
+        let threshold = match existing {
+            Some(_) => config.threshold,
+            None => scaled_threshold(config, message.channel_id),
+        };
+
+        // moderators have the last word on frozen and forced posts
+        if existing.as_ref().is_some_and(|starred| starred.frozen) {
+            continue;
+        }
+
+        let forced = existing.as_ref().is_some_and(|starred| starred.forced);
+        if forced || (eligible && star_count >= threshold) {
+            if existing.as_ref().is_some_and(|starred| starred.stars == star_count) {
+                continue;
+            }
+
+            update_starboard_message(ctx, data, config, message, guild_id.get(), star_count).await?;
+            if existing.is_none() {
+                progress.posted += 1;
+            }
+        } else if let Some(existing) = existing.filter(|starred| !past_keep_age(config, starred)) {
+            remove_starboard_entry(ctx, data, &existing).await?;
+            progress.removed += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Users who starred the board's post rather than the original, empty if the post is gone
+async fn starboard_copy_users(
+    ctx: &serenity::Context,
+    config: &StarboardConfig,
+    starred: &StarredMessage,
+) -> Result<Vec<u64>, Error> {
+    let (Some(channel_id), Some(message_id)) = (&starred.starboard_channel_id, &starred.starboard_message_id) else {
+        return Ok(Vec::new());
+    };
+    let channel_id = channel_id.parse::<serenity::ChannelId>()?;
+    let message_id = message_id.parse::<serenity::MessageId>()?;
+
+    sleep(REQUEST_DELAY).await;
+    let copy = match channel_id.message(&ctx.http, message_id).await {
+        Ok(copy) => copy,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    match copy.reactions.iter().find(|reaction| config.emoji().matches(&reaction.reaction_type)) {
+        Some(reaction) => reaction_users(ctx, &copy, &reaction.reaction_type).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn reaction_users(
+    ctx: &serenity::Context,
+    message: &serenity::Message,
+    reaction_type: &serenity::ReactionType,
+) -> Result<Vec<u64>, Error> {
+    let mut users = Vec::new();
+    let mut after = None;
+
+    loop {
+        sleep(REQUEST_DELAY).await;
+        let page = message.channel_id
+            .reaction_users(&ctx.http, message.id, reaction_type.clone(), Some(PAGE_LIMIT), after)
+            .await?;
+
+        let full_page = page.len() == PAGE_LIMIT as usize;
+        after = page.last().map(|user| user.id);
+        users.extend(page.into_iter().map(|user| user.id.get()));
+
+        if !full_page {
+            return Ok(users);
+        }
+    }
+}
+
+/// Re-checks everything starred within `window` of now, only runs once per process
+pub(crate) async fn startup_rescan(ctx: &serenity::Context, data: &Data, window: chrono::Duration) {
+    if STARTUP_RESCAN_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let since = (Utc::now() - window).format("%Y-%m-%d %H:%M:%S").to_string();
+    let tracked = match data.starboard.tracked_messages_since(&since).await {
+        Ok(tracked) => tracked,
+        Err(e) => {
+            eprintln!("Error loading starred messages for rescan: {}", e);
+            return;
+        }
+    };
+
+    println!("Starboard startup rescan: {} messages to check", tracked.len());
+
+    let mut boards_by_guild = HashMap::new();
+    let mut progress = RescanProgress::default();
+
+    for (guild_id, channel_id, message_id) in tracked {
+        let (Ok(guild_id), Ok(channel_id), Ok(message_id)) = (
+            guild_id.parse::<serenity::GuildId>(),
+            channel_id.parse::<serenity::ChannelId>(),
+            message_id.parse::<serenity::MessageId>(),
+        ) else {
+            continue;
+        };
+
+        let boards = match boards_by_guild.entry(guild_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match load_boards(data, guild_id).await {
+                Ok(boards) => entry.insert(boards),
+                Err(e) => {
+                    eprintln!("Error loading starboards for guild {}: {}", guild_id, e);
+                    continue;
+                }
+            },
+        };
+
+        sleep(REQUEST_DELAY).await;
+        let result = match channel_id.message(&ctx.http, message_id).await {
+            Ok(message) => {
+                progress.scanned += 1;
+                reconcile_message(ctx, data, guild_id, &message, boards, &mut progress).await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error rescanning starboard message {}: {}", message_id, e);
+            progress.failed += 1;
+        }
+
+        if progress.scanned > 0 && progress.scanned % 25 == 0 {
+            println!("Starboard startup rescan: {} checked", progress.scanned);
+        }
+    }
+
+    println!(
+        "Starboard startup rescan done: {} checked, {} posted, {} removed, {} failed",
+        progress.scanned, progress.posted, progress.removed, progress.failed
+    );
+}
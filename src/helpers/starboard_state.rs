@@ -0,0 +1,105 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use poise::serenity_prelude as serenity;
+use tokio::sync::OwnedMutexGuard;
+
+use crate::helpers::starboard::StarboardConfig;
+
+// posts whose embeds are kept around so count edits don't have to fetch them first
+const POST_CACHE_SIZE: usize = 500;
+
+/// In-memory starboard bookkeeping shared by the reaction handlers
+///
+/// Nothing here is a source of truth, it only saves work. Locks are per original message so
+/// guilds don't wait on each other, the post cache is dropped whenever a post changes shape,
+/// and count edits for the same post are coalesced until the pending one goes out.
+#[derive(Default)]
+pub struct StarboardState {
+    locks: Mutex<HashMap<u64, Arc<tokio::sync::Mutex<()>>>>,
+    posts: Mutex<PostCache>,
+    pending: Mutex<HashMap<serenity::MessageId, PendingEdit>>,
+}
+
+#[derive(Default)]
+struct PostCache {
+    embeds: HashMap<serenity::MessageId, Vec<serenity::CreateEmbed>>,
+    order: VecDeque<serenity::MessageId>,
+}
+
+/// Latest count waiting to be written to a post
+#[derive(Clone)]
+pub struct PendingEdit {
+    pub original_message_id: u64,
+    pub channel_id: serenity::ChannelId,
+    pub star_count: i64,
+    pub config: StarboardConfig,
+}
+
+/// Holds a message's lock, the map entry goes away with the last holder
+pub struct MessageGuard<'a> {
+    state: &'a StarboardState,
+    message_id: u64,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for MessageGuard<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.state.locks.lock().unwrap();
+        // one reference in the map and one in this guard means nobody is waiting
+        if locks.get(&self.message_id).is_some_and(|lock| Arc::strong_count(lock) == 2) {
+            locks.remove(&self.message_id);
+        }
+    }
+}
+
+impl StarboardState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serialises everything touching one original message's stars and posts
+    pub async fn lock_message(&self, message_id: u64) -> MessageGuard<'_> {
+        let lock = Arc::clone(self.locks.lock().unwrap().entry(message_id).or_default());
+        MessageGuard {
+            state: self,
+            message_id,
+            _guard: lock.lock_owned().await,
+        }
+    }
+
+    pub fn cached_post(&self, message_id: serenity::MessageId) -> Option<Vec<serenity::CreateEmbed>> {
+        self.posts.lock().unwrap().embeds.get(&message_id).cloned()
+    }
+
+    pub fn cache_post(&self, message_id: serenity::MessageId, embeds: Vec<serenity::CreateEmbed>) {
+        let mut posts = self.posts.lock().unwrap();
+        if posts.embeds.insert(message_id, embeds).is_none() {
+            posts.order.push_back(message_id);
+        }
+        while posts.order.len() > POST_CACHE_SIZE {
+            if let Some(oldest) = posts.order.pop_front() {
+                posts.embeds.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drops a post from the cache along with any edit still waiting on it
+    pub fn forget_post(&self, message_id: serenity::MessageId) {
+        let mut posts = self.posts.lock().unwrap();
+        if posts.embeds.remove(&message_id).is_some() {
+            posts.order.retain(|id| *id != message_id);
+        }
+        self.pending.lock().unwrap().remove(&message_id);
+    }
+
+    /// Stores the newest count for a post, returns `true` if nothing was pending yet and the
+    /// caller should schedule the edit
+    pub fn queue_edit(&self, message_id: serenity::MessageId, edit: PendingEdit) -> bool {
+        self.pending.lock().unwrap().insert(message_id, edit).is_none()
+    }
+
+    pub fn take_edit(&self, message_id: serenity::MessageId) -> Option<PendingEdit> {
+        self.pending.lock().unwrap().remove(&message_id)
+    }
+}
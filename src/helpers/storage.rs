@@ -0,0 +1,332 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::UserId;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::SqlitePool;
+
+use crate::helpers::auth::AuthDatabase;
+#[cfg(test)]
+use crate::helpers::memory_storage::{MemoryAuth, MemoryReminders, MemoryRoleColours, MemoryStarboard};
+use crate::helpers::reminder::ReminderStore;
+use crate::helpers::role_colours::RoleColourStore;
+use crate::helpers::starboard::{
+    BlockMode, ChannelFilter, Database, StarSource, StarTally, StarboardConfig, StarredMessage, StatsScope,
+};
+use crate::structs::auth::{AuthConfig, AuthKey, AuthenticatedUser};
+use crate::structs::reminders::{Reminder, ReminderId};
+use crate::types::Error;
+
+// how long a query waits on another connection's write lock before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Every store the bot uses, backed by one shared SQLite pool or kept in memory for tests
+#[derive(Clone)]
+pub struct Storage {
+    // commands that still run their own queries use this directly
+    pub pool: SqlitePool,
+    pub reminders: Arc<dyn ReminderStorage>,
+    pub starboard: Arc<dyn StarboardStorage>,
+    pub auth: Arc<dyn AuthStorage>,
+    pub role_colours: Arc<dyn RoleColourStorage>,
+}
+
+impl Storage {
+    /// Opens the database, creating the file if needed, and sets up every store's tables
+    pub async fn connect(database_url: &str) -> Result<Self, Error> {
+        // pragmas on the options apply to every connection the pool opens, not just the first
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        Self::open(pool).await
+    }
+
+    /// SQLite stores on an existing pool
+    pub async fn open(pool: SqlitePool) -> Result<Self, Error> {
+        let reminders = ReminderStore::new(pool.clone());
+        reminders.create_tables().await?;
+
+        let starboard = Database::new(pool.clone()).await?;
+
+        let auth = AuthDatabase::new(pool.clone());
+        auth.create_tables().await?;
+
+        let role_colours = RoleColourStore::new(pool.clone());
+        role_colours.create_tables().await?;
+
+        Ok(Self {
+            pool,
+            reminders: Arc::new(reminders),
+            starboard: Arc::new(starboard),
+            auth: Arc::new(auth),
+            role_colours: Arc::new(role_colours),
+        })
+    }
+
+    /// Stores that live in memory and start out empty, nothing touches the disk
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self, Error> {
+        // a single connection keeps the in-memory database alive, it's only opened if a
+        // command runs its own query
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_lazy("sqlite::memory:")?;
+
+        Ok(Self {
+            pool,
+            reminders: Arc::new(MemoryReminders::default()),
+            starboard: Arc::new(MemoryStarboard::default()),
+            auth: Arc::new(MemoryAuth::default()),
+            role_colours: Arc::new(MemoryRoleColours::default()),
+        })
+    }
+}
+
+#[async_trait]
+pub trait ReminderStorage: Send + Sync {
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<ReminderId, Error>;
+
+    /// Slash reminders only get a jump link once the confirmation has been posted
+    async fn set_context_url(&self, reminder_id: ReminderId, url: &str) -> Result<(), Error>;
+
+    /// Every reminder that still has to fire, used to fill the scheduler on startup
+    async fn get_pending(&self) -> Result<Vec<Reminder>, Error>;
+
+    async fn get_reminder(&self, reminder_id: ReminderId) -> Result<Option<Reminder>, Error>;
+
+    /// Unsent reminders the user owns or is subscribed to
+    async fn get_user_reminders(&self, user_id: UserId) -> Result<Vec<Reminder>, Error>;
+
+    async fn mark_due(&self, reminder_id: ReminderId) -> Result<(), Error>;
+
+    /// Moves a recurring reminder on to its next occurrence after one has been delivered
+    ///
+    /// `failure_reason` is kept when the occurrence was given up on instead, so the list can show it.
+    async fn reschedule(
+        &self,
+        reminder_id: ReminderId,
+        next_at: DateTime<Utc>,
+        failure_reason: Option<&str>,
+    ) -> Result<(), Error>;
+
+    async fn record_failed_attempt(
+        &self,
+        reminder_id: ReminderId,
+        reason: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), Error>;
+
+    /// Dead-letters a reminder, it stays in the owner's list with the reason until cancelled
+    async fn mark_failed(&self, reminder_id: ReminderId, reason: &str) -> Result<(), Error>;
+
+    /// Clears a pending snooze redelivery of a recurring reminder without touching the series
+    async fn finish_snooze(&self, reminder_id: ReminderId, failure_reason: Option<&str>) -> Result<(), Error>;
+
+    /// Pushes a delivered reminder back by redelivering it at `until`, for recurring reminders
+    /// the series itself is left alone
+    async fn snooze(&self, reminder_id: ReminderId, until: DateTime<Utc>) -> Result<(), Error>;
+
+    /// Marks the delivered reminder as done, which also drops any snooze still pending on it
+    async fn acknowledge(&self, reminder_id: ReminderId) -> Result<(), Error>;
+
+    /// Only the owner can cancel, and only before it went out
+    async fn cancel_reminder(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error>;
+
+    async fn count_active_reminders(&self, user_id: UserId) -> Result<i64, Error>;
+
+    /// Deletes delivered reminders last due before `before` along with their subscribers,
+    /// dead-lettered ones stay until the owner cancels them
+    async fn purge_sent(&self, before: DateTime<Utc>) -> Result<u64, Error>;
+
+    /// Returns `false` if the user was already subscribed
+    async fn add_subscriber(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error>;
+
+    async fn remove_subscriber(&self, reminder_id: ReminderId, user_id: UserId) -> Result<bool, Error>;
+
+    async fn get_subscribers(&self, reminder_id: ReminderId) -> Result<Vec<UserId>, Error>;
+}
+
+#[async_trait]
+pub trait AuthStorage: Send + Sync {
+    async fn get_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, Error>;
+
+    async fn set_auth_config(
+        &self,
+        guild_id: i64,
+        role_id: i64,
+        enabled: bool,
+    ) -> Result<AuthConfig, Error>;
+
+    /// Removes the config along with every key of the guild
+    async fn delete_auth_config(&self, guild_id: i64) -> Result<Option<AuthConfig>, Error>;
+
+    async fn is_user_authenticated(&self, user_id: i64, guild_id: i64) -> Result<bool, Error>;
+
+    async fn add_authenticated_user(&self, user_id: i64, guild_id: i64) -> Result<AuthenticatedUser, Error>;
+
+    /// Newest first
+    async fn get_authenticated_users(&self, guild_id: i64) -> Result<Vec<AuthenticatedUser>, Error>;
+
+    async fn remove_authenticated_user(&self, user_id: i64, guild_id: i64) -> Result<Option<AuthenticatedUser>, Error>;
+
+    /// Saves a new key, returns it with its id
+    async fn add_auth_key(&self, key: &AuthKey) -> Result<AuthKey, Error>;
+
+    /// Keys that haven't been revoked, newest first
+    async fn get_auth_keys(&self, guild_id: i64) -> Result<Vec<AuthKey>, Error>;
+
+    async fn find_auth_key(&self, guild_id: i64, key_hash: &str) -> Result<Option<AuthKey>, Error>;
+
+    /// Counts a use if the key is still usable at `now`, false if it isn't
+    async fn redeem_auth_key(&self, key_id: i64, now: DateTime<Utc>) -> Result<bool, Error>;
+
+    /// Gives back a use taken by [`AuthStorage::redeem_auth_key`] when the role couldn't be given
+    async fn release_auth_key(&self, key_id: i64) -> Result<(), Error>;
+
+    /// False if the guild has no such key or it was already revoked
+    async fn revoke_auth_key(&self, guild_id: i64, key_id: i64) -> Result<bool, Error>;
+}
+
+#[async_trait]
+pub trait RoleColourStorage: Send + Sync {
+    async fn set_feature_enabled(&self, guild_id: u64, enabled: bool) -> Result<(), Error>;
+
+    async fn is_feature_enabled(&self, guild_id: u64) -> Result<bool, Error>;
+}
+
+#[async_trait]
+pub trait StarboardStorage: Send + Sync {
+    /// A guild's boards, oldest first
+    async fn get_starboard_configs(&self, guild_id: u64) -> Result<Vec<StarboardConfig>, Error>;
+
+    async fn get_starboard_config_by_id(&self, board_id: i64) -> Result<Option<StarboardConfig>, Error>;
+
+    async fn get_starboard_config(&self, guild_id: u64, name: &str) -> Result<Option<StarboardConfig>, Error> {
+        Ok(self.get_starboard_configs(guild_id).await?
+            .into_iter()
+            .find(|config| config.name == name))
+    }
+
+    /// Inserts a new board or saves an existing one, returns its id
+    async fn update_starboard_config(&self, config: &StarboardConfig) -> Result<i64, Error>;
+
+    async fn set_starboard_channel(&self, board_id: i64, channel_id: u64) -> Result<(), Error>;
+
+    async fn set_starboard_threshold(&self, board_id: i64, threshold: i64) -> Result<(), Error>;
+
+    /// Drops the board along with everything stored for it
+    async fn delete_starboard(&self, board_id: i64) -> Result<(), Error>;
+
+    async fn get_channel_filters(&self, board_id: i64) -> Result<Vec<ChannelFilter>, Error>;
+
+    async fn set_channel_filter(&self, board_id: i64, channel_id: u64, allow: bool) -> Result<(), Error>;
+
+    /// Returns `false` if the channel had no filter
+    async fn remove_channel_filter(&self, board_id: i64, channel_id: u64) -> Result<bool, Error>;
+
+    async fn get_starred_message(&self, board_id: i64, message_id: u64) -> Result<Option<StarredMessage>, Error>;
+
+    /// The entry a starboard copy was posted for
+    async fn get_starred_message_by_copy(&self, starboard_message_id: u64) -> Result<Option<StarredMessage>, Error>;
+
+    /// Every board's entry for an original message
+    async fn get_starred_messages(&self, message_id: u64) -> Result<Vec<StarredMessage>, Error>;
+
+    async fn add_starred_message(&self, message: &StarredMessage) -> Result<(), Error>;
+
+    /// Saves the count and post id
+    async fn update_starred_message(&self, message: &StarredMessage) -> Result<(), Error>;
+
+    async fn delete_starred_message(&self, board_id: i64, message_id: u64) -> Result<(), Error>;
+
+    /// Returns `false` if the message isn't on the board
+    async fn set_frozen(&self, board_id: i64, message_id: u64, frozen: bool) -> Result<bool, Error>;
+
+    async fn set_famed(&self, board_id: i64, message_id: u64) -> Result<(), Error>;
+
+    async fn set_forced(&self, board_id: i64, message_id: u64, forced: bool) -> Result<(), Error>;
+
+    async fn is_blacklisted(&self, board_id: i64, message_id: u64) -> Result<bool, Error>;
+
+    async fn blacklist_message(&self, board_id: i64, message_id: u64) -> Result<(), Error>;
+
+    /// Returns `false` if the message wasn't blacklisted
+    async fn unblacklist_message(&self, board_id: i64, message_id: u64) -> Result<bool, Error>;
+
+    async fn get_block(&self, board_id: i64, user_id: u64) -> Result<Option<BlockMode>, Error>;
+
+    async fn block_user(&self, board_id: i64, user_id: u64, mode: BlockMode) -> Result<(), Error>;
+
+    /// Returns `false` if the user wasn't blocked
+    async fn unblock_user(&self, board_id: i64, user_id: u64) -> Result<bool, Error>;
+
+    /// Users whose stars don't count on a board
+    async fn star_blocked_users(&self, board_id: i64) -> Result<Vec<u64>, Error>;
+
+    /// Stars are always stored against the original message, `source` says where the reaction
+    /// is. Returns the board's star count for the message after the star is added
+    async fn add_star_reaction(
+        &self,
+        board_id: i64,
+        message_id: u64,
+        channel_id: u64,
+        user_id: u64,
+        source: StarSource,
+    ) -> Result<i64, Error>;
+
+    /// The star only goes away once the user has no reaction left on either message, returns
+    /// the count left afterwards
+    async fn remove_star_reaction(&self, board_id: i64, message_id: u64, user_id: u64, source: StarSource) -> Result<i64, Error>;
+
+    /// Drops the copy's side of a board's stars, used when discord clears the copy's reactions
+    async fn clear_copy_reactions(&self, board_id: i64, message_id: u64) -> Result<(), Error>;
+
+    /// Swaps a board's reaction rows for a message with the users discord actually reports on
+    /// the original and on the starboard copy
+    async fn replace_star_reactions(
+        &self,
+        board_id: i64,
+        message_id: u64,
+        channel_id: u64,
+        original: &[u64],
+        copy: &[u64],
+    ) -> Result<(), Error>;
+
+    /// Boards that have reactions or a post stored for a message
+    async fn boards_tracking(&self, message_id: u64) -> Result<Vec<i64>, Error>;
+
+    /// (guild, channel, message) for everything starred since `since`, used by the startup rescan
+    async fn tracked_messages_since(&self, since: &str) -> Result<Vec<(String, String, String)>, Error>;
+
+    /// Drops every board's reactions for a message, used when discord clears them all
+    async fn clear_star_reactions(&self, message_id: u64) -> Result<(), Error>;
+
+    /// Drops every board's entry and reactions for a message that no longer exists
+    async fn forget_message(&self, message_id: u64) -> Result<(), Error>;
+
+    async fn count_star_reactions(&self, board_id: i64, message_id: u64) -> Result<i64, Error>;
+
+    /// Most starred posts in the scope, best first
+    async fn top_messages(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarredMessage>, Error>;
+
+    /// Authors by stars on their starboard posts
+    async fn top_receivers(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarTally>, Error>;
+
+    /// Users by stars handed out, `messages` is how many distinct messages they starred
+    async fn top_givers(&self, scope: &StatsScope, limit: i64) -> Result<Vec<StarTally>, Error>;
+
+    /// (stars received, posts on the board, stars given)
+    async fn user_stats(&self, scope: &StatsScope, user_id: u64) -> Result<(i64, i64, i64), Error>;
+
+    async fn user_top_messages(&self, scope: &StatsScope, user_id: u64, limit: i64) -> Result<Vec<StarredMessage>, Error>;
+
+    async fn random_starred_message(&self, scope: &StatsScope) -> Result<Option<StarredMessage>, Error>;
+}
@@ -1,5 +1,4 @@
 use poise::serenity_prelude as serenity;
-use sqlx::SqlitePool;
 use std::env;
 use std::sync::Arc;
 use poise::futures_util::lock::Mutex;
@@ -10,20 +9,26 @@ mod helpers;
 
 mod types;
 mod structs;
+#[cfg(test)]
+mod tests;
 use types::{Context, Data, Error};
 
 use crate::commands::all_commands;
-use crate::helpers::auth::AuthDatabase;
-use crate::helpers::starboard;
-use crate::helpers::reminder::ReminderStore;
+use crate::helpers::reminder_scheduler::ReminderScheduler;
+use crate::helpers::reminder_components::{handle_reminder_component, REMINDER_COMPONENT_PREFIX};
 use crate::helpers::reminder_task::reminder_task;
-use crate::helpers::starboard::Database;
+use crate::helpers::starboard_activity::record_message;
+use crate::helpers::starboard_rescan::startup_rescan;
+use crate::helpers::storage::Storage;
+use crate::structs::time_parse::ParsedDuration;
 
 
 use crate::helpers::starboard_manager::{
     handle_reaction_add,
     handle_reaction_remove,
-    handle_reaction_remove_all
+    handle_reaction_remove_all,
+    handle_message_update,
+    handle_message_delete,
 };
 
 // split this whole file later down the line
@@ -37,11 +42,8 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>)
         poise::FrameworkError::ArgumentParse { ctx, error, .. } => {
             println!("Command failed: `{}`: {:?}", ctx.command().name, error);
 
-            match ctx {
-                poise::Context::Prefix(prefix_ctx) => {
-                    let _ = prefix_ctx.msg.react(&prefix_ctx.serenity_context().http, '❌').await;
-                }
-                _ => {}
+            if let poise::Context::Prefix(prefix_ctx) = ctx {
+                let _ = prefix_ctx.msg.react(&prefix_ctx.serenity_context().http, '❌').await;
             }
         }
         poise::FrameworkError::CommandCheckFailed { ctx, .. } => {
@@ -53,11 +55,8 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>)
 
             println!("Command permissions failed: `{}` ran by {} in {}", ctx.command().name, ctx.author().name, guild_name);
 
-            match ctx {
-                poise::Context::Prefix(prefix_ctx) => {
-                    let _ = prefix_ctx.msg.react(&prefix_ctx.serenity_context().http, '❌').await;
-                }
-                _ => {}
+            if let poise::Context::Prefix(prefix_ctx) = ctx {
+                let _ = prefix_ctx.msg.react(&prefix_ctx.serenity_context().http, '❌').await;
             }
         }
         _ => {
@@ -85,12 +84,86 @@ async fn event_handler(
         FullEvent::ReactionRemoveAll {channel_id, removed_from_message_id} => {
             handle_reaction_remove_all(ctx, *channel_id, *removed_from_message_id, data).await?;
         }
+        FullEvent::Message { new_message } if !new_message.author.bot => {
+            record_message(new_message.channel_id);
+        }
+        FullEvent::MessageUpdate { event, .. } => {
+            handle_message_update(ctx, event, data).await?;
+        }
+        FullEvent::MessageDelete { deleted_message_id, guild_id, .. } => {
+            handle_message_delete(ctx, *guild_id, &[*deleted_message_id], data).await?;
+        }
+        FullEvent::MessageDeleteBulk { multiple_deleted_messages_ids, guild_id, .. } => {
+            handle_message_delete(ctx, *guild_id, multiple_deleted_messages_ids, data).await?;
+        }
+        FullEvent::CacheReady { .. } => {
+            // opt in with e.g. STARBOARD_RESCAN_ON_STARTUP=7d to re-check the last week of stars
+            if let Ok(window) = env::var("STARBOARD_RESCAN_ON_STARTUP") {
+                match ParsedDuration::new(&window) {
+                    Ok(parsed) => startup_rescan(ctx, data, parsed.as_chrono_duration()).await,
+                    Err(e) => eprintln!("Invalid STARBOARD_RESCAN_ON_STARTUP {}: {}", window, e),
+                }
+            }
+        }
+        FullEvent::InteractionCreate { interaction: serenity::Interaction::Component(component) }
+            if component.data.custom_id.starts_with(REMINDER_COMPONENT_PREFIX) => {
+            handle_reminder_component(ctx, component, data).await?;
+        }
         _ => {}
     }
     Ok(())
 }
 
 
+/// Commands, hooks and the event handler, shared with the test harness
+pub(crate) fn framework_options() -> poise::FrameworkOptions<Data, Error> {
+    poise::FrameworkOptions {
+        commands: all_commands(),
+        event_handler: |ctx, event, framework, data| {
+            Box::pin(event_handler(ctx, event, framework, data))
+        },
+        prefix_options: poise::PrefixFrameworkOptions {
+            prefix: Some("n".into()),
+            ..Default::default()
+        },
+        pre_command: |ctx| {
+            Box::pin(async move {
+                let data = ctx.data();
+                println!("{}", *data.last_command_success.lock().await);
+                *data.last_command_success.lock().await = true;
+            })
+        },
+        on_error: |error| Box::pin(on_error(error)),
+        post_command: |ctx| {
+            Box::pin(async move {
+                let data = ctx.data();
+                let success = *data.last_command_success.lock().await;
+                println!("Success: {:?}", success);
+                if success {
+                    println!("Command {} ran", ctx.command().qualified_name);
+                    match ctx {
+                        poise::Context::Prefix(prefix_ctx) =>
+                            {
+                                if let Err(e) = prefix_ctx.msg.react(&prefix_ctx.serenity_context().http, '✅').await
+                                {
+                                    eprintln!("Error sending message: {:?}", e);
+                                }
+                            }
+                        poise::Context::Application(_) => {
+
+                        }
+                    }
+                } else {
+                    println!("Command {} failed", ctx.command().qualified_name);
+
+                }
+                *data.last_command_success.lock().await = true;
+            })
+        },
+        ..Default::default()
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let token = env::var("DISCORD_TOKEN")
@@ -99,99 +172,55 @@ async fn main() -> Result<(), Error> {
     let db_url = env::var("DATABASE_URL")
         .expect("Missing DATABASE_URL");
 
-    let pool = SqlitePool::connect(&db_url).await?;
+    let storage = Storage::connect(&db_url).await?;
 
     let http_client = Arc::new(serenity::Http::new(&token));
 
     let intents =
-        serenity::GatewayIntents::GUILD_MESSAGES
+        serenity::GatewayIntents::GUILDS
+            | serenity::GatewayIntents::GUILD_MESSAGES
             | serenity::GatewayIntents::MESSAGE_CONTENT
             | serenity::GatewayIntents::GUILD_MEMBERS
             | serenity::GatewayIntents::GUILD_MESSAGE_REACTIONS
             | serenity::GatewayIntents::DIRECT_MESSAGES;
 
     let framework = poise::Framework::builder()
-        .options(poise::FrameworkOptions {
-            commands: all_commands(),
-            event_handler: |ctx, event, framework, data| {
-                Box::pin(event_handler(ctx, event, framework, data))
-            },
-            prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some("n".into()),
-                ..Default::default()
-            },
-            pre_command: |ctx| {
-                Box::pin(async move {
-                    let data = ctx.data();
-                    println!("{}", *data.last_command_success.lock().await);
-                    *data.last_command_success.lock().await = true;
-                })
-            },
-            on_error: |error| Box::pin(on_error(error)),
-            post_command: |ctx| {
-                Box::pin(async move {
-                    let data = ctx.data();
-                    let success = *data.last_command_success.lock().await;
-                    println!("Success: {:?}", success);
-                    if success {
-                        println!("Command {} ran", ctx.command().qualified_name);
-                        match ctx {
-                            poise::Context::Prefix(prefix_ctx) =>
-                                {
-                                    if let Err(e) = prefix_ctx.msg.react(&prefix_ctx.serenity_context().http, '✅').await
-                                    {
-                                        eprintln!("Error sending message: {:?}", e);
-                                    }
-                                }
-                            poise::Context::Application(_) => {
-
-                            }
-                        }
-                    } else {
-                        println!("Command {} failed", ctx.command().qualified_name);
-
-                    }
-                    *data.last_command_success.lock().await = true;
-                })
-            },
-            ..Default::default()
-        })
+        .options(framework_options())
         .setup(move |_ctx, _ready, _framework| {
-            let pool = pool.clone();
+            let storage = storage.clone();
             let http_client = Arc::clone(&http_client);
 
             Box::pin(async move {
-                let reminders = ReminderStore::new(pool.clone());
-                let starboard = Database::new(&db_url).await?;
-                let auth = Arc::new(AuthDatabase::new(pool.clone()));
-                auth.create_tables().await?;
-                helpers::role_colours::init_role_colour_table(&pool).await?;
-
-                sqlx::query("PRAGMA journal_mode = WAL;").execute(&pool).await?;
-                sqlx::query("PRAGMA synchronous = NORMAL;").execute(&pool).await?;
+                let Storage { pool, reminders, starboard, auth, role_colours } = storage;
+                let reminder_scheduler = Arc::new(ReminderScheduler::new());
 
                 // the more i put into the data pool the more concerning
                 // it seems ngl
 
+                let starboard_state = Arc::new(helpers::starboard_state::StarboardState::new());
+
                 let data = Data {
                     db: pool.clone(),
                     last_command_success: Arc::from(Mutex::new(true)),
                     reminders: reminders.clone(),
+                    reminder_scheduler: Arc::clone(&reminder_scheduler),
                     http_client: Arc::clone(&http_client),
                     starboard: starboard.clone(),
-                    starboard_lock: Mutex::new(()),
+                    starboard_state: Arc::clone(&starboard_state),
                     auth: auth.clone(),
-
+                    role_colours: role_colours.clone(),
                 };
 
                 let task_data = Data {
                     db: pool,
                     last_command_success: Arc::new(Default::default()),
                     reminders,
+                    reminder_scheduler,
                     http_client,
                     starboard,
-                    starboard_lock: Mutex::new(()),
+                    starboard_state,
                     auth,
+                    role_colours,
                 };
 
 
@@ -1,10 +1,15 @@
-use sqlx::{FromRow, SqlitePool};
+use chrono::{DateTime, Utc};
+use rand::distr::{Alphanumeric, SampleString};
+use rand::rng;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
 use serde::{Deserialize, Serialize};
 
+const KEY_LENGTH: usize = 24;
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct AuthConfig {
     pub guild_id: i64,
-    pub key_id: String,
     pub role_id: i64,
     pub enabled: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
@@ -16,4 +21,66 @@ pub struct AuthenticatedUser {
     pub user_id: i64,
     pub guild_id: i64,
     pub authenticated_at: chrono::DateTime<chrono::Utc>,
-}
\ No newline at end of file
+}
+
+/// A key that can be redeemed for a guild's auth role, only the hash is ever stored
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AuthKey {
+    pub id: i64,
+    pub guild_id: i64,
+    pub key_hash: String,
+    pub label: Option<String>,
+    pub created_by: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_uses: Option<i64>,
+    pub uses: i64,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    Usable,
+    Revoked,
+    Expired,
+    UsedUp,
+}
+
+impl AuthKey {
+    /// Unsaved key for `plaintext`, the store fills in the id and creation time
+    pub fn new(guild_id: i64, plaintext: &str, created_by: i64) -> Self {
+        Self {
+            id: 0,
+            guild_id,
+            key_hash: hash_key(plaintext),
+            label: None,
+            created_by,
+            created_at: Utc::now(),
+            expires_at: None,
+            max_uses: None,
+            uses: 0,
+            revoked: false,
+        }
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> KeyStatus {
+        if self.revoked {
+            KeyStatus::Revoked
+        } else if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            KeyStatus::Expired
+        } else if self.max_uses.is_some_and(|max_uses| self.uses >= max_uses) {
+            KeyStatus::UsedUp
+        } else {
+            KeyStatus::Usable
+        }
+    }
+}
+
+/// Random key handed to the creator once
+pub fn generate_key() -> String {
+    Alphanumeric.sample_string(&mut rng(), KEY_LENGTH)
+}
+
+pub fn hash_key(plaintext: &str) -> String {
+    hex::encode(Sha256::digest(plaintext.trim().as_bytes()))
+}
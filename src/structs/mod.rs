@@ -1,4 +1,6 @@
 pub mod starboard_message;
 pub mod reminders;
+pub mod recurrence;
+pub mod snowflake;
 pub mod time_parse;
 pub(crate) mod auth;
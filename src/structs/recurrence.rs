@@ -0,0 +1,387 @@
+use std::fmt;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use regex::Regex;
+use thiserror::Error;
+
+use crate::structs::time_parse::ParsedDuration;
+
+static COUNT_SUFFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+(?:x(\d+)|(?:for\s+)?(\d+)\s+times?)$").unwrap());
+static TIME_OF_DAY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{1,2})(?::(\d{2}))?\s*(am|pm)?$").unwrap());
+
+// all schedules are evaluated in UTC, there's no per user timezone yet
+
+#[derive(Debug, Error)]
+pub enum RecurrenceError {
+    #[error("Invalid schedule. Try: every 2h, every day at 9am, weekdays at 17:00, every mon,thu at 10:00, cron 0 9 * * 1-5")]
+    InvalidFormat,
+    #[error("Weekday and weekend schedules need a time, e.g. weekdays at 9am")]
+    MissingTime,
+    #[error("Invalid time of day: {0}")]
+    InvalidTime(String),
+    #[error("Invalid end date: {0} (use YYYY-MM-DD or YYYY-MM-DD HH:MM)")]
+    InvalidUntil(String),
+    #[error("Invalid cron expression: {0}")]
+    InvalidCron(String),
+    #[error("Repeat interval must be at least one minute")]
+    IntervalTooShort,
+    #[error("Occurrence count must be at least 1")]
+    InvalidCount,
+    #[error("Schedule never fires before its end date")]
+    NeverFires,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecurrenceRule {
+    Interval(Duration),
+    Daily { at: NaiveTime },
+    Weekly { days: Vec<Weekday>, at: NaiveTime },
+    Cron(CronSchedule),
+}
+
+#[derive(Debug, Clone)]
+pub struct Recurrence {
+    pub rule: RecurrenceRule,
+    pub until: Option<DateTime<Utc>>,
+    pub max_occurrences: Option<i64>,
+}
+
+impl Recurrence {
+    /// Returns `true` if the input looks like a schedule rather than a plain duration
+    pub fn is_recurring_spec(input: &str) -> bool {
+        let lower = input.trim().to_lowercase();
+        ["every ", "daily", "weekdays", "weekends", "cron "]
+            .iter()
+            .any(|prefix| lower.starts_with(prefix))
+    }
+
+    /// Parses a schedule with optional `until <date>` and `x<N>` / `<N> times` suffixes
+    pub fn parse(input: &str) -> Result<Self, RecurrenceError> {
+        let mut spec = input.trim().to_lowercase();
+        let mut max_occurrences = None;
+        let mut until = None;
+
+        if let Some(captures) = COUNT_SUFFIX.captures(&spec) {
+            let count: i64 = captures.get(1).or(captures.get(2))
+                .and_then(|m| m.as_str().parse().ok())
+                .ok_or(RecurrenceError::InvalidCount)?;
+            if count < 1 {
+                return Err(RecurrenceError::InvalidCount);
+            }
+            max_occurrences = Some(count);
+            spec.truncate(captures.get(0).unwrap().start());
+        }
+
+        if let Some(index) = spec.find(" until ") {
+            until = Some(parse_until(spec[index + 7..].trim())?);
+            spec.truncate(index);
+        }
+
+        Ok(Self {
+            rule: RecurrenceRule::parse(spec.trim())?,
+            until,
+            max_occurrences,
+        })
+    }
+
+    /// First occurrence after `now`, or `None` if it would fall past the end date
+    pub fn first_occurrence(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, RecurrenceError> {
+        let first = match &self.rule {
+            RecurrenceRule::Interval(interval) => Some(now + *interval),
+            _ => self.rule.next_after(now),
+        };
+
+        first
+            .filter(|at| self.until.is_none_or(|until| *at <= until))
+            .ok_or(RecurrenceError::NeverFires)
+    }
+}
+
+impl RecurrenceRule {
+    pub fn parse(input: &str) -> Result<Self, RecurrenceError> {
+        let spec = input.trim().to_lowercase();
+
+        if let Some(expression) = spec.strip_prefix("cron ") {
+            return Ok(RecurrenceRule::Cron(CronSchedule::parse(expression)?));
+        }
+
+        let spec = spec.strip_prefix("every ").unwrap_or(&spec).trim();
+
+        let (days_part, at) = match spec.split_once(" at ") {
+            Some((days, time)) => (days.trim(), Some(parse_time_of_day(time.trim())?)),
+            None => (spec, None),
+        };
+
+        match (days_part, at) {
+            ("day" | "daily", Some(at)) => Ok(RecurrenceRule::Daily { at }),
+            ("weekday" | "weekdays", Some(at)) => Ok(RecurrenceRule::Weekly {
+                days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+                at,
+            }),
+            ("weekend" | "weekends", Some(at)) => Ok(RecurrenceRule::Weekly {
+                days: vec![Weekday::Sat, Weekday::Sun],
+                at,
+            }),
+            (days, Some(at)) => {
+                let mut parsed = days
+                    .split(',')
+                    .map(|day| parse_weekday(day.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                parsed.sort_by_key(|day| day.num_days_from_monday());
+                parsed.dedup();
+                Ok(RecurrenceRule::Weekly { days: parsed, at })
+            }
+            ("minute", None) => Ok(RecurrenceRule::Interval(Duration::minutes(1))),
+            ("hour", None) => Ok(RecurrenceRule::Interval(Duration::hours(1))),
+            ("day" | "daily", None) => Ok(RecurrenceRule::Interval(Duration::days(1))),
+            ("week", None) => Ok(RecurrenceRule::Interval(Duration::weeks(1))),
+            ("weekday" | "weekdays" | "weekend" | "weekends", None) => Err(RecurrenceError::MissingTime),
+            (interval, None) => {
+                let parsed = ParsedDuration::new(interval)
+                    .map_err(|_| RecurrenceError::InvalidFormat)?;
+                if parsed.duration < Duration::minutes(1) {
+                    return Err(RecurrenceError::IntervalTooShort);
+                }
+                Ok(RecurrenceRule::Interval(parsed.duration))
+            }
+        }
+    }
+
+    /// Next occurrence strictly after `after`
+    ///
+    /// Interval rules have no calendar anchor, use [`RecurrenceRule::next_from`] for those.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            RecurrenceRule::Interval(interval) => Some(after + *interval),
+            RecurrenceRule::Daily { at } => {
+                (0..=1).map(|offset| at_time(after.date_naive() + Duration::days(offset), *at))
+                    .find(|candidate| *candidate > after)
+            }
+            RecurrenceRule::Weekly { days, at } => {
+                (0..=7).map(|offset| after.date_naive() + Duration::days(offset))
+                    .filter(|date| days.contains(&date.weekday()))
+                    .map(|date| at_time(date, *at))
+                    .find(|candidate| *candidate > after)
+            }
+            RecurrenceRule::Cron(schedule) => schedule.next_after(after),
+        }
+    }
+
+    /// Next occurrence following `previous` that is also after `now`, missed occurrences are skipped
+    pub fn next_from(&self, previous: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            RecurrenceRule::Interval(interval) => {
+                let interval_secs = interval.num_seconds().max(1);
+                let behind = (now - previous).num_seconds().max(0);
+                let steps = behind / interval_secs + 1;
+                Some(previous + Duration::seconds(steps * interval_secs))
+            }
+            _ => self.next_after(previous.max(now)),
+        }
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecurrenceRule::Interval(interval) => {
+                let seconds = interval.num_seconds();
+                if seconds % 86400 == 0 {
+                    write!(f, "every {}d", seconds / 86400)
+                } else if seconds % 3600 == 0 {
+                    write!(f, "every {}h", seconds / 3600)
+                } else if seconds % 60 == 0 {
+                    write!(f, "every {}m", seconds / 60)
+                } else {
+                    write!(f, "every {}s", seconds)
+                }
+            }
+            RecurrenceRule::Daily { at } => write!(f, "every day at {}", at.format("%H:%M")),
+            RecurrenceRule::Weekly { days, at } => {
+                let days = days.iter()
+                    .map(|day| day.to_string().to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(f, "every {} at {}", days, at.format("%H:%M"))
+            }
+            RecurrenceRule::Cron(schedule) => write!(f, "cron {}", schedule.expression),
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rule)?;
+        if let Some(until) = self.until {
+            write!(f, " until {}", until.format("%Y-%m-%d %H:%M"))?;
+        }
+        if let Some(count) = self.max_occurrences {
+            write!(f, " x{}", count)?;
+        }
+        Ok(())
+    }
+}
+
+fn at_time(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(time))
+}
+
+fn parse_weekday(input: &str) -> Result<Weekday, RecurrenceError> {
+    let day = input.strip_suffix('s').filter(|d| d.len() > 2).unwrap_or(input);
+    day.parse::<Weekday>().map_err(|_| RecurrenceError::InvalidFormat)
+}
+
+/// Accepts `17:00`, `9:30`, `9`, `9am`, `9:30pm` and `12am`
+fn parse_time_of_day(input: &str) -> Result<NaiveTime, RecurrenceError> {
+    let invalid = || RecurrenceError::InvalidTime(input.to_string());
+
+    let captures = TIME_OF_DAY.captures(input).ok_or_else(invalid)?;
+    let mut hour: u32 = captures[1].parse().map_err(|_| invalid())?;
+    let minute: u32 = captures.get(2).map_or(Ok(0), |m| m.as_str().parse()).map_err(|_| invalid())?;
+
+    match captures.get(3).map(|m| m.as_str()) {
+        Some(_) if hour == 0 || hour > 12 => return Err(invalid()),
+        Some("am") => hour %= 12,
+        Some(_) => hour = hour % 12 + 12,
+        None => {}
+    }
+
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)
+}
+
+fn parse_until(input: &str) -> Result<DateTime<Utc>, RecurrenceError> {
+    let invalid = || RecurrenceError::InvalidUntil(input.to_string());
+
+    if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+        return Ok(Utc.from_utc_datetime(&datetime));
+    }
+
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d").map_err(|_| invalid())?;
+    let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).ok_or_else(invalid)?;
+    Ok(at_time(date, end_of_day))
+}
+
+/// Standard five field cron expression (minute hour day-of-month month day-of-week)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days_of_month: Vec<u32>,
+    months: Vec<u32>,
+    days_of_week: Vec<u32>,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, RecurrenceError> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(RecurrenceError::InvalidCron(expression.to_string()));
+        }
+
+        let mut days_of_week = parse_cron_field(fields[4], 0, 7, expression)?;
+        // 7 is an alias for sunday
+        if days_of_week.contains(&7) {
+            days_of_week.retain(|day| *day != 7);
+            if !days_of_week.contains(&0) {
+                days_of_week.insert(0, 0);
+            }
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_cron_field(fields[0], 0, 59, expression)?,
+            hours: parse_cron_field(fields[1], 0, 23, expression)?,
+            days_of_month: parse_cron_field(fields[2], 1, 31, expression)?,
+            months: parse_cron_field(fields[3], 1, 12, expression)?,
+            days_of_week,
+            // a stepped `*/2` still counts as unrestricted, same as vixie cron
+            dom_restricted: !fields[2].starts_with('*'),
+            dow_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months.contains(&date.month()) {
+            return false;
+        }
+
+        let dom = self.days_of_month.contains(&date.day());
+        let dow = self.days_of_week.contains(&date.weekday().num_days_from_sunday());
+
+        // cron semantics, when both day fields are restricted either one may match,
+        // otherwise both have to (a plain `*` matches every day anyway)
+        if self.dom_restricted && self.dow_restricted {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc();
+
+        // a few years covers every satisfiable expression, including feb 29th
+        for offset in 0..(366 * 5) {
+            let date = start.date() + Duration::days(offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+
+            for &hour in &self.hours {
+                for &minute in &self.minutes {
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if offset == 0 && (candidate.hour(), candidate.minute()) <= (start.hour(), start.minute()) {
+                        continue;
+                    }
+                    return Some(Utc.from_utc_datetime(&candidate));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn parse_cron_field(field: &str, min: u32, max: u32, expression: &str) -> Result<Vec<u32>, RecurrenceError> {
+    let invalid = || RecurrenceError::InvalidCron(expression.to_string());
+    let mut values = Vec::new();
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse::<u32>().map_err(|_| invalid())?,
+                    end.parse::<u32>().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse::<u32>().map_err(|_| invalid())?;
+                    // `5/15` means starting at 5 up to the max
+                    (value, if part.contains('/') { max } else { value })
+                }
+            },
+        };
+
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        values.extend((start..=end).step_by(step as usize));
+    }
+
+    values.sort_unstable();
+    values.dedup();
+    Ok(values)
+}
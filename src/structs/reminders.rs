@@ -1,38 +1,137 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{ChannelId, GuildId, Message, MessageId, RoleId, UserId};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
+use crate::structs::recurrence::{Recurrence, RecurrenceRule};
+use crate::structs::snowflake::Snowflake;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(transparent)]
+#[serde(transparent)]
+pub struct ReminderId(pub i64);
+
+impl fmt::Display for ReminderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Reminder {
-    pub id: i64,
-    pub user_id: String,
+    pub id: ReminderId,
+    pub user_id: Snowflake<UserId>,
     pub context_message_url: Option<String>,
     pub remind_at: DateTime<Utc>,
     pub reminder_message: String,
     pub sent: bool,
     pub created_at: Option<DateTime<Utc>>,
+    pub recurrence: Option<String>,
+    pub recur_until: Option<DateTime<Utc>>,
+    pub max_occurrences: Option<i64>,
+    pub occurrences: i64,
+    pub channel_id: Option<Snowflake<ChannelId>>,
+    pub attempts: i64,
+    pub retry_at: Option<DateTime<Utc>>,
+    pub failed: bool,
+    pub failure_reason: Option<String>,
+    pub acknowledged: bool,
+    pub target_channel_id: Option<Snowflake<ChannelId>>,
+    pub role_id: Option<Snowflake<RoleId>>,
+    pub context_channel_id: Option<Snowflake<ChannelId>>,
+    pub context_message_id: Option<Snowflake<MessageId>>,
 }
 
 impl Reminder {
     pub fn new(
-        user_id: String,
+        user_id: UserId,
         remind_at: DateTime<Utc>,
         reminder_message: String,
         context_message_url: Option<String>,
+        channel_id: Option<ChannelId>,
     ) -> Self {
         Self {
-            id: 0,
-            user_id,
+            id: ReminderId(0),
+            user_id: user_id.into(),
             context_message_url,
             remind_at,
             reminder_message,
             sent: false,
             created_at: None,
+            recurrence: None,
+            recur_until: None,
+            max_occurrences: None,
+            occurrences: 0,
+            channel_id: channel_id.map(Snowflake),
+            attempts: 0,
+            retry_at: None,
+            failed: false,
+            failure_reason: None,
+            acknowledged: false,
+            target_channel_id: None,
+            role_id: None,
+            context_channel_id: None,
+            context_message_id: None,
         }
     }
 
+    /// When the scheduler should next try to deliver this, a pending retry takes priority
+    pub fn fire_at(&self) -> DateTime<Utc> {
+        self.retry_at.unwrap_or(self.remind_at)
+    }
+
     pub fn is_due(&self) -> bool {
-        self.remind_at <= Utc::now() && !self.sent
+        self.fire_at() <= Utc::now() && !self.sent && !self.failed
+    }
+
+    /// A recurring reminder whose occurrence already went out and was snoozed, the row
+    /// already points at the following occurrence so delivering it must not advance again
+    pub fn is_snoozed_occurrence(&self, now: DateTime<Utc>) -> bool {
+        self.recurrence.is_some() && self.retry_at.is_some() && self.remind_at > now
+    }
+
+    pub fn recurring(mut self, recurrence: &Recurrence) -> Self {
+        self.recurrence = Some(recurrence.rule.to_string());
+        self.recur_until = recurrence.until;
+        self.max_occurrences = recurrence.max_occurrences;
+        self
+    }
+
+    /// Posts the reminder in a channel rather than the owner's DMs
+    pub fn in_channel(mut self, channel_id: ChannelId) -> Self {
+        self.target_channel_id = Some(channel_id.into());
+        self
+    }
+
+    pub fn pinging_role(mut self, role_id: RoleId) -> Self {
+        self.role_id = Some(role_id.into());
+        self
+    }
+
+    /// Links the reminder to a message whose content gets quoted when it fires
+    ///
+    /// Interaction and fetched messages have no `guild_id`, so the guild is passed in.
+    pub fn about(mut self, message: &Message, guild_id: Option<GuildId>) -> Self {
+        self.context_message_url = Some(message.id.link(message.channel_id, guild_id));
+        self.context_channel_id = Some(message.channel_id.into());
+        self.context_message_id = Some(message.id.into());
+        self
+    }
+
+    pub fn rule(&self) -> Option<RecurrenceRule> {
+        self.recurrence.as_deref().and_then(|rule| RecurrenceRule::parse(rule).ok())
+    }
+
+    /// Where the reminder goes after the current occurrence fires, `None` once the series is over
+    pub fn next_occurrence(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.max_occurrences.is_some_and(|max| self.occurrences + 1 >= max) {
+            return None;
+        }
+
+        self.rule()?
+            .next_from(self.remind_at, now)
+            .filter(|next| self.recur_until.is_none_or(|until| *next <= until))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::num::NonZeroU64;
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+use sqlx::{Decode, Encode, Sqlite, Type};
+
+/// Typed Discord ID stored in an INTEGER column
+///
+/// SQLite only has signed 64 bit integers, snowflakes fit in 63 bits so the cast is lossless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Snowflake<T>(pub T);
+
+impl<T> Deref for Snowflake<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Snowflake<T> {
+    fn from(id: T) -> Self {
+        Self(id)
+    }
+}
+
+impl<T: PartialEq> PartialEq<T> for Snowflake<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Snowflake<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T> Type<Sqlite> for Snowflake<T> {
+    fn type_info() -> SqliteTypeInfo {
+        <i64 as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <i64 as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q, T: Copy + Into<u64>> Encode<'q, Sqlite> for Snowflake<T> {
+    fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        let id: u64 = self.0.into();
+        args.push(SqliteArgumentValue::Int64(id as i64));
+        Ok(IsNull::No)
+    }
+}
+
+impl<'r, T: From<NonZeroU64>> Decode<'r, Sqlite> for Snowflake<T> {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, BoxDynError> {
+        let id = <i64 as Decode<Sqlite>>::decode(value)?;
+        let id = NonZeroU64::new(id as u64).ok_or("Discord IDs can't be zero")?;
+        Ok(Self(T::from(id)))
+    }
+}
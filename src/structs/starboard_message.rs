@@ -1,20 +1,273 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use chrono::Utc;
+use poise::serenity_prelude::{CreateEmbedFooter, EmojiId, MessageId, ReactionType, Timestamp};
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+static CUSTOM_EMOJI: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^<(a?):(\w{2,32}):(\d{17,20})>$").unwrap());
 
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct StarboardConfig {
+    pub id: i64,
     pub guild_id: String,
+    pub name: String,
     pub starboard_channel_id: Option<String>,
     pub threshold: i64,
     pub star_emoji: String,
+    pub colour: Option<i64>,
     pub self_star_allowed: bool,
     pub enabled: bool,
+    pub ignore_bots: bool,
+    pub on_delete: DeletePolicy,
+    pub max_age_days: Option<i64>,
+    pub activity_scale: Option<i64>,
+    pub keep_after_days: Option<i64>,
+    /// `min emoji [colour]` entries split by `;`, read through `tiers()`
+    pub tiers: String,
+    pub fame_stars: Option<i64>,
+    pub fame_channel_id: Option<String>,
+    pub fame_pin: bool,
+}
+
+impl StarboardConfig {
+    /// Same defaults as the `starboard_config` table
+    pub fn new(guild_id: u64, name: &str) -> Self {
+        Self {
+            id: 0,
+            guild_id: guild_id.to_string(),
+            name: name.to_string(),
+            starboard_channel_id: None,
+            threshold: 2,
+            star_emoji: "⭐".to_string(),
+            colour: None,
+            self_star_allowed: false,
+            enabled: true,
+            ignore_bots: false,
+            on_delete: DeletePolicy::Mark,
+            max_age_days: None,
+            activity_scale: None,
+            keep_after_days: None,
+            tiers: String::new(),
+            fame_stars: None,
+            fame_channel_id: None,
+            fame_pin: false,
+        }
+    }
+
+    /// Messages older than the board's max age don't get posted
+    pub fn is_too_old(&self, message_id: MessageId) -> bool {
+        self.max_age_days.is_some_and(|days| age_days(message_id.created_at()) > days)
+    }
+
+    /// Posts older than `keep_after_days` stay up when they drop below the threshold
+    pub fn keeps_post(&self, starboard_message_id: MessageId) -> bool {
+        self.keep_after_days.is_some_and(|days| age_days(starboard_message_id.created_at()) >= days)
+    }
+
+    pub fn emoji(&self) -> StarEmoji {
+        StarEmoji::parse(&self.star_emoji).unwrap_or_else(|| StarEmoji::Unicode(self.star_emoji.clone()))
+    }
+
+    /// Tiers from fewest stars up, entries that don't parse are skipped
+    pub fn tiers(&self) -> Vec<StarTier> {
+        let mut tiers: Vec<StarTier> = self.tiers
+            .split(';')
+            .filter_map(|entry| {
+                let mut parts = entry.split_whitespace();
+                Some(StarTier {
+                    min_stars: parts.next()?.parse().ok()?,
+                    emoji: parts.next()?.to_string(),
+                    colour: parts.next().and_then(|colour| i64::from_str_radix(colour, 16).ok()),
+                })
+            })
+            .collect();
+        tiers.sort_by_key(|tier| tier.min_stars);
+        tiers
+    }
+
+    /// Adds a tier or replaces the one starting at the same count
+    pub fn set_tier(&mut self, tier: StarTier) {
+        let mut tiers = self.tiers();
+        tiers.retain(|existing| existing.min_stars != tier.min_stars);
+        tiers.push(tier);
+        self.store_tiers(tiers);
+    }
+
+    /// Returns `false` if no tier starts at `min_stars`
+    pub fn remove_tier(&mut self, min_stars: i64) -> bool {
+        let mut tiers = self.tiers();
+        let before = tiers.len();
+        tiers.retain(|tier| tier.min_stars != min_stars);
+        let removed = tiers.len() != before;
+        self.store_tiers(tiers);
+        removed
+    }
+
+    fn store_tiers(&mut self, mut tiers: Vec<StarTier>) {
+        tiers.sort_by_key(|tier| tier.min_stars);
+        self.tiers = tiers
+            .iter()
+            .map(|tier| match tier.colour {
+                Some(colour) => format!("{} {} {:06x}", tier.min_stars, tier.emoji, colour),
+                None => format!("{} {}", tier.min_stars, tier.emoji),
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+    }
+
+    /// Highest tier a post with `star_count` stars has reached
+    pub fn tier_for(&self, star_count: i64) -> Option<StarTier> {
+        self.tiers().into_iter().rev().find(|tier| star_count >= tier.min_stars)
+    }
+
+    /// Emoji shown in a post's footer, the tier's if it reached one
+    pub fn footer_emoji(&self, star_count: i64) -> StarEmoji {
+        self.tier_for(star_count)
+            .and_then(|tier| StarEmoji::parse(&tier.emoji))
+            .unwrap_or_else(|| self.emoji())
+    }
+}
+
+/// What happens to a board's post when the original message is deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, poise::ChoiceParameter)]
+#[sqlx(rename_all = "lowercase")]
+pub enum DeletePolicy {
+    #[name = "keep"]
+    Keep,
+    #[name = "mark deleted"]
+    Mark,
+    #[name = "remove"]
+    Remove,
+}
+
+/// Footer emoji and colour a post switches to once it has `min_stars`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StarTier {
+    pub min_stars: i64,
+    pub emoji: String,
+    pub colour: Option<i64>,
+}
+
+/// Which message a star was left on, stored as bits in `starboard_reactions.sources` so a
+/// user starring both the original and its starboard copy is one row and one star
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StarSource {
+    Original,
+    Copy,
+}
+
+impl StarSource {
+    pub fn bit(self) -> i64 {
+        match self {
+            StarSource::Original => 1,
+            StarSource::Copy => 2,
+        }
+    }
+}
+
+/// What a `starboard block` keeps off a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, poise::ChoiceParameter)]
+#[sqlx(rename_all = "lowercase")]
+pub enum BlockMode {
+    #[name = "their messages"]
+    Messages,
+    #[name = "their stars"]
+    Stars,
+    #[name = "both"]
+    Both,
+}
+
+impl BlockMode {
+    pub fn blocks_messages(self) -> bool {
+        matches!(self, BlockMode::Messages | BlockMode::Both)
+    }
+
+    pub fn blocks_stars(self) -> bool {
+        matches!(self, BlockMode::Stars | BlockMode::Both)
+    }
+}
+
+fn age_days(created_at: Timestamp) -> i64 {
+    (Utc::now().timestamp() - created_at.unix_timestamp()) / 86_400
+}
+
+/// Allow or deny list entry for a board, `channel_id` may be a category
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ChannelFilter {
+    pub channel_id: String,
+    pub allow: bool,
+}
+
+/// Emoji a board counts, custom emojis are kept as `<:name:id>` but compared by ID only since
+/// reactions can carry a different name or drop the animated flag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StarEmoji {
+    Unicode(String),
+    Custom { id: EmojiId, name: String, animated: bool },
+}
+
+impl StarEmoji {
+    /// Parses `<:name:id>`, `<a:name:id>` or a unicode emoji, `None` for anything that's plain text
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(captures) = CUSTOM_EMOJI.captures(input) {
+            return Some(Self::Custom {
+                id: captures[3].parse().ok()?,
+                name: captures[2].to_string(),
+                animated: !captures[1].is_empty(),
+            });
+        }
+
+        let looks_like_text = input.chars().any(|c| c.is_ascii_alphanumeric() || c.is_whitespace());
+        if input.is_empty() || looks_like_text || input.chars().count() > 16 {
+            return None;
+        }
+        Some(Self::Unicode(input.to_string()))
+    }
+
+    pub fn matches(&self, reaction: &ReactionType) -> bool {
+        match (self, reaction) {
+            (Self::Custom { id, .. }, ReactionType::Custom { id: reacted, .. }) => id == reacted,
+            // ⭐ and ⭐️ only differ by a variation selector
+            (Self::Unicode(emoji), ReactionType::Unicode(reacted)) => {
+                emoji.replace('\u{FE0F}', "") == reacted.replace('\u{FE0F}', "")
+            }
+            _ => false,
+        }
+    }
+
+    pub fn image_url(&self) -> Option<String> {
+        match self {
+            Self::Custom { id, animated, .. } => Some(format!(
+                "https://cdn.discordapp.com/emojis/{}.{}", id, if *animated { "gif" } else { "png" })),
+            Self::Unicode(_) => None,
+        }
+    }
+
+    /// Footers are plain text, so custom emojis go in as the footer icon instead
+    pub fn footer(&self, star_count: i64) -> CreateEmbedFooter {
+        match self.image_url() {
+            Some(url) => CreateEmbedFooter::new(star_count.to_string()).icon_url(url),
+            None => CreateEmbedFooter::new(format!("{} {}", self, star_count)),
+        }
+    }
+}
+
+impl fmt::Display for StarEmoji {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unicode(emoji) => f.write_str(emoji),
+            Self::Custom { id, name, animated } => {
+                write!(f, "<{}:{}:{}>", if *animated { "a" } else { "" }, name, id)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct StarredMessage {
     pub id: i64,
+    pub board_id: i64,
     pub original_message_id: String,
     pub original_channel_id: String,
     pub starboard_message_id: Option<String>,
@@ -22,4 +275,32 @@ pub struct StarredMessage {
     pub stars: i64,
     pub starred_by: String,
     pub created_at: Option<String>,
+    pub author_id: Option<String>,
+    /// count is no longer kept up to date
+    pub frozen: bool,
+    /// posted by a moderator, stays up below the threshold
+    pub forced: bool,
+    /// already pinned or sent to the hall of fame
+    pub famed: bool,
+}
+
+impl StarredMessage {
+    pub fn link(&self, guild_id: u64) -> String {
+        format!("https://discord.com/channels/{}/{}/{}", guild_id, self.original_channel_id, self.original_message_id)
+    }
+}
+
+/// Which starboard rows the stats commands look at, `since` is compared against `created_at`
+#[derive(Debug, Clone)]
+pub struct StatsScope {
+    pub guild_id: String,
+    pub board_id: Option<i64>,
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct StarTally {
+    pub user_id: String,
+    pub stars: i64,
+    pub messages: i64,
 }
\ No newline at end of file
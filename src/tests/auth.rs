@@ -0,0 +1,198 @@
+use chrono::{Duration, Utc};
+use sqlx::sqlite::SqlitePoolOptions;
+
+use crate::helpers::storage::Storage;
+use crate::structs::auth::{hash_key, AuthKey, KeyStatus};
+use crate::tests::backends;
+
+const GUILD: i64 = 1;
+const ROLE: i64 = 50;
+const CREATOR: i64 = 3;
+
+#[tokio::test]
+async fn config_is_kept_per_guild() {
+    for (backend, storage) in backends().await {
+        let auth = &storage.auth;
+        assert!(auth.get_auth_config(GUILD).await.unwrap().is_none(), "{backend}");
+
+        let created = auth.set_auth_config(GUILD, ROLE, true).await.unwrap();
+        auth.add_auth_key(&AuthKey::new(GUILD, "key", CREATOR)).await.unwrap();
+
+        // updating keeps the row
+        let updated = auth.set_auth_config(GUILD, ROLE, false).await.unwrap();
+        assert_eq!(updated.created_at, created.created_at, "{backend}");
+        assert!(!auth.get_auth_config(GUILD).await.unwrap().unwrap().enabled, "{backend}");
+
+        // the keys go with the config
+        assert!(auth.delete_auth_config(GUILD).await.unwrap().is_some(), "{backend}");
+        assert!(auth.get_auth_config(GUILD).await.unwrap().is_none(), "{backend}");
+        assert!(auth.find_auth_key(GUILD, &hash_key("key")).await.unwrap().is_none(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn keys_are_stored_hashed_and_per_guild() {
+    for (backend, storage) in backends().await {
+        let auth = &storage.auth;
+        let mut key = AuthKey::new(GUILD, "secret", CREATOR);
+        key.label = Some("mods".to_string());
+        let stored = auth.add_auth_key(&key).await.unwrap();
+
+        assert_ne!(stored.key_hash, "secret", "{backend}");
+        assert_eq!(stored.label.as_deref(), Some("mods"), "{backend}");
+        assert_eq!(stored.created_by, CREATOR, "{backend}");
+
+        let found = auth.find_auth_key(GUILD, &hash_key("secret")).await.unwrap().unwrap();
+        assert_eq!(found.id, stored.id, "{backend}");
+        assert!(auth.find_auth_key(GUILD + 1, &hash_key("secret")).await.unwrap().is_none(), "{backend}");
+        assert!(auth.find_auth_key(GUILD, &hash_key("guess")).await.unwrap().is_none(), "{backend}");
+
+        // same key can't be added twice to a guild, another guild is fine
+        assert!(auth.add_auth_key(&key).await.is_err(), "{backend}");
+        auth.add_auth_key(&AuthKey::new(GUILD + 1, "secret", CREATOR)).await.unwrap();
+
+        let newer = auth.add_auth_key(&AuthKey::new(GUILD, "other", CREATOR)).await.unwrap();
+        let ids: Vec<i64> = auth.get_auth_keys(GUILD).await.unwrap().iter().map(|key| key.id).collect();
+        assert_eq!(ids, vec![newer.id, stored.id], "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn keys_run_out_of_uses() {
+    for (backend, storage) in backends().await {
+        let auth = &storage.auth;
+        let mut key = AuthKey::new(GUILD, "twice", CREATOR);
+        key.max_uses = Some(2);
+        let key = auth.add_auth_key(&key).await.unwrap();
+        let now = Utc::now();
+
+        assert!(auth.redeem_auth_key(key.id, now).await.unwrap(), "{backend}");
+        assert!(auth.redeem_auth_key(key.id, now).await.unwrap(), "{backend}");
+        assert!(!auth.redeem_auth_key(key.id, now).await.unwrap(), "{backend}");
+
+        let key = auth.find_auth_key(GUILD, &hash_key("twice")).await.unwrap().unwrap();
+        assert_eq!(key.uses, 2, "{backend}");
+        assert_eq!(key.status(now), KeyStatus::UsedUp, "{backend}");
+
+        // a use given back can be taken again
+        auth.release_auth_key(key.id).await.unwrap();
+        assert!(auth.redeem_auth_key(key.id, now).await.unwrap(), "{backend}");
+        assert!(!auth.redeem_auth_key(key.id, now).await.unwrap(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn keys_expire() {
+    for (backend, storage) in backends().await {
+        let auth = &storage.auth;
+        let mut key = AuthKey::new(GUILD, "soon", CREATOR);
+        key.expires_at = Some(Utc::now() + Duration::hours(1));
+        let key = auth.add_auth_key(&key).await.unwrap();
+
+        assert!(auth.redeem_auth_key(key.id, Utc::now()).await.unwrap(), "{backend}");
+
+        let later = Utc::now() + Duration::hours(2);
+        assert!(!auth.redeem_auth_key(key.id, later).await.unwrap(), "{backend}");
+        assert_eq!(key.status(later), KeyStatus::Expired, "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn revoked_keys_stop_working() {
+    for (backend, storage) in backends().await {
+        let auth = &storage.auth;
+        let key = auth.add_auth_key(&AuthKey::new(GUILD, "leaked", CREATOR)).await.unwrap();
+
+        // only the guild the key belongs to can revoke it
+        assert!(!auth.revoke_auth_key(GUILD + 1, key.id).await.unwrap(), "{backend}");
+        assert!(auth.revoke_auth_key(GUILD, key.id).await.unwrap(), "{backend}");
+        assert!(!auth.revoke_auth_key(GUILD, key.id).await.unwrap(), "{backend}");
+
+        assert!(!auth.redeem_auth_key(key.id, Utc::now()).await.unwrap(), "{backend}");
+        assert!(auth.get_auth_keys(GUILD).await.unwrap().is_empty(), "{backend}");
+        let key = auth.find_auth_key(GUILD, &hash_key("leaked")).await.unwrap().unwrap();
+        assert_eq!(key.status(Utc::now()), KeyStatus::Revoked, "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn users_authenticate_once_per_guild() {
+    for (backend, storage) in backends().await {
+        let auth = &storage.auth;
+        auth.set_auth_config(GUILD, ROLE, true).await.unwrap();
+
+        assert!(!auth.is_user_authenticated(7, GUILD).await.unwrap(), "{backend}");
+        auth.add_authenticated_user(7, GUILD).await.unwrap();
+        assert!(auth.add_authenticated_user(7, GUILD).await.is_err(), "{backend}");
+        // other guilds are separate
+        auth.add_authenticated_user(7, GUILD + 1).await.unwrap();
+
+        assert!(auth.is_user_authenticated(7, GUILD).await.unwrap(), "{backend}");
+        assert_eq!(auth.get_authenticated_users(GUILD).await.unwrap().len(), 1, "{backend}");
+
+        assert!(auth.remove_authenticated_user(7, GUILD).await.unwrap().is_some(), "{backend}");
+        assert!(auth.remove_authenticated_user(7, GUILD).await.unwrap().is_none(), "{backend}");
+        assert!(!auth.is_user_authenticated(7, GUILD).await.unwrap(), "{backend}");
+        assert!(auth.is_user_authenticated(7, GUILD + 1).await.unwrap(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn role_colours_toggle() {
+    for (backend, storage) in backends().await {
+        let colours = &storage.role_colours;
+        assert!(!colours.is_feature_enabled(GUILD as u64).await.unwrap(), "{backend}");
+        colours.set_feature_enabled(GUILD as u64, true).await.unwrap();
+        assert!(colours.is_feature_enabled(GUILD as u64).await.unwrap(), "{backend}");
+        colours.set_feature_enabled(GUILD as u64, false).await.unwrap();
+        assert!(!colours.is_feature_enabled(GUILD as u64).await.unwrap(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn plaintext_keys_are_migrated_to_hashes() {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"CREATE TABLE auth_configs (
+            guild_id INTEGER PRIMARY KEY,
+            key_id TEXT NOT NULL,
+            role_id INTEGER NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )"#
+    )
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("CREATE INDEX idx_auth_configs_key_id ON auth_configs(key_id)").execute(&pool).await.unwrap();
+    sqlx::query("INSERT INTO auth_configs (guild_id, key_id, role_id) VALUES (?, 'old-key', ?)")
+        .bind(GUILD)
+        .bind(ROLE)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let storage = Storage::open(pool.clone()).await.unwrap();
+
+    let config = storage.auth.get_auth_config(GUILD).await.unwrap().unwrap();
+    assert_eq!(config.role_id, ROLE);
+    assert!(config.enabled);
+
+    let key = storage.auth.find_auth_key(GUILD, &hash_key("old-key")).await.unwrap().unwrap();
+    assert_eq!(key.label.as_deref(), Some("migrated"));
+    assert_eq!(key.status(Utc::now()), KeyStatus::Usable);
+
+    let plaintext: Vec<(String,)> = sqlx::query_as("SELECT key_hash FROM auth_keys WHERE key_hash = 'old-key'")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert!(plaintext.is_empty());
+
+    // opening again finds nothing left to migrate
+    Storage::open(pool).await.unwrap();
+}
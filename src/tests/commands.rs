@@ -0,0 +1,194 @@
+use hyper::Method;
+use poise::serenity_prelude as serenity;
+
+use crate::commands::all_commands;
+use crate::commands::moderation::BOT_OWNER_ID;
+use crate::structs::auth::AuthKey;
+use crate::tests::fixtures::{self, GENERAL, GUILD, STARBOARD};
+use crate::tests::harness::Harness;
+use crate::types::{Data, Error};
+
+const AUTHOR: u64 = 50;
+const FAN: u64 = 51;
+const OTHER_FAN: u64 = 52;
+const LATE_FAN: u64 = 53;
+
+async fn with_board() -> Harness {
+    let harness = Harness::new().await;
+    let owner = fixtures::user(BOT_OWNER_ID.get(), "owner");
+    harness.send(owner, &format!("nstarboard setup <#{}> 2", STARBOARD)).await;
+    harness
+}
+
+#[tokio::test]
+async fn setup_replies_and_saves_the_board() {
+    let harness = with_board().await;
+
+    let replies = harness.discord.posted_in(GENERAL);
+    assert_eq!(replies.len(), 1);
+    assert!(replies[0]["content"].as_str().unwrap().contains("Board **starboard** set up"));
+
+    let config = harness.data.starboard.get_starboard_config(GUILD, "starboard").await.unwrap().unwrap();
+    assert_eq!(config.starboard_channel_id, Some(STARBOARD.to_string()));
+    assert_eq!(config.threshold, 2);
+}
+
+fn check_slash_limits(command: &poise::Command<Data, Error>, depth: usize) {
+    let name = &command.qualified_name;
+    assert!(command.subcommands.len() <= 25, "{name} has {} subcommands", command.subcommands.len());
+    assert!(command.parameters.len() <= 25, "{name} has {} options", command.parameters.len());
+    assert!(command.subcommands.is_empty() || depth < 2, "{name} nests groups too deep");
+
+    for subcommand in &command.subcommands {
+        check_slash_limits(subcommand, depth + 1);
+    }
+}
+
+#[test]
+fn commands_fit_discords_limits() {
+    let commands = all_commands();
+    assert!(commands.len() <= 100);
+    for command in commands.iter().filter(|command| command.slash_action.is_some() || !command.subcommands.is_empty()) {
+        check_slash_limits(command, 0);
+    }
+}
+
+#[tokio::test]
+async fn grouped_settings_run_by_their_full_name() {
+    let harness = with_board().await;
+    let owner = fixtures::user(BOT_OWNER_ID.get(), "owner");
+
+    harness.send(owner, "nstarboard config threshold 5").await;
+
+    let config = harness.data.starboard.get_starboard_config(GUILD, "starboard").await.unwrap().unwrap();
+    assert_eq!(config.threshold, 5);
+}
+
+#[tokio::test]
+async fn setup_needs_a_moderator() {
+    let harness = Harness::new().await;
+    let stranger = fixtures::user(AUTHOR, "stranger");
+    harness.discord.member(fixtures::member(GUILD, stranger.clone(), &[]));
+
+    let command = harness.send(stranger, &format!("nstarboard setup <#{}>", STARBOARD)).await;
+
+    assert!(harness.discord.reacted(GENERAL, command["id"].as_str().unwrap().parse().unwrap(), "❌"));
+    assert!(harness.data.starboard.get_starboard_configs(GUILD).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn second_star_posts_to_the_starboard() {
+    let harness = with_board().await;
+    let message = harness.send(fixtures::user(AUTHOR, "author"), "something worth starring").await;
+
+    harness.react(&message, FAN, "⭐").await;
+    assert!(harness.discord.posted_in(STARBOARD).is_empty());
+
+    harness.react(&message, OTHER_FAN, "⭐").await;
+    let posts = harness.discord.posted_in(STARBOARD);
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0]["embeds"][0]["description"], "something worth starring");
+
+    let message_id = message["id"].as_str().unwrap().parse().unwrap();
+    let board_id = harness.data.starboard.get_starboard_config(GUILD, "starboard").await.unwrap().unwrap().id;
+    let starred = harness.data.starboard.get_starred_message(board_id, message_id).await.unwrap().unwrap();
+    assert_eq!(starred.stars, 2);
+}
+
+#[tokio::test]
+async fn self_stars_are_taken_off() {
+    let harness = with_board().await;
+    let message = harness.send(fixtures::user(AUTHOR, "author"), "starring my own post").await;
+
+    harness.react(&message, AUTHOR, "⭐").await;
+
+    let path = format!("/channels/{}/messages/{}/reactions/⭐/{}", GENERAL, message["id"].as_str().unwrap(), AUTHOR);
+    assert_eq!(harness.discord.sent(Method::DELETE, &path).len(), 1);
+    assert!(harness.discord.posted_in(STARBOARD).is_empty());
+}
+
+fn nsfw(mut channel: serde_json::Value) -> serde_json::Value {
+    channel["nsfw"] = true.into();
+    channel
+}
+
+#[tokio::test]
+async fn uncached_nsfw_messages_stay_off_sfw_boards() {
+    let harness = with_board().await;
+    harness.discord.channel(nsfw(fixtures::text_channel(GENERAL, GUILD, "general")));
+    let message = harness.send(fixtures::user(AUTHOR, "author"), "not for the starboard").await;
+
+    harness.react(&message, FAN, "⭐").await;
+    harness.react(&message, OTHER_FAN, "⭐").await;
+    assert!(harness.discord.posted_in(STARBOARD).is_empty());
+
+    // an nsfw board takes them, looked up over http as well
+    harness.discord.channel(nsfw(fixtures::text_channel(STARBOARD, GUILD, "starboard")));
+    let message = harness.send(fixtures::user(AUTHOR, "author"), "for the nsfw board").await;
+    harness.react(&message, FAN, "⭐").await;
+    harness.react(&message, OTHER_FAN, "⭐").await;
+    assert_eq!(harness.discord.posted_in(STARBOARD).len(), 1);
+}
+
+#[tokio::test]
+async fn deleted_starboard_posts_come_back_on_the_next_star() {
+    let harness = with_board().await;
+    let message = harness.send(fixtures::user(AUTHOR, "author"), "posted twice").await;
+    harness.react(&message, FAN, "⭐").await;
+    harness.react(&message, OTHER_FAN, "⭐").await;
+
+    let message_id = message["id"].as_str().unwrap().parse().unwrap();
+    let board_id = harness.data.starboard.get_starboard_config(GUILD, "starboard").await.unwrap().unwrap().id;
+    let starred = harness.data.starboard.get_starred_message(board_id, message_id).await.unwrap().unwrap();
+    let copy_id = starred.starboard_message_id.unwrap().parse().unwrap();
+
+    harness.dispatch(serenity::FullEvent::MessageDelete {
+        channel_id: serenity::ChannelId::new(STARBOARD),
+        deleted_message_id: serenity::MessageId::new(copy_id),
+        guild_id: Some(serenity::GuildId::new(GUILD)),
+    }).await;
+    assert!(harness.data.starboard.get_starred_message(board_id, message_id).await.unwrap().is_none());
+
+    harness.react(&message, LATE_FAN, "⭐").await;
+    let posts = harness.discord.posted_in(STARBOARD);
+    assert_eq!(posts.len(), 2);
+    assert_eq!(posts[1]["embeds"][0]["description"], "posted twice");
+}
+
+#[tokio::test]
+async fn single_use_key_gives_the_role_once() {
+    let harness = Harness::new().await;
+    harness.data.auth.set_auth_config(GUILD as i64, 60, true).await.unwrap();
+    let mut key = AuthKey::new(GUILD as i64, "let-me-in", BOT_OWNER_ID.get() as i64);
+    key.max_uses = Some(1);
+    harness.data.auth.add_auth_key(&key).await.unwrap();
+
+    for id in [AUTHOR, FAN] {
+        harness.discord.member(fixtures::member(GUILD, fixtures::user(id, "member"), &[]));
+        harness.send(fixtures::user(id, "member"), "nauthenticate let-me-in").await;
+    }
+
+    let role_path = |user: u64| format!("/guilds/{}/members/{}/roles/60", GUILD, user);
+    assert_eq!(harness.discord.sent(Method::PUT, &role_path(AUTHOR)).len(), 1);
+    assert!(harness.discord.sent(Method::PUT, &role_path(FAN)).is_empty());
+    assert!(harness.data.auth.is_user_authenticated(AUTHOR as i64, GUILD as i64).await.unwrap());
+    assert!(!harness.data.auth.is_user_authenticated(FAN as i64, GUILD as i64).await.unwrap());
+}
+
+#[tokio::test]
+async fn keys_arent_spent_on_members_that_cant_be_found() {
+    let harness = Harness::new().await;
+    harness.data.auth.set_auth_config(GUILD as i64, 60, true).await.unwrap();
+    let mut key = AuthKey::new(GUILD as i64, "let-me-in", BOT_OWNER_ID.get() as i64);
+    key.max_uses = Some(1);
+    harness.data.auth.add_auth_key(&key).await.unwrap();
+
+    // never registered as a member, the lookup 404s
+    harness.send(fixtures::user(AUTHOR, "stranger"), "nauthenticate let-me-in").await;
+
+    let replies = harness.discord.posted_in(GENERAL);
+    assert_eq!(replies[0]["embeds"][0]["title"], "Failed to Add Role");
+    let key = harness.data.auth.find_auth_key(GUILD as i64, &key.key_hash).await.unwrap().unwrap();
+    assert_eq!(key.uses, 0);
+    assert!(!harness.data.auth.is_user_authenticated(AUTHOR as i64, GUILD as i64).await.unwrap());
+}
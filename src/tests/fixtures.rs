@@ -0,0 +1,123 @@
+use serde_json::{json, Value};
+
+pub(crate) const GUILD: u64 = 1;
+pub(crate) const BOT: u64 = 2;
+pub(crate) const OWNER: u64 = 3;
+pub(crate) const GENERAL: u64 = 10;
+pub(crate) const STARBOARD: u64 = 20;
+
+// what @everyone gets in the fixture guild, view channel, send messages and embed links
+const EVERYONE_PERMISSIONS: &str = "19456";
+
+pub(crate) fn user(id: u64, name: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": name,
+        "global_name": null,
+        "avatar": null,
+        "bot": false,
+    })
+}
+
+pub(crate) fn bot_user() -> Value {
+    json!({
+        "id": BOT.to_string(),
+        "username": "bot",
+        "global_name": null,
+        "avatar": null,
+        "bot": true,
+    })
+}
+
+pub(crate) fn guild(id: u64, owner_id: u64) -> Value {
+    json!({
+        "id": id.to_string(),
+        "name": "test guild",
+        "icon": null,
+        "owner_id": owner_id.to_string(),
+        "verification_level": 0,
+        "default_message_notifications": 0,
+        "explicit_content_filter": 0,
+        "roles": [{
+            "id": id.to_string(),
+            "name": "@everyone",
+            "color": 0,
+            "colors": { "primary_color": 0 },
+            "hoist": false,
+            "managed": false,
+            "mentionable": false,
+            "permissions": EVERYONE_PERMISSIONS,
+            "position": 0,
+        }],
+        "emojis": [],
+        "features": [],
+        "mfa_level": 0,
+        "system_channel_flags": 0,
+        "premium_tier": 0,
+        "preferred_locale": "en-US",
+        "nsfw_level": 0,
+        "stickers": [],
+        "premium_progress_bar_enabled": false,
+    })
+}
+
+pub(crate) fn text_channel(id: u64, guild_id: u64, name: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "guild_id": guild_id.to_string(),
+        "type": 0,
+        "name": name,
+        "position": 0,
+        "permission_overwrites": [],
+        "nsfw": false,
+    })
+}
+
+pub(crate) fn member(guild_id: u64, user: Value, roles: &[u64]) -> Value {
+    json!({
+        "guild_id": guild_id.to_string(),
+        "user": user,
+        "roles": roles.iter().map(u64::to_string).collect::<Vec<_>>(),
+        "joined_at": "2024-01-01T00:00:00Z",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+    })
+}
+
+/// A plain message, `guild_id` is left out like it is on messages fetched over http
+pub(crate) fn message(id: u64, channel_id: u64, guild_id: Option<u64>, author: Value, content: &str) -> Value {
+    let mut message = json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "author": author,
+        "content": content,
+        "timestamp": "2024-01-01T00:00:00Z",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": false,
+        "type": 0,
+    });
+    if let Some(guild_id) = guild_id {
+        message["guild_id"] = json!(guild_id.to_string());
+    }
+    message
+}
+
+pub(crate) fn reaction(message: &Value, user_id: u64, guild_id: u64, emoji: &str) -> Value {
+    json!({
+        "user_id": user_id.to_string(),
+        "channel_id": message["channel_id"],
+        "message_id": message["id"],
+        "guild_id": guild_id.to_string(),
+        "message_author_id": message["author"]["id"],
+        "emoji": { "id": null, "name": emoji },
+        "burst": false,
+        "type": 0,
+    })
+}
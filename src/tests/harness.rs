@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::helpers::reminder_scheduler::ReminderScheduler;
+use crate::helpers::starboard_state::StarboardState;
+use crate::helpers::storage::Storage;
+use crate::tests::fixtures::{self, BOT, GENERAL, GUILD, OWNER, STARBOARD};
+use crate::tests::mock_discord::MockDiscord;
+use crate::types::{Data, Error};
+
+const TOKEN: &str = "test-token";
+
+// messages the tests send start here, below the ids the mock hands out
+const FIRST_MESSAGE_ID: u64 = 1_000;
+
+/// The bot wired up to a mock discord with in-memory storage
+///
+/// Events go through poise's own dispatch with the real framework options, so prefix
+/// commands, checks, hooks and `event_handler` all run as they would live.
+pub(crate) struct Harness {
+    pub discord: MockDiscord,
+    pub ctx: serenity::Context,
+    pub data: Data,
+    options: poise::FrameworkOptions<Data, Error>,
+    shard_manager: Arc<serenity::ShardManager>,
+    last_message_id: AtomicU64,
+}
+
+impl Harness {
+    /// A guild with #general and #starboard, the bot can post in both
+    pub async fn new() -> Self {
+        let discord = MockDiscord::start().await;
+        discord.guild(fixtures::guild(GUILD, OWNER));
+        discord.channel(fixtures::text_channel(GENERAL, GUILD, "general"));
+        discord.channel(fixtures::text_channel(STARBOARD, GUILD, "starboard"));
+        discord.member(fixtures::member(GUILD, fixtures::bot_user(), &[]));
+
+        let http = Arc::new(serenity::HttpBuilder::new(TOKEN)
+            .proxy(discord.url.as_str())
+            .ratelimiter_disabled(true)
+            .build());
+        let cache = Arc::new(serenity::Cache::new());
+        let type_map = Arc::new(RwLock::new(serenity::prelude::TypeMap::new()));
+        let ws_url = Arc::new(Mutex::new(discord.gateway_url.clone()));
+
+        let (shard_manager, _) = serenity::ShardManager::new(serenity::ShardManagerOptions {
+            data: Arc::clone(&type_map),
+            event_handlers: Vec::new(),
+            raw_event_handlers: Vec::new(),
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 0,
+            shard_total: 1,
+            ws_url: Arc::clone(&ws_url),
+            cache: Arc::clone(&cache),
+            http: Arc::clone(&http),
+            intents: serenity::GatewayIntents::empty(),
+            presence: None,
+        });
+
+        // a context needs a shard messenger, which needs a runner around a connected shard
+        let shard_info = serenity::ShardInfo { id: serenity::ShardId(0), total: 1 };
+        let shard = serenity::Shard::new(ws_url, TOKEN, shard_info, serenity::GatewayIntents::empty(), None)
+            .await
+            .unwrap();
+        let runner = serenity::ShardRunner::new(serenity::ShardRunnerOptions {
+            data: Arc::clone(&type_map),
+            event_handlers: Vec::new(),
+            raw_event_handlers: Vec::new(),
+            framework: None,
+            manager: Arc::clone(&shard_manager),
+            shard,
+            cache: Arc::clone(&cache),
+            http: Arc::clone(&http),
+        });
+
+        let ctx = serenity::Context {
+            data: type_map,
+            shard: serenity::ShardMessenger::new(&runner),
+            shard_id: serenity::ShardId(0),
+            http: Arc::clone(&http),
+            cache,
+        };
+
+        let Storage { pool, reminders, starboard, auth, role_colours } = Storage::in_memory().unwrap();
+        let data = Data {
+            db: pool,
+            last_command_success: Arc::new(poise::futures_util::lock::Mutex::new(true)),
+            reminders,
+            reminder_scheduler: Arc::new(ReminderScheduler::new()),
+            starboard,
+            starboard_state: Arc::new(StarboardState::new()),
+            http_client: http,
+            auth,
+            role_colours,
+        };
+
+        Self {
+            discord,
+            ctx,
+            data,
+            options: crate::framework_options(),
+            shard_manager,
+            last_message_id: AtomicU64::new(FIRST_MESSAGE_ID),
+        }
+    }
+
+    pub async fn dispatch(&self, event: serenity::FullEvent) {
+        let framework = poise::FrameworkContext {
+            bot_id: serenity::UserId::new(BOT),
+            options: &self.options,
+            user_data: &self.data,
+            shard_manager: &self.shard_manager,
+        };
+        poise::dispatch_event(framework, &self.ctx, event).await;
+    }
+
+    /// Sends a message in #general, it can be fetched from the mock afterwards
+    pub async fn send(&self, author: Value, content: &str) -> Value {
+        let id = self.last_message_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let message = fixtures::message(id, GENERAL, Some(GUILD), author, content);
+
+        // messages fetched over http don't carry the guild
+        let mut fetched = message.clone();
+        fetched.as_object_mut().unwrap().remove("guild_id");
+        self.discord.message(fetched);
+
+        let new_message = serde_json::from_value(message.clone()).unwrap();
+        self.dispatch(serenity::FullEvent::Message { new_message }).await;
+        message
+    }
+
+    pub async fn react(&self, message: &Value, user_id: u64, emoji: &str) {
+        let add_reaction = serde_json::from_value(fixtures::reaction(message, user_id, GUILD, emoji)).unwrap();
+        self.dispatch(serenity::FullEvent::ReactionAdd { add_reaction }).await;
+    }
+}
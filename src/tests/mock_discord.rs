@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::tests::fixtures;
+
+// serenity puts the api version in front of every route
+const API_PREFIX: &str = "/api/v10";
+
+// ids handed out to messages the bot sends, far above anything the fixtures use
+const FIRST_SENT_ID: u64 = 900_000;
+
+/// A request the bot made, `path` has the api prefix stripped and is percent-decoded
+#[derive(Debug, Clone)]
+pub(crate) struct Recorded {
+    pub method: Method,
+    pub path: String,
+    pub body: Value,
+}
+
+#[derive(Default)]
+struct MockState {
+    routes: HashMap<(Method, String), Value>,
+    requests: Vec<Recorded>,
+    last_id: u64,
+}
+
+/// Stands in for discord's REST api and gateway on localhost
+///
+/// GETs are answered from canned responses, messages the bot sends are stored so they can be
+/// fetched and edited afterwards, and everything else succeeds with an empty body. Every
+/// request is recorded for the test to look at.
+pub(crate) struct MockDiscord {
+    state: Arc<Mutex<MockState>>,
+    pub url: String,
+    pub gateway_url: String,
+}
+
+impl MockDiscord {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState { last_id: FIRST_SENT_ID, ..Default::default() }));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = Arc::clone(&server_state);
+                tokio::spawn(async move {
+                    let service = service_fn(move |request| handle(Arc::clone(&state), request));
+                    let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+                });
+            }
+        });
+
+        // shards only need the socket to open, nothing is ever sent over it
+        let gateway = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway_url = format!("ws://{}", gateway.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((stream, _)) = gateway.accept().await {
+                if let Ok(socket) = tokio_tungstenite::accept_async(stream).await {
+                    sockets.push(socket);
+                }
+            }
+        });
+
+        Self { state, url, gateway_url }
+    }
+
+    /// Answers `method path` with `body` from now on
+    pub fn serve(&self, method: Method, path: &str, body: Value) {
+        self.state.lock().unwrap().routes.insert((method, path.to_string()), body);
+    }
+
+    pub fn guild(&self, guild: Value) {
+        self.serve(Method::GET, &format!("/guilds/{}", guild["id"].as_str().unwrap()), guild);
+    }
+
+    pub fn channel(&self, channel: Value) {
+        self.serve(Method::GET, &format!("/channels/{}", channel["id"].as_str().unwrap()), channel);
+    }
+
+    pub fn member(&self, member: Value) {
+        let path = format!("/guilds/{}/members/{}",
+                           member["guild_id"].as_str().unwrap(), member["user"]["id"].as_str().unwrap());
+        self.serve(Method::GET, &path, member);
+    }
+
+    pub fn message(&self, message: Value) {
+        let path = format!("/channels/{}/messages/{}",
+                           message["channel_id"].as_str().unwrap(), message["id"].as_str().unwrap());
+        self.serve(Method::GET, &path, message);
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Bodies of every `method path` request, oldest first
+    pub fn sent(&self, method: Method, path: &str) -> Vec<Value> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.method == method && request.path == path)
+            .map(|request| request.body)
+            .collect()
+    }
+
+    /// Messages the bot posted in a channel
+    pub fn posted_in(&self, channel_id: u64) -> Vec<Value> {
+        self.sent(Method::POST, &format!("/channels/{}/messages", channel_id))
+    }
+
+    /// Whether the bot reacted to a message with `emoji`
+    pub fn reacted(&self, channel_id: u64, message_id: u64, emoji: &str) -> bool {
+        let path = format!("/channels/{}/messages/{}/reactions/{}/@me", channel_id, message_id, emoji);
+        !self.sent(Method::PUT, &path).is_empty()
+    }
+}
+
+async fn handle(state: Arc<Mutex<MockState>>, request: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let method = request.method().clone();
+    let path = percent_decode(request.uri().path().trim_start_matches(API_PREFIX));
+    let bytes = request.into_body().collect().await.map(|body| body.to_bytes()).unwrap_or_default();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+    let mut state = state.lock().unwrap();
+    state.requests.push(Recorded { method: method.clone(), path: path.clone(), body: body.clone() });
+
+    if let Some(canned) = state.routes.get(&(method.clone(), path.clone())) {
+        return Ok(json_response(StatusCode::OK, canned.clone()));
+    }
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let response = match (&method, segments.as_slice()) {
+        (&Method::POST, ["channels", channel_id, "messages"]) => {
+            state.last_id += 1;
+            let message = sent_message(state.last_id, channel_id, &body);
+            let route = format!("/channels/{}/messages/{}", channel_id, state.last_id);
+            state.routes.insert((Method::GET, route), message.clone());
+            json_response(StatusCode::OK, message)
+        }
+        (&Method::PATCH, ["channels", _, "messages", _]) => {
+            let route = (Method::GET, path.clone());
+            match state.routes.get_mut(&route) {
+                Some(message) => {
+                    merge(message, &body);
+                    json_response(StatusCode::OK, message.clone())
+                }
+                None => not_found(),
+            }
+        }
+        (&Method::GET, _) => not_found(),
+        _ => Response::builder().status(StatusCode::NO_CONTENT).body(Full::default()).unwrap(),
+    };
+    Ok(response)
+}
+
+/// What discord sends back for a new message, the bot is the author
+fn sent_message(id: u64, channel_id: &str, body: &Value) -> Value {
+    let mut message = fixtures::message(id, channel_id.parse().unwrap(), None, fixtures::bot_user(), "");
+    merge(&mut message, body);
+    message
+}
+
+fn merge(message: &mut Value, body: &Value) {
+    let Some(fields) = body.as_object() else {
+        return;
+    };
+    for key in ["content", "embeds", "components"] {
+        if let Some(value) = fields.get(key).filter(|value| !value.is_null()) {
+            message[key] = value.clone();
+        }
+    }
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
+
+fn not_found() -> Response<Full<Bytes>> {
+    json_response(StatusCode::NOT_FOUND, json!({ "message": "Unknown", "code": 10000 }))
+}
+
+// emoji in reaction routes come through percent-encoded
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,39 @@
+mod auth;
+mod commands;
+mod fixtures;
+mod harness;
+mod mock_discord;
+mod recurrence;
+mod reminders;
+mod starboard;
+
+use chrono::{Duration, Utc};
+use poise::serenity_prelude as serenity;
+use sqlx::sqlite::SqlitePoolOptions;
+
+use crate::helpers::storage::Storage;
+
+// discord's epoch, snowflakes count milliseconds from here
+const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
+
+/// Both backends, every store contract test runs against each so they can't drift apart
+async fn backends() -> Vec<(&'static str, Storage)> {
+    vec![("memory", Storage::in_memory().unwrap()), ("sqlite", sqlite().await)]
+}
+
+/// The real SQLite stores on a private in-memory database
+async fn sqlite() -> Storage {
+    // one connection, every new connection to :memory: would be a separate empty database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    Storage::open(pool).await.unwrap()
+}
+
+/// A message id created `days` ago, for the board age limits
+fn message_id_aged(days: i64) -> serenity::MessageId {
+    let created_ms = (Utc::now() - Duration::days(days)).timestamp_millis();
+    serenity::MessageId::new(((created_ms - DISCORD_EPOCH_MS) as u64) << 22)
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc, Weekday};
+
+use crate::structs::recurrence::{Recurrence, RecurrenceError, RecurrenceRule};
+
+fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+}
+
+fn time(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+fn next(spec: &str, after: DateTime<Utc>) -> DateTime<Utc> {
+    RecurrenceRule::parse(spec).unwrap().next_after(after).unwrap()
+}
+
+#[test]
+fn schedules_are_told_apart_from_durations() {
+    for spec in ["every 2h", "daily at 9", "Weekdays at 17:00", "weekends at 10am", "cron 0 9 * * *"] {
+        assert!(Recurrence::is_recurring_spec(spec), "{spec}");
+    }
+    for spec in ["10m", "2h", "everyday", "tomorrow"] {
+        assert!(!Recurrence::is_recurring_spec(spec), "{spec}");
+    }
+}
+
+#[test]
+fn accepted_formats() {
+    let cases = [
+        ("every 2h", RecurrenceRule::Interval(Duration::hours(2))),
+        ("every 90m", RecurrenceRule::Interval(Duration::minutes(90))),
+        ("every minute", RecurrenceRule::Interval(Duration::minutes(1))),
+        ("every hour", RecurrenceRule::Interval(Duration::hours(1))),
+        ("every day", RecurrenceRule::Interval(Duration::days(1))),
+        ("daily", RecurrenceRule::Interval(Duration::days(1))),
+        ("every week", RecurrenceRule::Interval(Duration::weeks(1))),
+        ("every day at 9am", RecurrenceRule::Daily { at: time(9, 0) }),
+        ("daily at 17:30", RecurrenceRule::Daily { at: time(17, 30) }),
+        ("every day at 12am", RecurrenceRule::Daily { at: time(0, 0) }),
+        ("every day at 12pm", RecurrenceRule::Daily { at: time(12, 0) }),
+        ("every day at 9:15pm", RecurrenceRule::Daily { at: time(21, 15) }),
+        ("weekends at 10", RecurrenceRule::Weekly { days: vec![Weekday::Sat, Weekday::Sun], at: time(10, 0) }),
+        ("every thu,mon,mondays at 10:00", RecurrenceRule::Weekly { days: vec![Weekday::Mon, Weekday::Thu], at: time(10, 0) }),
+    ];
+    for (spec, expected) in cases {
+        assert_eq!(RecurrenceRule::parse(spec).unwrap(), expected, "{spec}");
+    }
+
+    let weekdays = RecurrenceRule::parse("weekdays at 9").unwrap();
+    assert_eq!(weekdays.to_string(), "every mon,tue,wed,thu,fri at 09:00");
+    assert_eq!(RecurrenceRule::parse("CRON 0 9 * * 1-5").unwrap().to_string(), "cron 0 9 * * 1-5");
+}
+
+#[test]
+fn rejected_formats() {
+    assert!(matches!(RecurrenceRule::parse("every fortnight"), Err(RecurrenceError::InvalidFormat)));
+    assert!(matches!(RecurrenceRule::parse("every someday at 9"), Err(RecurrenceError::InvalidFormat)));
+    assert!(matches!(RecurrenceRule::parse("every 30s"), Err(RecurrenceError::IntervalTooShort)));
+
+    for spec in ["weekdays", "weekends", "every weekday"] {
+        assert!(matches!(RecurrenceRule::parse(spec), Err(RecurrenceError::MissingTime)), "{spec}");
+    }
+
+    for spec in ["every day at 25:00", "every day at 9:60", "every day at 13pm", "every day at 0am", "every day at noon"] {
+        assert!(matches!(RecurrenceRule::parse(spec), Err(RecurrenceError::InvalidTime(_))), "{spec}");
+    }
+
+    for spec in ["cron 0 9 * *", "cron 60 * * * *", "cron * 24 * * *", "cron * * 0 * *", "cron * * * 13 *",
+                 "cron * * * * 8", "cron */0 * * * *", "cron 5-1 * * * *", "cron a * * * *"] {
+        assert!(matches!(RecurrenceRule::parse(spec), Err(RecurrenceError::InvalidCron(_))), "{spec}");
+    }
+
+    assert!(matches!(Recurrence::parse("every day at 9 x0"), Err(RecurrenceError::InvalidCount)));
+    assert!(matches!(Recurrence::parse("every day at 9 until tomorrow"), Err(RecurrenceError::InvalidUntil(_))));
+}
+
+#[test]
+fn daily_and_weekly_roll_over_month_ends() {
+    // past today's time moves to the next day, across the month and year
+    assert_eq!(next("every day at 9", at(2025, 1, 31, 10, 0)), at(2025, 2, 1, 9, 0));
+    assert_eq!(next("every day at 9", at(2025, 12, 31, 9, 0)), at(2026, 1, 1, 9, 0));
+    assert_eq!(next("every day at 9", at(2025, 3, 4, 8, 59)), at(2025, 3, 4, 9, 0));
+
+    // 2024-02-29 is a thursday, 2025-02-28 is a friday
+    assert_eq!(next("every day at 9", at(2024, 2, 28, 12, 0)), at(2024, 2, 29, 9, 0));
+    assert_eq!(next("every mon at 8", at(2025, 2, 28, 12, 0)), at(2025, 3, 3, 8, 0));
+    assert_eq!(next("weekdays at 8", at(2024, 2, 29, 12, 0)), at(2024, 3, 1, 8, 0));
+    // the same weekday a week on
+    assert_eq!(next("every fri at 8", at(2025, 2, 28, 8, 0)), at(2025, 3, 7, 8, 0));
+}
+
+#[test]
+fn cron_skips_months_without_the_day() {
+    assert_eq!(next("cron 0 9 31 * *", at(2025, 4, 1, 0, 0)), at(2025, 5, 31, 9, 0));
+    assert_eq!(next("cron 30 8 29 2 *", at(2025, 3, 1, 0, 0)), at(2028, 2, 29, 8, 30));
+    assert_eq!(next("cron 0 0 1 * *", at(2024, 2, 29, 23, 59)), at(2024, 3, 1, 0, 0));
+    // never satisfiable
+    assert!(RecurrenceRule::parse("cron 0 0 30 2 *").unwrap().next_after(at(2025, 1, 1, 0, 0)).is_none());
+}
+
+#[test]
+fn cron_fields() {
+    // later the same day, steps, ranges and lists
+    assert_eq!(next("cron */15 * * * *", at(2025, 6, 2, 10, 7)), at(2025, 6, 2, 10, 15));
+    assert_eq!(next("cron 5/20 * * * *", at(2025, 6, 2, 10, 46)), at(2025, 6, 2, 11, 5));
+    assert_eq!(next("cron 0 9-17/4 * * *", at(2025, 6, 2, 13, 0)), at(2025, 6, 2, 17, 0));
+    assert_eq!(next("cron 0 9,18 * * *", at(2025, 6, 2, 9, 0)), at(2025, 6, 2, 18, 0));
+    // 7 means sunday, 2025-06-08 is one
+    assert_eq!(next("cron 0 9 * * 7", at(2025, 6, 2, 0, 0)), at(2025, 6, 8, 9, 0));
+}
+
+#[test]
+fn cron_day_fields_match_either() {
+    // the 15th or any monday, whichever comes first. 2025-06-09 is a monday
+    assert_eq!(next("cron 0 9 15 * 1", at(2025, 6, 3, 0, 0)), at(2025, 6, 9, 9, 0));
+    assert_eq!(next("cron 0 9 15 * 1", at(2025, 6, 10, 0, 0)), at(2025, 6, 15, 9, 0));
+    // only one restricted, the other doesn't widen it
+    assert_eq!(next("cron 0 9 15 * *", at(2025, 6, 3, 0, 0)), at(2025, 6, 15, 9, 0));
+    assert_eq!(next("cron 0 9 * * 1", at(2025, 6, 10, 0, 0)), at(2025, 6, 16, 9, 0));
+    // a stepped day of month narrows the mondays instead of adding every other day
+    assert_eq!(next("cron 0 9 */2 * 1", at(2025, 6, 3, 0, 0)), at(2025, 6, 9, 9, 0));
+    assert_eq!(next("cron 0 9 */2 * 1", at(2025, 6, 10, 0, 0)), at(2025, 6, 23, 9, 0));
+}
+
+#[test]
+fn intervals_skip_missed_occurrences() {
+    let rule = RecurrenceRule::parse("every 2h").unwrap();
+    let previous = at(2025, 1, 31, 23, 0);
+    assert_eq!(rule.next_from(previous, previous), Some(at(2025, 2, 1, 1, 0)));
+    // down for five hours, lands back on the same grid
+    assert_eq!(rule.next_from(previous, at(2025, 2, 1, 4, 0)), Some(at(2025, 2, 1, 5, 0)));
+
+    let daily = RecurrenceRule::parse("every day at 9").unwrap();
+    assert_eq!(daily.next_from(at(2025, 2, 1, 9, 0), at(2025, 2, 3, 12, 0)), Some(at(2025, 2, 4, 9, 0)));
+}
+
+#[test]
+fn until_and_count_limits() {
+    let recurrence = Recurrence::parse("every day at 9 until 2025-03-01").unwrap();
+    assert_eq!(recurrence.until, Some(Utc.with_ymd_and_hms(2025, 3, 1, 23, 59, 59).unwrap()));
+    assert_eq!(recurrence.max_occurrences, None);
+    assert_eq!(recurrence.first_occurrence(at(2025, 3, 1, 8, 0)).unwrap(), at(2025, 3, 1, 9, 0));
+    assert!(matches!(recurrence.first_occurrence(at(2025, 3, 1, 10, 0)), Err(RecurrenceError::NeverFires)));
+
+    let recurrence = Recurrence::parse("every 1h until 2025-03-01 12:00").unwrap();
+    assert_eq!(recurrence.first_occurrence(at(2025, 3, 1, 11, 0)).unwrap(), at(2025, 3, 1, 12, 0));
+    assert!(recurrence.first_occurrence(at(2025, 3, 1, 11, 1)).is_err());
+
+    for (spec, count) in [("every day at 9 x3", 3), ("every day at 9 5 times", 5), ("every day at 9 for 1 time", 1)] {
+        let recurrence = Recurrence::parse(spec).unwrap();
+        assert_eq!(recurrence.max_occurrences, Some(count), "{spec}");
+        assert_eq!(recurrence.rule, RecurrenceRule::Daily { at: time(9, 0) }, "{spec}");
+    }
+
+    // both suffixes together, count goes last
+    let recurrence = Recurrence::parse("weekdays at 17:00 until 2026-12-31 x10").unwrap();
+    assert_eq!(recurrence.max_occurrences, Some(10));
+    assert_eq!(recurrence.until, Some(Utc.with_ymd_and_hms(2026, 12, 31, 23, 59, 59).unwrap()));
+    assert_eq!(recurrence.to_string(), "every mon,tue,wed,thu,fri at 17:00 until 2026-12-31 23:59 x10");
+}
@@ -0,0 +1,125 @@
+use chrono::{Duration, Utc};
+use poise::serenity_prelude::{GuildId, Message, UserId};
+
+use crate::helpers::reminder_scheduler::ReminderScheduler;
+use crate::structs::reminders::{Reminder, ReminderId};
+use crate::tests::{backends, fixtures};
+
+const OWNER: UserId = UserId::new(1);
+const SUBSCRIBER: UserId = UserId::new(2);
+
+fn reminder_in(minutes: i64) -> Reminder {
+    Reminder::new(OWNER, Utc::now() + Duration::minutes(minutes), format!("in {minutes}"), None, None)
+}
+
+#[tokio::test]
+async fn pending_skips_sent_and_failed() {
+    for (backend, storage) in backends().await {
+        let reminders = &storage.reminders;
+        let later = reminders.add_reminder(&reminder_in(30)).await.unwrap();
+        let sooner = reminders.add_reminder(&reminder_in(5)).await.unwrap();
+        let sent = reminders.add_reminder(&reminder_in(1)).await.unwrap();
+        let failed = reminders.add_reminder(&reminder_in(2)).await.unwrap();
+
+        reminders.mark_due(sent).await.unwrap();
+        reminders.mark_failed(failed, "channel gone").await.unwrap();
+
+        let pending: Vec<ReminderId> = reminders.get_pending().await.unwrap().iter().map(|reminder| reminder.id).collect();
+        assert_eq!(pending, vec![sooner, later], "{backend}");
+
+        // dead-lettered ones still show up for the owner
+        assert_eq!(reminders.count_active_reminders(OWNER).await.unwrap(), 2, "{backend}");
+        assert_eq!(reminders.get_user_reminders(OWNER).await.unwrap().len(), 3, "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn subscribers_see_and_cannot_cancel() {
+    for (backend, storage) in backends().await {
+        let reminders = &storage.reminders;
+        let id = reminders.add_reminder(&reminder_in(10)).await.unwrap();
+
+        assert!(reminders.add_subscriber(id, SUBSCRIBER).await.unwrap(), "{backend}");
+        assert!(!reminders.add_subscriber(id, SUBSCRIBER).await.unwrap(), "{backend}");
+        assert_eq!(reminders.get_user_reminders(SUBSCRIBER).await.unwrap().len(), 1, "{backend}");
+
+        assert!(!reminders.cancel_reminder(id, SUBSCRIBER).await.unwrap(), "{backend}");
+        assert!(reminders.cancel_reminder(id, OWNER).await.unwrap(), "{backend}");
+        assert!(reminders.get_reminder(id).await.unwrap().is_none(), "{backend}");
+        assert!(reminders.get_subscribers(id).await.unwrap().is_empty(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn retries_and_snoozes_move_the_fire_time() {
+    for (backend, storage) in backends().await {
+        let reminders = &storage.reminders;
+        let id = reminders.add_reminder(&reminder_in(-1)).await.unwrap();
+        assert!(reminders.get_reminder(id).await.unwrap().unwrap().is_due(), "{backend}");
+
+        let retry_at = Utc::now() + Duration::minutes(5);
+        reminders.record_failed_attempt(id, "rate limited", retry_at).await.unwrap();
+        let reminder = reminders.get_reminder(id).await.unwrap().unwrap();
+        assert_eq!(reminder.attempts, 1, "{backend}");
+        assert_eq!(reminder.fire_at().timestamp(), retry_at.timestamp(), "{backend}");
+        assert!(!reminder.is_due(), "{backend}");
+
+        reminders.mark_due(id).await.unwrap();
+        let until = Utc::now() + Duration::hours(1);
+        reminders.snooze(id, until).await.unwrap();
+        let reminder = reminders.get_reminder(id).await.unwrap().unwrap();
+        assert!(!reminder.sent, "{backend}");
+        assert_eq!(reminder.fire_at().timestamp(), until.timestamp(), "{backend}");
+
+        // acknowledging a one-off finishes it
+        reminders.acknowledge(id).await.unwrap();
+        let reminder = reminders.get_reminder(id).await.unwrap().unwrap();
+        assert!(reminder.sent && reminder.acknowledged && reminder.retry_at.is_none(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn purge_keeps_unsent_and_recent() {
+    for (backend, storage) in backends().await {
+        let reminders = &storage.reminders;
+        let old = reminders.add_reminder(&reminder_in(-120)).await.unwrap();
+        let recent = reminders.add_reminder(&reminder_in(-1)).await.unwrap();
+        let unsent = reminders.add_reminder(&reminder_in(-120)).await.unwrap();
+        reminders.mark_due(old).await.unwrap();
+        reminders.mark_due(recent).await.unwrap();
+
+        let purged = reminders.purge_sent(Utc::now() - Duration::minutes(60)).await.unwrap();
+        assert_eq!(purged, 1, "{backend}");
+        assert!(reminders.get_reminder(old).await.unwrap().is_none(), "{backend}");
+        assert!(reminders.get_reminder(recent).await.unwrap().is_some(), "{backend}");
+        assert!(reminders.get_reminder(unsent).await.unwrap().is_some(), "{backend}");
+    }
+}
+
+#[test]
+fn scheduler_pops_due_in_order_once() {
+    let scheduler = ReminderScheduler::new();
+    let now = Utc::now();
+
+    scheduler.schedule(ReminderId(3), now + Duration::minutes(10));
+    scheduler.schedule(ReminderId(2), now - Duration::minutes(1));
+    scheduler.schedule(ReminderId(1), now - Duration::minutes(5));
+    // moved reminders can be queued twice
+    scheduler.schedule(ReminderId(2), now);
+
+    assert_eq!(scheduler.pop_due(now), vec![ReminderId(1), ReminderId(2)]);
+    assert_eq!(scheduler.next_due(), Some(now + Duration::minutes(10)));
+    assert!(scheduler.pop_due(now).is_empty());
+}
+
+#[test]
+fn about_links_into_the_guild() {
+    // context menu and fetched messages come without a guild id
+    let message: Message = serde_json::from_value(fixtures::message(30, 10, None, fixtures::user(5, "author"), "hi")).unwrap();
+
+    let reminder = reminder_in(5).about(&message, Some(GuildId::new(1)));
+    assert_eq!(reminder.context_message_url.as_deref(), Some("https://discord.com/channels/1/10/30"));
+
+    let reminder = reminder_in(5).about(&message, None);
+    assert_eq!(reminder.context_message_url.as_deref(), Some("https://discord.com/channels/@me/10/30"));
+}
@@ -0,0 +1,277 @@
+use poise::serenity_prelude as serenity;
+use sqlx::sqlite::SqlitePoolOptions;
+
+use crate::helpers::starboard::{BlockMode, DEFAULT_BOARD, StarSource, StarboardConfig, StarredMessage, StatsScope};
+use crate::helpers::starboard_activity::{record_message, MAX_SCALE};
+use crate::helpers::starboard_manager::{post_action, PostAction};
+use crate::helpers::storage::Storage;
+use crate::tests::{backends, message_id_aged};
+
+const GUILD: u64 = 1;
+const CHANNEL: u64 = 10;
+const MESSAGE: u64 = 100;
+const COPY: u64 = 200;
+
+fn board(threshold: i64) -> StarboardConfig {
+    StarboardConfig {
+        threshold,
+        starboard_channel_id: Some("20".to_string()),
+        ..StarboardConfig::new(GUILD, "starboard")
+    }
+}
+
+fn posted(board_id: i64, stars: i64, starboard_message_id: serenity::MessageId) -> StarredMessage {
+    StarredMessage {
+        id: 0,
+        board_id,
+        original_message_id: MESSAGE.to_string(),
+        original_channel_id: CHANNEL.to_string(),
+        starboard_message_id: Some(starboard_message_id.get().to_string()),
+        starboard_channel_id: Some("20".to_string()),
+        stars,
+        starred_by: String::new(),
+        created_at: None,
+        author_id: Some("5".to_string()),
+        frozen: false,
+        forced: false,
+        famed: false,
+    }
+}
+
+#[tokio::test]
+async fn configs_round_trip() {
+    for (backend, storage) in backends().await {
+        let board_id = storage.starboard.update_starboard_config(&board(3)).await.unwrap();
+        // saving again by name keeps the id
+        let again = storage.starboard.update_starboard_config(&board(4)).await.unwrap();
+        assert_eq!(board_id, again, "{backend}");
+
+        let config = storage.starboard.get_starboard_config(GUILD, "starboard").await.unwrap().unwrap();
+        assert_eq!(config.threshold, 4, "{backend}");
+
+        storage.starboard.set_starboard_threshold(board_id, 6).await.unwrap();
+        let config = storage.starboard.get_starboard_config_by_id(board_id).await.unwrap().unwrap();
+        assert_eq!(config.threshold, 6, "{backend}");
+
+        storage.starboard.delete_starboard(board_id).await.unwrap();
+        assert!(storage.starboard.get_starboard_configs(GUILD).await.unwrap().is_empty(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn stars_on_original_and_copy_count_once() {
+    for (backend, storage) in backends().await {
+        let board_id = storage.starboard.update_starboard_config(&board(2)).await.unwrap();
+        let stars = &storage.starboard;
+
+        assert_eq!(stars.add_star_reaction(board_id, MESSAGE, CHANNEL, 1, StarSource::Original).await.unwrap(), 1, "{backend}");
+        assert_eq!(stars.add_star_reaction(board_id, MESSAGE, CHANNEL, 2, StarSource::Original).await.unwrap(), 2, "{backend}");
+        // the same user starring the copy is still one star
+        assert_eq!(stars.add_star_reaction(board_id, MESSAGE, CHANNEL, 1, StarSource::Copy).await.unwrap(), 2, "{backend}");
+
+        // and it only goes once both reactions are gone
+        assert_eq!(stars.remove_star_reaction(board_id, MESSAGE, 1, StarSource::Original).await.unwrap(), 2, "{backend}");
+        assert_eq!(stars.remove_star_reaction(board_id, MESSAGE, 1, StarSource::Copy).await.unwrap(), 1, "{backend}");
+
+        stars.add_star_reaction(board_id, MESSAGE, CHANNEL, 3, StarSource::Copy).await.unwrap();
+        stars.clear_copy_reactions(board_id, MESSAGE).await.unwrap();
+        assert_eq!(stars.count_star_reactions(board_id, MESSAGE).await.unwrap(), 1, "{backend}");
+
+        stars.replace_star_reactions(board_id, MESSAGE, CHANNEL, &[1, 2], &[2, 3]).await.unwrap();
+        assert_eq!(stars.count_star_reactions(board_id, MESSAGE).await.unwrap(), 3, "{backend}");
+        assert_eq!(stars.boards_tracking(MESSAGE).await.unwrap(), vec![board_id], "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn posts_are_found_by_original_and_copy() {
+    for (backend, storage) in backends().await {
+        let board_id = storage.starboard.update_starboard_config(&board(2)).await.unwrap();
+        let stars = &storage.starboard;
+
+        stars.add_starred_message(&posted(board_id, 2, serenity::MessageId::new(COPY))).await.unwrap();
+        // one entry per board and message
+        assert!(stars.add_starred_message(&posted(board_id, 2, serenity::MessageId::new(COPY))).await.is_err(), "{backend}");
+
+        let by_copy = stars.get_starred_message_by_copy(COPY).await.unwrap().unwrap();
+        assert_eq!(by_copy.original_message_id, MESSAGE.to_string(), "{backend}");
+
+        assert!(stars.set_frozen(board_id, MESSAGE, true).await.unwrap(), "{backend}");
+        assert!(!stars.set_frozen(board_id, MESSAGE + 1, true).await.unwrap(), "{backend}");
+        assert!(stars.get_starred_message(board_id, MESSAGE).await.unwrap().unwrap().frozen, "{backend}");
+
+        stars.forget_message(MESSAGE).await.unwrap();
+        assert!(stars.get_starred_messages(MESSAGE).await.unwrap().is_empty(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn blocks_and_blacklist() {
+    for (backend, storage) in backends().await {
+        let board_id = storage.starboard.update_starboard_config(&board(2)).await.unwrap();
+        let stars = &storage.starboard;
+
+        stars.block_user(board_id, 7, BlockMode::Stars).await.unwrap();
+        stars.block_user(board_id, 8, BlockMode::Messages).await.unwrap();
+        assert_eq!(stars.star_blocked_users(board_id).await.unwrap(), vec![7], "{backend}");
+        assert!(stars.unblock_user(board_id, 7).await.unwrap(), "{backend}");
+        assert!(!stars.unblock_user(board_id, 7).await.unwrap(), "{backend}");
+
+        stars.blacklist_message(board_id, MESSAGE).await.unwrap();
+        assert!(stars.is_blacklisted(board_id, MESSAGE).await.unwrap(), "{backend}");
+        assert!(stars.unblacklist_message(board_id, MESSAGE).await.unwrap(), "{backend}");
+        assert!(!stars.is_blacklisted(board_id, MESSAGE).await.unwrap(), "{backend}");
+    }
+}
+
+#[tokio::test]
+async fn stats_stay_in_the_guild() {
+    for (backend, storage) in backends().await {
+        let board_id = storage.starboard.update_starboard_config(&board(2)).await.unwrap();
+        let other_guild = storage.starboard
+            .update_starboard_config(&StarboardConfig::new(GUILD + 1, "starboard"))
+            .await
+            .unwrap();
+        let stars = &storage.starboard;
+
+        stars.add_starred_message(&posted(board_id, 4, serenity::MessageId::new(COPY))).await.unwrap();
+        stars.add_starred_message(&posted(other_guild, 9, serenity::MessageId::new(COPY + 1))).await.unwrap();
+        stars.add_star_reaction(board_id, MESSAGE, CHANNEL, 1, StarSource::Original).await.unwrap();
+
+        let scope = StatsScope { guild_id: GUILD.to_string(), board_id: None, since: None };
+        let top = stars.top_messages(&scope, 10).await.unwrap();
+        assert_eq!(top.len(), 1, "{backend}");
+        assert_eq!(top[0].stars, 4, "{backend}");
+
+        let receivers = stars.top_receivers(&scope, 10).await.unwrap();
+        assert_eq!((receivers[0].user_id.as_str(), receivers[0].stars), ("5", 4), "{backend}");
+        assert_eq!(stars.user_stats(&scope, 5).await.unwrap(), (4, 1, 0), "{backend}");
+        assert_eq!(stars.user_stats(&scope, 1).await.unwrap(), (0, 0, 1), "{backend}");
+        assert!(stars.random_starred_message(&scope).await.unwrap().is_some(), "{backend}");
+
+        // nothing was starred in the future
+        let future = StatsScope { since: Some("9999-01-01 00:00:00".to_string()), ..scope };
+        assert!(stars.top_messages(&future, 10).await.unwrap().is_empty(), "{backend}");
+        assert!(stars.top_givers(&future, 10).await.unwrap().is_empty(), "{backend}");
+    }
+}
+
+#[test]
+fn posts_once_the_threshold_is_reached() {
+    let config = board(3);
+    let channel = serenity::ChannelId::new(1_000);
+    let message = message_id_aged(0);
+
+    assert_eq!(post_action(&config, None, 2, channel, message), PostAction::Nothing);
+    assert_eq!(post_action(&config, None, 3, channel, message), PostAction::Post);
+}
+
+#[test]
+fn old_messages_are_not_posted() {
+    let config = StarboardConfig { max_age_days: Some(7), ..board(1) };
+    let channel = serenity::ChannelId::new(1_001);
+
+    assert_eq!(post_action(&config, None, 5, channel, message_id_aged(2)), PostAction::Post);
+    assert_eq!(post_action(&config, None, 5, channel, message_id_aged(30)), PostAction::Nothing);
+}
+
+#[test]
+fn posts_come_down_below_the_threshold() {
+    let config = board(3);
+    let channel = serenity::ChannelId::new(1_002);
+    let starred = posted(1, 3, message_id_aged(0));
+
+    assert_eq!(post_action(&config, Some(&starred), 4, channel, message_id_aged(0)), PostAction::Update);
+    assert_eq!(post_action(&config, Some(&starred), 2, channel, message_id_aged(0)), PostAction::Remove);
+}
+
+#[test]
+fn forced_frozen_and_kept_posts_stay_up() {
+    let config = StarboardConfig { keep_after_days: Some(7), ..board(3) };
+    let channel = serenity::ChannelId::new(1_003);
+    let message = message_id_aged(0);
+
+    let forced = StarredMessage { forced: true, ..posted(1, 3, message_id_aged(0)) };
+    assert_eq!(post_action(&config, Some(&forced), 0, channel, message), PostAction::Update);
+
+    let frozen = StarredMessage { frozen: true, ..posted(1, 3, message_id_aged(0)) };
+    assert_eq!(post_action(&config, Some(&frozen), 0, channel, message), PostAction::Nothing);
+    assert_eq!(post_action(&config, Some(&frozen), 10, channel, message), PostAction::Nothing);
+
+    let kept = posted(1, 3, message_id_aged(10));
+    assert_eq!(post_action(&config, Some(&kept), 0, channel, message), PostAction::Update);
+}
+
+#[test]
+fn busy_channels_need_more_stars() {
+    let config = StarboardConfig { activity_scale: Some(10), ..board(2) };
+    // activity is global, every test uses its own channel
+    let channel = serenity::ChannelId::new(1_004);
+    let message = message_id_aged(0);
+
+    for _ in 0..20 {
+        record_message(channel);
+    }
+    assert_eq!(post_action(&config, None, 3, channel, message), PostAction::Nothing);
+    assert_eq!(post_action(&config, None, 4, channel, message), PostAction::Post);
+
+    // scaling is capped
+    for _ in 0..1_000 {
+        record_message(channel);
+    }
+    assert_eq!(post_action(&config, None, 2 * MAX_SCALE, channel, message), PostAction::Post);
+
+    // it only raises the bar for new posts
+    let starred = posted(1, 2, message_id_aged(0));
+    assert_eq!(post_action(&config, Some(&starred), 2, channel, message), PostAction::Update);
+}
+
+#[tokio::test]
+async fn single_board_databases_migrate_per_guild() {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    // the tables as they were before boards had names
+    for table in [
+        "CREATE TABLE starboard_messages (id INTEGER PRIMARY KEY, original_message_id TEXT NOT NULL UNIQUE,
+            original_channel_id TEXT NOT NULL, starboard_message_id TEXT, starboard_channel_id TEXT,
+            stars INTEGER DEFAULT 1, starred_by TEXT NOT NULL, created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)",
+        "CREATE TABLE starboard_reactions (id INTEGER PRIMARY KEY, message_id TEXT NOT NULL, user_id TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, UNIQUE(message_id, user_id))",
+        "CREATE TABLE starboard_config (guild_id TEXT PRIMARY KEY, starboard_channel_id TEXT, threshold INTEGER DEFAULT 2,
+            star_emoji TEXT DEFAULT '⭐', self_star_allowed BOOLEAN DEFAULT FALSE, enabled BOOLEAN DEFAULT TRUE)",
+        "INSERT INTO starboard_config (guild_id, starboard_channel_id, threshold) VALUES ('1', '20', 2), ('2', '40', 3)",
+        // posted in the first guild
+        "INSERT INTO starboard_messages (original_message_id, original_channel_id, starboard_message_id, starboard_channel_id, stars, starred_by)
+            VALUES ('100', '10', '200', '20', 2, '7')",
+        "INSERT INTO starboard_reactions (message_id, user_id) VALUES ('100', '7'), ('100', '8')",
+        // below the threshold, one in each guild
+        "INSERT INTO starboard_reactions (message_id, user_id) VALUES ('101', '7'), ('301', '9'), ('301', '8')",
+    ] {
+        sqlx::query(table).execute(&pool).await.unwrap();
+    }
+
+    let storage = Storage::open(pool).await.unwrap();
+    let starboard = &storage.starboard;
+    let first = starboard.get_starboard_config(GUILD, DEFAULT_BOARD).await.unwrap().unwrap();
+    let second = starboard.get_starboard_config(GUILD + 1, DEFAULT_BOARD).await.unwrap().unwrap();
+    assert_eq!(second.threshold, 3);
+
+    let posted = starboard.get_starred_message(first.id, 100).await.unwrap().unwrap();
+    assert_eq!(posted.starboard_message_id.as_deref(), Some("200"));
+    assert_eq!(starboard.count_star_reactions(first.id, 100).await.unwrap(), 2);
+
+    // the next star on each message picks up the ones from before the migration
+    let count = starboard.add_star_reaction(second.id, 301, 30, 5, StarSource::Original).await.unwrap();
+    assert_eq!(count, 3);
+    let count = starboard.add_star_reaction(first.id, 101, 10, 5, StarSource::Original).await.unwrap();
+    assert_eq!(count, 2);
+
+    // and nothing ends up on the other guild's board
+    assert_eq!(starboard.count_star_reactions(first.id, 301).await.unwrap(), 0);
+    assert_eq!(starboard.count_star_reactions(second.id, 101).await.unwrap(), 0);
+    let count = starboard.add_star_reaction(first.id, 301, 30, 6, StarSource::Original).await.unwrap();
+    assert_eq!(count, 1);
+}
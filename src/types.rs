@@ -21,11 +21,13 @@ pub struct ColorRole {
 pub struct Data {
     pub db: SqlitePool,
     pub last_command_success: Arc<Mutex<bool>>,
-    pub reminders: crate::helpers::reminder::ReminderStore,
-    pub starboard: crate::helpers::starboard::Database,
-    pub starboard_lock: Mutex<()>,
+    pub reminders: Arc<dyn crate::helpers::storage::ReminderStorage>,
+    pub reminder_scheduler: Arc<crate::helpers::reminder_scheduler::ReminderScheduler>,
+    pub starboard: Arc<dyn crate::helpers::storage::StarboardStorage>,
+    pub starboard_state: Arc<crate::helpers::starboard_state::StarboardState>,
     pub http_client: Arc<serenity::Http>,
-    pub auth: Arc<crate::helpers::auth::AuthDatabase>
+    pub auth: Arc<dyn crate::helpers::storage::AuthStorage>,
+    pub role_colours: Arc<dyn crate::helpers::storage::RoleColourStorage>,
 }
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;